@@ -0,0 +1,10 @@
+# Two-tone paint, brightening toward the silhouette, with speckled flakes
+bsdf diffuse
+base = constant 0.5 0.02 0.03
+edge_color = constant 0.9 0.35 0.1
+edge = layer_weight 0.35
+paint = mix base edge_color edge
+flakes = worley cell 0.5
+sparkle = power flakes 8
+speckled = add paint sparkle
+color speckled
@@ -111,6 +111,18 @@ impl Aabb {
         true
     }
 
+    pub fn centroid(&self) -> Point3 {
+        Point3::new(
+            (self.x.min + self.x.max) / 2.0,
+            (self.y.min + self.y.max) / 2.0,
+            (self.z.min + self.z.max) / 2.0,
+        )
+    }
+
+    pub fn diagonal(&self) -> Vec3 {
+        Vec3::new(self.x.size(), self.y.size(), self.z.size())
+    }
+
     pub fn longest_axis(&self) -> u8 {
         if self.x.size() > self.y.size() {
             if self.x.size() > self.z.size() {
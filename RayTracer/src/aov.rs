@@ -0,0 +1,136 @@
+use crate::{
+    color::Color,
+    vec3::{Point3, Vec3},
+};
+use image::{DynamicImage, ImageFormat, Rgb, Rgb32FImage};
+
+/// Auxiliary values of one camera sample, taken at its first hit.
+#[derive(Clone, Copy, Default)]
+pub struct AovSample {
+    pub albedo: Color,
+    pub normal: Vec3,
+    /// distance along the viewing direction, zero when nothing was hit
+    pub depth: f64,
+    pub position: Point3,
+    /// index of the top-level object hit, `None` when nothing was hit
+    pub object_id: Option<u32>,
+    /// number of the material hit within the world, `None` when nothing was hit
+    pub material_id: Option<u32>,
+    /// radiance from emitters reached in at most one bounce, and the rest
+    pub direct: Color,
+    pub indirect: Color,
+}
+
+impl AovSample {
+    /// Adds up the continuous values; the IDs of `self` are kept if set.
+    pub fn accumulate(&mut self, other: &Self) {
+        self.albedo += other.albedo;
+        self.normal += other.normal;
+        self.depth += other.depth;
+        self.position += other.position;
+        self.direct += other.direct;
+        self.indirect += other.indirect;
+        if self.object_id.is_none() {
+            self.object_id = other.object_id;
+        }
+        if self.material_id.is_none() {
+            self.material_id = other.material_id;
+        }
+    }
+}
+
+/// Reads one pass out of a sample.
+type Pass = fn(&AovSample) -> Color;
+
+/// Per pixel sums of `AovSample`s. The IDs can't be averaged, so the first
+/// sample of a pixel decides them.
+pub struct AovBuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<AovSample>,
+}
+
+impl AovBuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![AovSample::default(); (width * height) as usize],
+        }
+    }
+
+    pub fn add_sample(&mut self, i: u32, j: u32, sample: &AovSample) {
+        self.pixels[(j * self.width + i) as usize].accumulate(sample);
+    }
+
+    /// Averages of the sums, `scale` being one over the number of samples
+    /// taken per pixel.
+    pub fn resolve(&self, scale: f64) -> Vec<AovSample> {
+        self.pixels
+            .iter()
+            .map(|pixel| AovSample {
+                albedo: pixel.albedo * scale,
+                normal: pixel.normal * scale,
+                depth: pixel.depth * scale,
+                position: pixel.position * scale,
+                direct: pixel.direct * scale,
+                indirect: pixel.indirect * scale,
+                ..*pixel
+            })
+            .collect()
+    }
+
+    /// Writes every pass as a float OpenEXR image named `<prefix>.<pass>.exr`.
+    /// IDs are written as false colors. The lighting split is only written
+    /// when the integrator filled it in.
+    pub fn write(&self, prefix: &str, scale: f64, with_lighting: bool) {
+        let pixels = self.resolve(scale);
+        let mut passes: Vec<(&str, Pass)> = vec![
+            ("albedo", |s| s.albedo),
+            ("normal", |s| s.normal),
+            ("depth", |s| Color::new(s.depth, s.depth, s.depth)),
+            ("position", |s| s.position),
+            ("object_id", |s| {
+                s.object_id.map_or(Color::zeros(), |id| id_color(id as u64))
+            }),
+            ("material_id", |s| {
+                s.material_id
+                    .map_or(Color::zeros(), |id| id_color(id as u64))
+            }),
+        ];
+        if with_lighting {
+            passes.push(("direct", |s| s.direct));
+            passes.push(("indirect", |s| s.indirect));
+        }
+
+        for (name, pass) in passes {
+            let img = Rgb32FImage::from_fn(self.width, self.height, |i, j| {
+                let c = pass(&pixels[(j * self.width + i) as usize]);
+                Rgb([c.x as f32, c.y as f32, c.z as f32])
+            });
+            let path = format!("{}.{}.exr", prefix, name);
+            match DynamicImage::ImageRgb32F(img).save_with_format(&path, ImageFormat::OpenExr) {
+                Ok(_) => {
+                    println!("Ouput {} pass as \"{}\"", name, path);
+                }
+                Err(_) => {
+                    eprintln!("Outputting {} pass failed", name);
+                }
+            }
+        }
+    }
+}
+
+/// A color that tells neighbouring IDs apart.
+fn id_color(id: u64) -> Color {
+    // splitmix64 finalizer
+    let mut z = id.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^= z >> 31;
+    Color::new(
+        (z & 0xffff) as f64 / 65535.0,
+        ((z >> 16) & 0xffff) as f64 / 65535.0,
+        ((z >> 32) & 0xffff) as f64 / 65535.0,
+    )
+}
@@ -0,0 +1,199 @@
+use crate::{rtw_image::RtwImage, rtweekend, vec3::Vec3};
+use std::{f64::consts::PI, sync::Arc};
+
+/// Shape of the lens opening, which out of focus highlights (bokeh) take on.
+/// Shapes are given within the unit disk and scaled by the lens radius.
+#[derive(Clone)]
+pub enum Aperture {
+    Circle,
+    /// regular polygon with its corners on the unit circle, like the iris
+    /// formed by `blades` diaphragm blades
+    Polygon {
+        blades: u32,
+        rotation: f64,
+    },
+    /// the bright pixels of an image stretched over [-1, 1]^2
+    Image {
+        width: u32,
+        height: u32,
+        /// the bright pixels, so sampling needs no rejection
+        open_pixels: Arc<[(u32, u32)]>,
+    },
+}
+
+impl Aperture {
+    pub fn polygon(blades: u32, rotation_deg: f64) -> Self {
+        Self::Polygon {
+            blades: blades.max(3),
+            rotation: rotation_deg.to_radians(),
+        }
+    }
+
+    /// Loads a mask from the assets directory, falling back to a circle when
+    /// it can't be read or has no bright pixels.
+    pub fn from_image(filename: &str) -> Self {
+        let mask = RtwImage::open(filename);
+        let (width, height) = (mask.width(), mask.height());
+        let open_pixels: Arc<[(u32, u32)]> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| is_open(&mask, x, y))
+            .collect();
+        if open_pixels.is_empty() {
+            return Self::Circle;
+        }
+
+        Self::Image {
+            width,
+            height,
+            open_pixels,
+        }
+    }
+
+    /// Area of the shape at unit lens radius.
+    pub fn area(&self) -> f64 {
+        match self {
+            Self::Circle => PI,
+            Self::Polygon { blades, .. } => {
+                let n = *blades as f64;
+                0.5 * n * (2.0 * PI / n).sin()
+            }
+            Self::Image {
+                width,
+                height,
+                open_pixels,
+            } => 4.0 * open_pixels.len() as f64 / (width * height) as f64,
+        }
+    }
+
+    /// A uniformly distributed point of the shape, with z = 0.
+    pub fn sample(&self) -> Vec3 {
+        match self {
+            Self::Circle => Vec3::random_in_unit_disk(),
+            Self::Polygon { blades, rotation } => {
+                // the triangles between the center and every edge are equally likely
+                let step = 2.0 * PI / *blades as f64;
+                let k = rtweekend::random_int_in_range(0, *blades as i32) as f64;
+                let corner = |angle: f64| Vec3::new(angle.cos(), angle.sin(), 0.0);
+                let a = corner(rotation + k * step);
+                let b = corner(rotation + (k + 1.0) * step);
+
+                let (mut s, mut t) = (rtweekend::random_double(), rtweekend::random_double());
+                if s + t > 1.0 {
+                    (s, t) = (1.0 - s, 1.0 - t);
+                }
+                a * s + b * t
+            }
+            Self::Image {
+                width,
+                height,
+                open_pixels,
+            } => {
+                let k = rtweekend::random_int_in_range(0, open_pixels.len() as i32);
+                let (px, py) = open_pixels[k as usize];
+                let x = (px as f64 + rtweekend::random_double()) / *width as f64;
+                let y = (py as f64 + rtweekend::random_double()) / *height as f64;
+                Vec3::new(2.0 * x - 1.0, 1.0 - 2.0 * y, 0.0)
+            }
+        }
+    }
+}
+
+fn is_open(mask: &RtwImage, x: u32, y: u32) -> bool {
+    let [r, g, b] = mask.pixel_data(x, y);
+    (r as u32 + g as u32 + b as u32) > 3 * 127
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether (`x`, `y`) of [-1, 1]^2 lies within `aperture`, `mask` being
+    /// the image of image apertures.
+    fn contains(aperture: &Aperture, mask: &RtwImage, x: f64, y: f64) -> bool {
+        match aperture {
+            Aperture::Circle => x * x + y * y <= 1.0,
+            Aperture::Polygon { blades, rotation } => {
+                // within the half plane of every edge
+                let step = 2.0 * PI / *blades as f64;
+                (0..*blades).all(|k| {
+                    let middle = rotation + (k as f64 + 0.5) * step;
+                    x * middle.cos() + y * middle.sin() <= (step / 2.0).cos() + 1e-12
+                })
+            }
+            Aperture::Image { width, height, .. } => {
+                let px = ((x + 1.0) / 2.0 * *width as f64) as u32;
+                let py = ((1.0 - y) / 2.0 * *height as f64) as u32;
+                is_open(mask, px, py)
+            }
+        }
+    }
+
+    fn apertures() -> Vec<Aperture> {
+        vec![
+            Aperture::Circle,
+            Aperture::polygon(5, 10.0),
+            Aperture::polygon(6, 0.0),
+            Aperture::from_image("apertures/star.png"),
+        ]
+    }
+
+    #[test]
+    fn test_area() {
+        assert!((Aperture::polygon(4, 0.0).area() - 2.0).abs() < 1e-12);
+        assert!((Aperture::polygon(6, 0.0).area() - 1.5 * 3.0_f64.sqrt()).abs() < 1e-12);
+
+        // the mask is a five pointed star with its inner corners at 0.45
+        let star = Aperture::from_image("apertures/star.png");
+        assert!(matches!(star, Aperture::Image { .. }));
+        assert!((star.area() - 5.0 * 0.45 * (PI / 5.0).sin()).abs() < 0.01);
+
+        // against the fraction of a grid of points within each shape
+        const N: usize = 200;
+        let mask = RtwImage::open("apertures/star.png");
+        for aperture in apertures() {
+            let inside = (0..N * N)
+                .filter(|k| {
+                    let x = 2.0 * ((k % N) as f64 + 0.5) / N as f64 - 1.0;
+                    let y = 2.0 * ((k / N) as f64 + 0.5) / N as f64 - 1.0;
+                    contains(&aperture, &mask, x, y)
+                })
+                .count();
+            let area = 4.0 * inside as f64 / (N * N) as f64;
+            assert!((aperture.area() - area).abs() < 0.02 * area);
+        }
+    }
+
+    #[test]
+    fn test_samples_are_uniform() {
+        const N: usize = 100_000;
+        const BINS: usize = 4;
+        let bin = |p: f64| (((p + 1.0) / 2.0 * BINS as f64) as usize).min(BINS - 1);
+        let mask = RtwImage::open("apertures/star.png");
+
+        for aperture in apertures() {
+            // every sample within the shape, and as many in every part of the
+            // unit square as its share of the area
+            let mut counts = [[0; BINS]; BINS];
+            for _ in 0..N {
+                let p = aperture.sample();
+                assert!(p.z == 0.0 && contains(&aperture, &mask, p.x, p.y));
+                counts[bin(p.y)][bin(p.x)] += 1;
+            }
+
+            let mut expected = [[0.0; BINS]; BINS];
+            const GRID: usize = 256;
+            for k in 0..GRID * GRID {
+                let x = 2.0 * ((k % GRID) as f64 + 0.5) / GRID as f64 - 1.0;
+                let y = 2.0 * ((k / GRID) as f64 + 0.5) / GRID as f64 - 1.0;
+                if contains(&aperture, &mask, x, y) {
+                    expected[bin(y)][bin(x)] += 1.0;
+                }
+            }
+            let total: f64 = expected.iter().flatten().sum();
+            for (count, expected) in counts.iter().flatten().zip(expected.iter().flatten()) {
+                let frequency = *count as f64 / N as f64;
+                assert!((frequency - expected / total).abs() < 0.01);
+            }
+        }
+    }
+}
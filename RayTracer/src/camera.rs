@@ -1,17 +1,27 @@
 use crate::{
-    color::{self, Color},
+    aov::{AovBuffer, AovSample},
+    aperture::Aperture,
+    camera_path::{CameraPath, CameraView},
+    color::Color,
+    color_space::OutputTransform,
+    denoise,
+    film::{self, Film},
+    filter::{BoxFilter, Filter, GaussianFilter, LanczosFilter, MitchellFilter, TentFilter},
     hittable::{HitRecord, Hittable, HittableList},
+    integrator::{Bdpt, PathTracer, SpectralPath, Sppm},
     interval::Interval,
-    pdf::{CosinePdf, HittablePdf, Pdf},
-    ray::Ray,
+    lens_system::LensSystem,
+    light::{LightBvh, LightList, LightSampler, PowerLightSampler},
+    physical_camera::PhysicalCamera,
+    projection::Projection,
+    ray::{Ray, RayDifferentials},
     rtweekend,
+    stereo::{StereoLayout, StereoRig},
     vec3::{Point3, Vec3},
 };
-use clap;
 use image::{DynamicImage, ImageFormat};
 use indicatif::{ProgressBar, ProgressStyle};
 use std::{
-    f64::INFINITY,
     fs::File,
     sync::{Arc, Mutex},
     thread,
@@ -30,27 +40,70 @@ pub struct Camera {
     pixel00_loc: Point3,
     pixel_delta_u: Vec3,
     pixel_delta_v: Vec3,
-    defocus_angle: f64,
+    /// radius of the thin lens, fixed as the camera moves and refocuses
+    lens_radius: f64,
     defocus_disk_u: Vec3,
     defocus_disk_v: Vec3,
+    aperture: Aperture,
+    projection: Projection,
+    /// traced through instead of the thin lens when set
+    lens: Option<Arc<LensSystem>>,
+    /// renders both eyes into one image when set
+    stereo: Option<StereoRig>,
+    /// factor applied to the radiance reaching the film
+    exposure: f64,
+    /// time the shutter opens, the frame number in an animation; it stays
+    /// open for one unit of time
+    shutter_open: f64,
+    anamorphic_squeeze: f64,
+    vup: Vec3,
+    view: CameraView,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+/// A point on the lens seen from a point in the scene, for connecting light
+/// paths to the camera.
+#[derive(Default)]
+pub struct ImportanceSample {
+    /// unit direction from the point towards the lens
+    pub wi: Vec3,
+    pub distance: f64,
+    pub lens_point: Point3,
+    pub we: f64,
+    pub pdf: f64,
+    pub i: u32,
+    pub j: u32,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum IntegratorKind {
+    Path,
+    SpectralPath,
+    Bdpt,
+    Sppm,
+}
+
+/// What every camera is built with, however its lens is described.
+#[derive(Clone, Copy)]
+pub struct CameraParams {
+    pub aspect_ratio: f64,
+    pub image_width: u32,
+    pub samples_per_pixel: u32,
+    pub max_depth: u32,
+    pub background: Color,
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vec3,
 }
 
 impl Camera {
-    pub fn new(
-        aspect_ratio: f64,
-        image_width: u32,
-        samples_per_pixel: u32,
-        max_depth: u32,
-        background: &Color,
-        vfov: f64,
-        lookfrom: &Point3,
-        lookat: &Point3,
-        vup: &Vec3,
-        defocus_angle: f64,
-        focus_dist: f64,
-    ) -> Self {
+    /// A thin lens camera, `defocus_angle` being the angle the lens subtends
+    /// at the focus distance.
+    pub fn new(params: &CameraParams, vfov: f64, defocus_angle: f64, focus_dist: f64) -> Self {
         let image_height = {
-            let height = image_width as f64 / aspect_ratio;
+            let height = params.image_width as f64 / params.aspect_ratio;
             if height < 1.0 {
                 1
             } else {
@@ -58,115 +111,390 @@ impl Camera {
             }
         };
 
-        let sqrt_spp = (samples_per_pixel as f64).sqrt() as u32;
+        let sqrt_spp = (params.samples_per_pixel as f64).sqrt() as u32;
         let pixel_samples_scale = 1.0 / sqrt_spp.pow(2) as f64;
         let recip_sqrt_spp = 1.0 / sqrt_spp as f64;
 
-        let center = *lookfrom;
+        let mut camera = Self {
+            image_width: params.image_width,
+            image_height,
+            sqrt_spp,
+            pixel_samples_scale,
+            recip_sqrt_spp,
+            max_depth: params.max_depth,
+            background: params.background,
+            center: Point3::zeros(),
+            pixel00_loc: Point3::zeros(),
+            pixel_delta_u: Vec3::zeros(),
+            pixel_delta_v: Vec3::zeros(),
+            lens_radius: focus_dist * (defocus_angle / 2.0).to_radians().tan(),
+            defocus_disk_u: Vec3::zeros(),
+            defocus_disk_v: Vec3::zeros(),
+            aperture: Aperture::Circle,
+            projection: Projection::Perspective,
+            lens: None,
+            stereo: None,
+            exposure: 1.0,
+            shutter_open: 0.0,
+            anamorphic_squeeze: 1.0,
+            vup: params.vup,
+            view: CameraView {
+                lookfrom: params.lookfrom,
+                lookat: params.lookat,
+                vfov,
+                focus_dist,
+            },
+            u: Vec3::zeros(),
+            v: Vec3::zeros(),
+            w: Vec3::zeros(),
+        };
+        let view = camera.view;
+        camera.set_view(&view);
+        camera
+    }
+
+    /// A camera set up from focal length, f-number, sensor size and exposure
+    /// settings instead of a field of view and defocus angle.
+    pub fn new_physical(params: &CameraParams, physical: &PhysicalCamera) -> Self {
+        let mut camera = Self::new(
+            params,
+            physical.vfov(params.aspect_ratio),
+            0.0,
+            physical.focus_dist,
+        );
+        camera.lens_radius = physical.lens_radius();
+        camera.anamorphic_squeeze = physical.anamorphic_squeeze;
+        camera.aperture = physical.aperture.clone();
+        camera.exposure = physical.exposure();
+        let view = camera.view;
+        camera.set_view(&view);
+        camera
+    }
+
+    /// A camera looking through a lens system, which is already focused.
+    pub fn new_realistic(params: &CameraParams, lens: &Arc<LensSystem>) -> Self {
+        let mut camera = Self::new(params, lens.vfov(), 0.0, 10.0);
+        camera.lens = Some(lens.clone());
+        camera
+    }
+
+    pub fn set_projection(&mut self, projection: &Projection) {
+        self.projection = *projection;
+    }
+
+    /// Makes the camera render a left and a right eye, packed into an image
+    /// twice as wide or tall as the view of one eye.
+    pub fn set_stereo(&mut self, stereo: &StereoRig) {
+        self.stereo = Some(*stereo);
+    }
+
+    /// Moves the camera to `view`, keeping its image, lens and exposure
+    /// settings. The lens keeps its radius, so the defocus angle shrinks as the
+    /// focus distance grows, like it does refocusing a real lens. Lens systems
+    /// keep their own field of view and focus.
+    pub fn set_view(&mut self, view: &CameraView) {
+        self.view = *view;
+        self.center = view.lookfrom;
 
-        let theta = vfov.to_radians();
+        let theta = view.vfov.to_radians();
         let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h * focus_dist;
-        let viewport_width = viewport_height * image_width as f64 / image_height as f64;
+        let viewport_height = 2.0 * h * view.focus_dist;
+        let viewport_width = viewport_height * self.image_width as f64 / self.image_height as f64;
 
-        let w = (*lookfrom - *lookat).unit();
-        let u = vup.cross(&w).unit();
-        let v = w.cross(&u);
+        self.w = (view.lookfrom - view.lookat).unit();
+        self.u = self.vup.cross(&self.w).unit();
+        self.v = self.w.cross(&self.u);
 
-        let viewport_u = u * viewport_width;
-        let viewport_v = -v * viewport_height;
+        let viewport_u = self.u * viewport_width;
+        let viewport_v = -self.v * viewport_height;
 
-        let pixel_delta_u = viewport_u / image_width as f64;
-        let pixel_delta_v = viewport_v / image_height as f64;
+        self.pixel_delta_u = viewport_u / self.image_width as f64;
+        self.pixel_delta_v = viewport_v / self.image_height as f64;
 
-        let viewport_upper_left = center - w * focus_dist - viewport_u / 2.0 - viewport_v / 2.0;
-        let pixel00_loc = viewport_upper_left + (pixel_delta_u + pixel_delta_v) * 0.5;
+        let viewport_upper_left =
+            self.center - self.w * view.focus_dist - viewport_u / 2.0 - viewport_v / 2.0;
+        self.pixel00_loc = viewport_upper_left + (self.pixel_delta_u + self.pixel_delta_v) * 0.5;
 
-        let defocus_radius = focus_dist * (defocus_angle / 2.0).to_radians().tan();
-        let defocus_disk_u = u * defocus_radius;
-        let defocus_disk_v = v * defocus_radius;
+        // an anamorphic lens squeezes the aperture horizontally
+        self.defocus_disk_u = self.u * self.lens_radius / self.anamorphic_squeeze;
+        self.defocus_disk_v = self.v * self.lens_radius;
+    }
 
-        Self {
-            image_width,
-            image_height,
-            sqrt_spp,
-            pixel_samples_scale,
-            recip_sqrt_spp,
-            max_depth,
-            background: *background,
-            center,
-            pixel00_loc,
-            pixel_delta_u,
-            pixel_delta_v,
-            defocus_angle,
-            defocus_disk_u,
-            defocus_disk_v,
+    /// Renders `world` as set up by the command line `matches`, either one
+    /// image or, for a turntable or a camera path file, the numbered images
+    /// NAME.0000.png, NAME.0001.png, ... of its frames, moving the camera
+    /// between frames. The scene, light sampler and integrators are set up
+    /// once for all of them.
+    pub fn render(&self, world: &HittableList, lights: &LightList, matches: &clap::ArgMatches) {
+        let name = String::from("output/") + matches.get_one::<String>("NAME").unwrap();
+
+        let mut camera = self.clone();
+        if let Some(projection) = matches.get_one::<String>("projection") {
+            // by default as tall as the perspective view at the look-at point
+            let view_height = 2.0
+                * (self.view.vfov.to_radians() / 2.0).tan()
+                * (self.view.lookat - self.view.lookfrom).length();
+            camera.set_projection(&match projection.as_str() {
+                "orthographic" => Projection::Orthographic {
+                    height: matches
+                        .get_one::<f64>("ortho-height")
+                        .copied()
+                        .unwrap_or(view_height),
+                },
+                "equirectangular" => Projection::Equirectangular,
+                "cubemap" => Projection::CubeMap,
+                "fisheye" => Projection::Fisheye {
+                    fov: *matches.get_one::<f64>("fisheye-fov").unwrap(),
+                },
+                _ => Projection::Perspective,
+            });
         }
-    }
+        if let Some(shape) = matches.get_one::<String>("aperture") {
+            camera.aperture = match (shape.as_str(), shape.parse::<u32>()) {
+                ("circle", _) => Aperture::Circle,
+                (_, Ok(blades)) => Aperture::polygon(blades, 0.0),
+                (filename, _) => Aperture::from_image(filename),
+            };
+        }
+        if let Some(interocular) = matches.get_one::<f64>("stereo") {
+            let convergence = matches
+                .get_one::<f64>("convergence")
+                .copied()
+                .unwrap_or((self.view.lookat - self.view.lookfrom).length());
+            // panoramas are stacked, flat views put side by side
+            let layout = match self.projection {
+                Projection::Perspective | Projection::Orthographic { .. } => {
+                    StereoLayout::SideBySide
+                }
+                _ => StereoLayout::OverUnder,
+            };
+            camera.set_stereo(&StereoRig::new(*interocular, convergence, layout));
+        }
+
+        let light_sampler: Arc<dyn LightSampler> =
+            match matches.get_one::<String>("light-sampler").unwrap().as_str() {
+                "power" => Arc::new(PowerLightSampler::new(lights, &world.bounding_box())),
+                _ => Arc::new(LightBvh::new(lights)),
+            };
+        let mut integrator = match matches.get_one::<String>("integrator").unwrap().as_str() {
+            "bdpt" => IntegratorKind::Bdpt,
+            "sppm" => IntegratorKind::Sppm,
+            _ => IntegratorKind::Path,
+        };
+        if integrator == IntegratorKind::Bdpt
+            && (camera.projection != Projection::Perspective
+                || camera.lens.is_some()
+                || camera.stereo.is_some())
+        {
+            // light subpaths are connected to the thin lens of a perspective camera
+            eprintln!(
+                "The bdpt integrator needs a perspective thin lens camera, using path instead"
+            );
+            integrator = IntegratorKind::Path;
+        }
+        if matches.get_flag("spectral") {
+            if integrator == IntegratorKind::Path {
+                integrator = IntegratorKind::SpectralPath;
+            } else {
+                eprintln!("Only the path integrator has a spectral mode, rendering in RGB");
+            }
+        }
+
+        let radius = matches.get_one::<f64>("filter-radius").copied();
+        let filter: Arc<dyn Filter> = match matches.get_one::<String>("filter").unwrap().as_str() {
+            "tent" => Arc::new(TentFilter::new(radius.unwrap_or(1.0))),
+            "gaussian" => Arc::new(GaussianFilter::new(radius.unwrap_or(1.5), 2.0)),
+            "mitchell" => Arc::new(MitchellFilter::new(
+                radius.unwrap_or(2.0),
+                1.0 / 3.0,
+                1.0 / 3.0,
+            )),
+            "lanczos" => Arc::new(LanczosFilter::new(radius.unwrap_or(3.0), 3.0)),
+            _ => Arc::new(BoxFilter::new(radius.unwrap_or(0.5))),
+        };
 
-    pub fn render(&self, world: &HittableList/*, lights: &Arc<dyn Hittable>*/) {
-        let matches = clap::command!()
-            .arg(
-                clap::arg!(-o <NAME>)
-                    .help("image filename without extension name")
-                    .value_parser(clap::value_parser!(String))
-                    .default_value("output"),
-            )
-            .get_matches();
-        let path = String::from("output/") + matches.get_one::<String>("NAME").unwrap() + ".png";
-        let mut output_file = File::create(&path).unwrap();
+        let output_transform = match matches
+            .get_one::<String>("output-transform")
+            .unwrap()
+            .as_str()
+        {
+            "rec709" => OutputTransform::Rec709,
+            "gamma2" => OutputTransform::Gamma2,
+            "linear" => OutputTransform::Linear,
+            _ => OutputTransform::Srgb,
+        };
 
+        let settings = RenderSettings {
+            integrator,
+            filter,
+            output_transform,
+            write_aovs: matches.get_flag("aov"),
+            denoise: matches.get_flag("denoise"),
+            keep_noisy: matches.get_flag("keep-noisy"),
+            split_eyes: matches.get_flag("split-eyes"),
+        };
+
+        let sppm = if integrator == IntegratorKind::Sppm {
+            if settings.write_aovs || settings.denoise {
+                eprintln!("AOVs and denoising are not supported by the sppm integrator");
+            }
+            Some(Sppm::new(
+                lights,
+                &world.bounding_box(),
+                *matches.get_one::<usize>("photons").unwrap(),
+                *matches.get_one::<f64>("photon-radius").unwrap(),
+            ))
+        } else {
+            None
+        };
+        let scene = Scene {
+            world: Arc::new(world.clone()),
+            light_sampler,
+            bdpt: Arc::new(Bdpt::new(lights, &world.bounding_box())),
+            path: Arc::new(PathTracer::new()),
+            spectral: Arc::new(SpectralPath::new()),
+            sppm,
+        };
+
+        let path = match (
+            matches.get_one::<u32>("turntable"),
+            matches.get_one::<String>("camera-path"),
+        ) {
+            (Some(frames), _) => Some(CameraPath::turntable(&camera.view, *frames)),
+            (None, Some(filename)) => Some(CameraPath::load(filename)),
+            (None, None) => None,
+        };
+        let Some(path) = path else {
+            camera.render_frame(&scene, &settings, &name);
+            return;
+        };
+        if path.is_empty() {
+            eprintln!("The camera path has no keyframes");
+            return;
+        }
+
+        let (first, last) = path.frame_range();
+        let frame_start = matches
+            .get_one::<i64>("frame-start")
+            .copied()
+            .unwrap_or(first.ceil() as i64);
+        let frame_end = matches
+            .get_one::<i64>("frame-end")
+            .copied()
+            .unwrap_or(last.floor() as i64);
+        for frame in frame_start..=frame_end {
+            println!("Frame {} of {}..={}", frame, frame_start, frame_end);
+            let mut frame_camera = camera.clone();
+            frame_camera.set_view(&path.view_at(frame as f64));
+            frame_camera.shutter_open = frame as f64;
+            frame_camera.render_frame(&scene, &settings, &format!("{}.{:04}", name, frame));
+        }
+    }
+
+    /// Renders one image of `scene` as `name`.png, plus its AOVs and noisy
+    /// version when asked for.
+    fn render_frame(&self, scene: &Scene, settings: &RenderSettings, name: &str) {
+        let (width, height) = self.film_size();
         let self_clone = Arc::new(self.clone());
-        let world = Arc::new(world.clone());
-        // let lights = Arc::new(lights.clone());
-        let img = Arc::new(Mutex::new(DynamicImage::new_rgb8(
-            self.image_width,
-            self.image_height,
-        )));
-
-        let bar = Arc::new(
-            ProgressBar::new((self.image_height * self.image_width) as u64).with_style(
-                ProgressStyle::with_template("[{elapsed_precise}] {bar:80} {percent}%").unwrap(),
-            ),
-        );
+        let integrator = settings.integrator;
+        let write_aovs = settings.write_aovs;
+        let denoise = settings.denoise;
+        let collect_aovs = write_aovs || denoise;
+
+        if let Some(sppm) = &scene.sppm {
+            // one estimate per pixel, at its center
+            let filter: Arc<dyn Filter> = Arc::new(BoxFilter::default());
+            let mut film = Film::new(width, height, &filter);
+            sppm.render(
+                self,
+                &scene.world,
+                scene.light_sampler.as_ref(),
+                self.sqrt_spp,
+                &mut film,
+            );
+            self.write_pixels(&self.expose(&film, 1.0), name, settings);
+            return;
+        }
+
+        let filter = settings.filter.clone();
+        let film = Arc::new(Mutex::new(Film::new(width, height, &filter)));
+        let aovs = Arc::new(Mutex::new(AovBuffer::new(width, height)));
+
+        let bar = Arc::new(ProgressBar::new((height * width) as u64).with_style(
+            ProgressStyle::with_template("[{elapsed_precise}] {bar:80} {percent}%").unwrap(),
+        ));
 
         let threads_num = thread::available_parallelism().unwrap().get();
         let mut render_threads = Vec::new();
         for thread_ind in 0..threads_num {
             let self_clone = self_clone.clone();
-            let world = world.clone();
-            // let lights = lights.clone();
-            let img = img.clone();
+            let world = scene.world.clone();
+            let light_sampler = scene.light_sampler.clone();
+            let bdpt = scene.bdpt.clone();
+            let path = scene.path.clone();
+            let spectral = scene.spectral.clone();
+            let film = film.clone();
+            let filter = filter.clone();
+            let aovs = aovs.clone();
             let bar = bar.clone();
             let render_thread = thread::spawn(move || {
-                for j in 0..self_clone.image_height {
-                    for i in 0..self_clone.image_width {
-                        if (j * self_clone.image_width + i) % threads_num as u32
-                            == thread_ind as u32
-                        {
-                            let mut pixel_color = Color::zeros();
+                // samples and BDPT splats may land on pixels of other threads,
+                // so they are merged into `film` at the end
+                let mut thread_film = Film::new(width, height, &filter);
+                for j in 0..height {
+                    for i in 0..width {
+                        if (j * width + i) % threads_num as u32 == thread_ind as u32 {
+                            let mut pixel_aov = AovSample::default();
                             for s_j in 0..self_clone.sqrt_spp {
                                 for s_i in 0..self_clone.sqrt_spp {
-                                    let r = self_clone.get_ray(i, j, s_i, s_j);
-                                    pixel_color += self_clone.ray_color(
-                                        &r,
-                                        self_clone.max_depth,
-                                        &world,
-                                        // &lights,
-                                    );
+                                    let (x, y) = self_clone.film_sample(i, j, s_i, s_j);
+                                    // black where the projection has no ray
+                                    let mut weight = 1.0;
+                                    let Some(r) = self_clone.ray_through(x, y, &mut weight) else {
+                                        thread_film.add_sample(x, y, &Color::zeros());
+                                        continue;
+                                    };
+                                    let mut direct = Color::zeros();
+                                    let color = match integrator {
+                                        IntegratorKind::Path => path.li(
+                                            &self_clone,
+                                            &r,
+                                            &world,
+                                            light_sampler.as_ref(),
+                                            &mut direct,
+                                        ),
+                                        IntegratorKind::SpectralPath => spectral.li(
+                                            &self_clone,
+                                            &r,
+                                            &world,
+                                            light_sampler.as_ref(),
+                                            &mut direct,
+                                        ),
+                                        IntegratorKind::Bdpt => {
+                                            bdpt.li(&self_clone, &r, &world, &mut thread_film)
+                                        }
+                                        IntegratorKind::Sppm => unreachable!(),
+                                    } * weight;
+                                    direct *= weight;
+                                    thread_film.add_sample(x, y, &color);
+
+                                    if collect_aovs {
+                                        let mut aov = self_clone.first_hit_aov(&r, &world);
+                                        aov.direct = direct * self_clone.exposure;
+                                        aov.indirect = (color - direct) * self_clone.exposure;
+                                        pixel_aov.accumulate(&aov);
+                                    }
                                 }
                             }
-                            color::write_color(
-                                &(pixel_color * self_clone.pixel_samples_scale),
-                                &mut img.lock().unwrap(),
-                                i,
-                                j,
-                            );
+                            if collect_aovs {
+                                aovs.lock().unwrap().add_sample(i, j, &pixel_aov);
+                            }
 
                             bar.inc(1);
                         }
                     }
                 }
+                film.lock().unwrap().merge(&thread_film);
             });
             render_threads.push(render_thread);
         }
@@ -176,12 +504,91 @@ impl Camera {
         }
         Arc::try_unwrap(bar).unwrap().finish();
 
-        match Arc::try_unwrap(img)
-            .unwrap()
-            .into_inner()
-            .unwrap()
-            .write_to(&mut output_file, ImageFormat::Png)
-        {
+        let pixels = self.expose(&film.lock().unwrap(), self.pixel_samples_scale);
+        if denoise {
+            let guides = aovs.lock().unwrap().resolve(self.pixel_samples_scale);
+            let denoised = denoise::denoise(&pixels, &guides, width, height);
+            self.write_pixels(&denoised, name, settings);
+
+            if settings.keep_noisy {
+                let noisy_name = String::from(name) + ".noisy";
+                self.write_pixels(&pixels, &noisy_name, settings);
+            }
+        } else {
+            self.write_pixels(&pixels, name, settings);
+        }
+        if write_aovs {
+            aovs.lock().unwrap().write(
+                name,
+                self.pixel_samples_scale,
+                integrator != IntegratorKind::Bdpt,
+            );
+        }
+    }
+
+    /// Auxiliary values at the first surface `r` hits.
+    fn first_hit_aov(&self, r: &Ray, world: &HittableList) -> AovSample {
+        let mut rec = HitRecord::default();
+        if !world.hit(r, &Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return AovSample::default();
+        }
+        rec.prepare_shading(r);
+
+        let mat = rec.mat.as_ref().unwrap();
+        AovSample {
+            albedo: mat.albedo(&rec),
+            normal: rec.normal,
+            depth: (rec.p - *r.origin()) * self.forward(),
+            position: rec.p,
+            object_id: Some(rec.object_id),
+            material_id: world.material_id(mat),
+            ..Default::default()
+        }
+    }
+
+    /// The pixels of `film` scaled by the exposure of the camera.
+    fn expose(&self, film: &Film, scale: f64) -> Vec<Color> {
+        film.resolve(scale)
+            .iter()
+            .map(|pixel| *pixel * self.exposure)
+            .collect()
+    }
+
+    /// Writes `pixels` of the whole film as `name`.png through the output
+    /// transform of `settings`, or when splitting eyes the views of a stereo
+    /// camera as `name`.left.png and `name`.right.png.
+    fn write_pixels(&self, pixels: &[Color], name: &str, settings: &RenderSettings) {
+        let (width, height) = self.film_size();
+        let images = match &self.stereo {
+            Some(stereo) if settings.split_eyes => {
+                let (eye_width, eye_height) = (self.image_width, self.image_height);
+                let eye = |right: u32| {
+                    let (x0, y0) = match stereo.layout {
+                        StereoLayout::SideBySide => (right * eye_width, 0),
+                        StereoLayout::OverUnder => (0, right * eye_height),
+                    };
+                    (0..eye_height)
+                        .flat_map(|j| (0..eye_width).map(move |i| (i, j)))
+                        .map(|(i, j)| pixels[((y0 + j) * width + x0 + i) as usize])
+                        .collect::<Vec<_>>()
+                };
+                vec![
+                    (".left", eye(0), eye_width, eye_height),
+                    (".right", eye(1), eye_width, eye_height),
+                ]
+            }
+            _ => vec![("", pixels.to_vec(), width, height)],
+        };
+
+        for (suffix, pixels, width, height) in images {
+            let path = format!("{}{}.png", name, suffix);
+            let img = film::to_image(&pixels, width, height, settings.output_transform);
+            Self::write_image(&img, &mut File::create(&path).unwrap(), &path);
+        }
+    }
+
+    fn write_image(img: &DynamicImage, output_file: &mut File, path: &str) {
+        match img.write_to(output_file, ImageFormat::Png) {
             Ok(_) => {
                 println!("Ouput image as \"{}\"", path);
             }
@@ -191,21 +598,219 @@ impl Camera {
         }
     }
 
-    fn get_ray(&self, i: u32, j: u32, s_i: u32, s_j: u32) -> Ray {
-        let offset = self.sample_square_stratified(s_i, s_j);
-        let pixel_sample = self.pixel00_loc
-            + (self.pixel_delta_u * (i as f64 + offset.x)
-                + self.pixel_delta_v * (j as f64 + offset.y));
+    /// Size of the rendered image, holding both eyes of a stereo camera.
+    fn film_size(&self) -> (u32, u32) {
+        match &self.stereo {
+            Some(stereo) => stereo.image_size(self.image_width, self.image_height),
+            None => (self.image_width, self.image_height),
+        }
+    }
 
-        let ray_origin = if self.defocus_angle <= 0.0 {
+    pub fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
+    pub fn background(&self) -> Color {
+        self.background
+    }
+
+    /// A time while the shutter is open, for the rays of a sample.
+    pub fn sample_time(&self) -> f64 {
+        self.shutter_open + rtweekend::random_double()
+    }
+
+    /// The direction the camera looks in.
+    pub fn forward(&self) -> Vec3 {
+        -self.w
+    }
+
+    /// Area and solid angle densities of a camera ray.
+    pub fn pdf_we(&self, r: &Ray, pdf_pos: &mut f64, pdf_dir: &mut f64) {
+        let cos_theta = r.direction().unit() * self.forward();
+        if cos_theta <= 0.0 || self.raster_position(r).is_none() {
+            *pdf_pos = 0.0;
+            *pdf_dir = 0.0;
+            return;
+        }
+
+        *pdf_pos = 1.0 / self.lens_area();
+        *pdf_dir = 1.0 / (self.image_plane_area() * cos_theta.powi(3));
+    }
+
+    /// Picks a point on the lens to connect `p` to, and the pixel it lands on.
+    pub fn sample_wi(&self, p: &Point3, sample: &mut ImportanceSample) -> bool {
+        sample.lens_point = if self.lens_radius <= 0.0 {
             self.center
         } else {
             self.defocus_disk_sample()
         };
-        let ray_direction = pixel_sample - ray_origin;
-        let ray_time = rtweekend::random_double();
 
-        Ray::new_with_time(&ray_origin, &ray_direction, ray_time)
+        let to_lens = sample.lens_point - *p;
+        sample.distance = to_lens.length();
+        if sample.distance == 0.0 {
+            return false;
+        }
+        sample.wi = to_lens / sample.distance;
+
+        let cos_theta = -sample.wi * self.forward();
+        if cos_theta <= 0.0 {
+            return false;
+        }
+        match self.raster_position(&Ray::new(&sample.lens_point, &-sample.wi)) {
+            Some((i, j)) => {
+                sample.i = i;
+                sample.j = j;
+            }
+            None => return false,
+        }
+
+        sample.pdf = sample.distance.powi(2) / (cos_theta * self.lens_area());
+        sample.we = 1.0 / (self.image_plane_area() * self.lens_area() * cos_theta.powi(4));
+        true
+    }
+
+    fn lens_area(&self) -> f64 {
+        if self.lens_radius <= 0.0 {
+            1.0
+        } else {
+            self.aperture.area() * self.defocus_disk_u.length() * self.defocus_disk_v.length()
+        }
+    }
+
+    /// Area of the visible part of the image plane at unit distance from the lens.
+    fn image_plane_area(&self) -> f64 {
+        let focus_dist = (self.center - self.pixel00_loc) * self.w;
+        let viewport_u = self.pixel_delta_u * self.image_width as f64;
+        let viewport_v = self.pixel_delta_v * self.image_height as f64;
+        viewport_u.length() * viewport_v.length() / focus_dist.powi(2)
+    }
+
+    /// The pixel a ray leaving the lens passes through.
+    fn raster_position(&self, r: &Ray) -> Option<(u32, u32)> {
+        let denom = *r.direction() * self.w;
+        if denom == 0.0 {
+            return None;
+        }
+        let t = (self.pixel00_loc - *r.origin()) * self.w / denom;
+        if t <= 0.0 {
+            return None;
+        }
+
+        let d = r.at(t) - self.pixel00_loc;
+        let x = d * self.pixel_delta_u / self.pixel_delta_u.squared_length() + 0.5;
+        let y = d * self.pixel_delta_v / self.pixel_delta_v.squared_length() + 0.5;
+        if x < 0.0 || y < 0.0 || x >= self.image_width as f64 || y >= self.image_height as f64 {
+            None
+        } else {
+            Some((x as u32, y as u32))
+        }
+    }
+
+    /// A ray through a stratified point of pixel (i, j), and the factor
+    /// applied to the radiance it carries.
+    pub fn get_ray(&self, i: u32, j: u32, s_i: u32, s_j: u32, weight: &mut f64) -> Option<Ray> {
+        let (x, y) = self.film_sample(i, j, s_i, s_j);
+        self.ray_through(x, y, weight)
+    }
+
+    /// A stratified position within pixel (i, j), in pixels from the top left
+    /// corner of the image.
+    fn film_sample(&self, i: u32, j: u32, s_i: u32, s_j: u32) -> (f64, f64) {
+        let offset = self.sample_square_stratified(s_i, s_j);
+        (i as f64 + 0.5 + offset.x, j as f64 + 0.5 + offset.y)
+    }
+
+    /// A camera ray through film position (`x`, `y`), `None` where the
+    /// projection has none or the lens blocks it. Lens systems weight their
+    /// rays by `weight`, which is 1 otherwise.
+    ///
+    /// Rays of the thin lens and the other projections carry differentials
+    /// towards the neighbouring pixels, shrunk when there are several samples
+    /// per pixel (pbrt), which filtered textures use to size their footprint.
+    fn ray_through(&self, x: f64, y: f64, weight: &mut f64) -> Option<Ray> {
+        let ray_time = self.sample_time();
+        let (x, y, eye_offset) = match &self.stereo {
+            Some(stereo) => stereo.eye_position(x, y, self.image_width, self.image_height),
+            None => (x, y, 0.0),
+        };
+        let eye = self.center + self.u * eye_offset;
+        let (s, t) = (x / self.image_width as f64, y / self.image_height as f64);
+        *weight = 1.0;
+        let footprint = self.recip_sqrt_spp.max(0.125);
+
+        if let Some(lens) = &self.lens {
+            let r = lens.generate_ray(s, t, weight)?;
+            let (o, d) = (*r.origin() * lens.mm_to_scene(), *r.direction());
+            let ray_origin = eye + self.u * o.x + self.v * o.y - self.w * o.z;
+            let ray_direction = self.u * d.x + self.v * d.y - self.w * d.z;
+            return Some(Ray::new_with_time(&ray_origin, &ray_direction, ray_time));
+        }
+
+        match self.projection {
+            Projection::Perspective => {
+                let mut pixel_sample = self.pixel00_loc
+                    + (self.pixel_delta_u * (x - 0.5) + self.pixel_delta_v * (y - 0.5));
+                if let Some(stereo) = &self.stereo {
+                    pixel_sample += self.u * stereo.image_shift(eye_offset, self.view.focus_dist);
+                }
+
+                let ray_origin = if self.lens_radius <= 0.0 {
+                    eye
+                } else {
+                    self.defocus_disk_sample() + self.u * eye_offset
+                };
+                let ray_direction = pixel_sample - ray_origin;
+
+                let mut r = Ray::new_with_time(&ray_origin, &ray_direction, ray_time);
+                r.set_differentials(&RayDifferentials {
+                    rx_origin: ray_origin,
+                    rx_direction: ray_direction + self.pixel_delta_u * footprint,
+                    ry_origin: ray_origin,
+                    ry_direction: ray_direction + self.pixel_delta_v * footprint,
+                });
+                Some(r)
+            }
+            Projection::Orthographic { height } => {
+                let width = height * self.image_width as f64 / self.image_height as f64;
+                let ray_origin = eye + self.u * ((s - 0.5) * width) + self.v * ((0.5 - t) * height);
+
+                let mut r = Ray::new_with_time(&ray_origin, &-self.w, ray_time);
+                let pixel_size = height / self.image_height as f64 * footprint;
+                r.set_differentials(&RayDifferentials {
+                    rx_origin: ray_origin + self.u * pixel_size,
+                    rx_direction: -self.w,
+                    ry_origin: ray_origin - self.v * pixel_size,
+                    ry_direction: -self.w,
+                });
+                Some(r)
+            }
+            _ => {
+                let aspect_ratio = self.image_width as f64 / self.image_height as f64;
+                let to_world = |d: Vec3| self.u * d.x + self.v * d.y - self.w * d.z;
+                let d = self.projection.direction(s, t, aspect_ratio)?.unit();
+                let ray_direction = to_world(d);
+                // omni-directional stereo: the eyes sit on a circle, to the
+                // sides of every horizontal direction, and come together
+                // towards the poles
+                let ray_origin = self.center + (self.u * d.z + self.w * d.x) * eye_offset;
+
+                let mut r = Ray::new_with_time(&ray_origin, &ray_direction, ray_time);
+                let ds = footprint / self.image_width as f64;
+                let dt = footprint / self.image_height as f64;
+                if let (Some(dx), Some(dy)) = (
+                    self.projection.direction(s + ds, t, aspect_ratio),
+                    self.projection.direction(s, t + dt, aspect_ratio),
+                ) {
+                    r.set_differentials(&RayDifferentials {
+                        rx_origin: ray_origin,
+                        rx_direction: to_world(dx.unit()),
+                        ry_origin: ray_origin,
+                        ry_direction: to_world(dy.unit()),
+                    });
+                }
+                Some(r)
+            }
+        }
     }
 
     fn sample_square_stratified(&self, s_i: u32, s_j: u32) -> Vec3 {
@@ -216,61 +821,254 @@ impl Camera {
     }
 
     fn defocus_disk_sample(&self) -> Point3 {
-        let p = Vec3::random_in_unit_disk();
+        let p = self.aperture.sample();
         self.center + self.defocus_disk_u * p.x + self.defocus_disk_v * p.y
     }
+}
 
-    fn ray_color(
-        &self,
-        r: &Ray,
-        depth: u32,
-        world: &HittableList,
-        // lights: &Arc<dyn Hittable>,
-    ) -> Color {
-        if depth > 0 {
-            let mut rec = HitRecord::default();
-
-            if world.hit(r, &Interval::new(0.001, INFINITY), &mut rec) {
-                match &rec.mat {
-                    Some(mat) => {
-                        let mut scattered = Ray::default();
-                        let mut attenuation = Color::default();
-                        let color_from_emission = mat.emitted(r, &rec, rec.u, rec.v, &rec.p);
-                        // let color_from_emission = mat.emitted(r, &rec, rec.u, rec.v, &rec.p);
-
-                        if mat.scatter(r, &rec, &mut attenuation, &mut scattered) {
-                            let surface_pdf = CosinePdf::new(&rec.normal);
-                            // let light_pdf = HittablePdf::new(lights, &rec.p);
-                            scattered =
-                                Ray::new_with_time(&rec.p, &surface_pdf.generate(), r.time());
-                            // scattered = Ray::new_with_time(&rec.p, &light_pdf.generate(), r.time());
-                            let pdf_val = surface_pdf.value(scattered.direction());
-                            // let pdf_val = light_pdf.value(scattered.direction());
-
-                            let scattering_pdf = mat.scattering_pdf(r, &rec, &scattered);
-
-                            // let sample_color = self.ray_color(&scattered, depth - 1, world, lights);
-                            let color_from_scatter =
-                                attenuation.elemul(&self.ray_color(&scattered, depth - 1, world))
-                                    * scattering_pdf
-                                    / pdf_val;
-                            // let color_from_scatter =
-                            //     attenuation.elemul(&sample_color) * scattering_pdf / pdf_val;
-
-                            color_from_emission + color_from_scatter
-                        } else {
-                            color_from_emission
-                        }
-                    }
-                    _ => {
-                        panic!()
-                    }
-                }
-            } else {
-                self.background
+/// The command line of the renderer, read by `main` for the scene to build and
+/// by the camera for how to render it.
+pub fn command() -> clap::Command {
+    clap::command!()
+        .arg(
+            clap::arg!(--scene <SCENE>)
+                .help("scene to render")
+                .value_parser(clap::value_parser!(String))
+                .default_value("cornell-box"),
+        )
+        .arg(
+            clap::arg!(--"texture-path" <DIR>)
+                .help("also look up images in DIR, after assets/ and earlier directories")
+                .value_parser(clap::value_parser!(String))
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            clap::arg!(--"texture-budget" <MEGABYTES>)
+                .help("memory full resolution image tiles may take, streaming them from disk")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            clap::arg!(--"texture-filter" <FILTER>)
+                .help("how every image texture is filtered, replacing the scene's")
+                .value_parser(["nearest", "bilinear", "trilinear", "ewa"]),
+        )
+        .arg(
+            clap::arg!(-o <NAME>)
+                .help("image filename without extension name")
+                .value_parser(clap::value_parser!(String))
+                .default_value("output"),
+        )
+        .arg(
+            clap::arg!(--"light-sampler" <SAMPLER>)
+                .help("how a light is picked for next event estimation")
+                .value_parser(["power", "bvh"])
+                .default_value("bvh"),
+        )
+        .arg(
+            clap::arg!(--integrator <INTEGRATOR>)
+                .help("light transport algorithm")
+                .value_parser(["path", "bdpt", "sppm"])
+                .default_value("path"),
+        )
+        .arg(
+            clap::arg!(--spectral)
+                .help("trace the path integrator at sampled wavelengths, for dispersion")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::arg!(--photons <COUNT>)
+                .help("photons shot per iteration of the sppm integrator")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("200000"),
+        )
+        .arg(
+            clap::arg!(--"photon-radius" <RADIUS>)
+                .help("initial gather radius of the sppm integrator, 0 to derive it from the scene size")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("0"),
+        )
+        .arg(
+            clap::arg!(--filter <FILTER>)
+                .help("pixel reconstruction filter")
+                .value_parser(["box", "tent", "gaussian", "mitchell", "lanczos"])
+                .default_value("box"),
+        )
+        .arg(
+            clap::arg!(--"filter-radius" <RADIUS>)
+                .help("filter radius in pixels, defaults to one suiting the filter")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .arg(
+            clap::arg!(--"working-space" <SPACE>)
+                .help("primaries of the linear RGB colors scenes are given and rendered in")
+                .value_parser(["rec709", "acescg"])
+                .default_value("rec709"),
+        )
+        .arg(
+            clap::arg!(--"output-transform" <TRANSFORM>)
+                .help("curve taking the linear working space to the image")
+                .value_parser(["srgb", "rec709", "gamma2", "linear"])
+                .default_value("srgb"),
+        )
+        .arg(
+            clap::arg!(--aov)
+                .help("also write albedo, normal, depth, position, ID and lighting passes")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::arg!(--denoise)
+                .help("filter the image guided by the albedo, normal and depth passes")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::arg!(--"keep-noisy")
+                .help("also write the unfiltered image as NAME.noisy.png")
+                .requires("denoise")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::arg!(--projection <PROJECTION>)
+                .help("how the film maps to rays, replacing the scene's; panoramas want a 2:1 image, cube maps 3:2")
+                .value_parser(["perspective", "orthographic", "equirectangular", "cubemap", "fisheye"]),
+        )
+        .arg(
+            clap::arg!(--"ortho-height" <HEIGHT>)
+                .help("height of an orthographic view, defaults to the perspective view's at the look-at point")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .arg(
+            clap::arg!(--"fisheye-fov" <DEGREES>)
+                .help("angle covered by the image circle of a fisheye")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("180"),
+        )
+        .arg(
+            clap::arg!(--aperture <SHAPE>)
+                .help("shape of the lens opening of cameras with depth of field, replacing the scene's: circle, a number of iris blades or a mask image"),
+        )
+        .arg(
+            clap::arg!(--stereo <INTEROCULAR>)
+                .help("render a left and a right eye this far apart into one image")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .arg(
+            clap::arg!(--convergence <DISTANCE>)
+                .help("distance appearing at screen depth in stereo, defaults to the look-at point's")
+                .requires("stereo")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .arg(
+            clap::arg!(--"split-eyes")
+                .help("write the eyes as NAME.left.png and NAME.right.png")
+                .requires("stereo")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::arg!(--turntable <FRAMES>)
+                .help("render FRAMES frames orbiting the look-at point as NAME.0000.png, ...")
+                .value_parser(clap::value_parser!(u32)),
+        )
+        .arg(
+            clap::arg!(--"camera-path" <FILE>)
+                .help("render the frames between the keyframes of a camera path file as NAME.0000.png, ...")
+                .conflicts_with("turntable"),
+        )
+        .arg(
+            clap::arg!(--"frame-start" <FRAME>)
+                .help("first frame of an animation to render, defaults to its first keyframe")
+                .value_parser(clap::value_parser!(i64)),
+        )
+        .arg(
+            clap::arg!(--"frame-end" <FRAME>)
+                .help("last frame of an animation to render, defaults to its last keyframe")
+                .value_parser(clap::value_parser!(i64)),
+        )
+}
+
+/// What a render reads from the command line, the same for all its frames.
+struct RenderSettings {
+    integrator: IntegratorKind,
+    filter: Arc<dyn Filter>,
+    output_transform: OutputTransform,
+    write_aovs: bool,
+    denoise: bool,
+    keep_noisy: bool,
+    /// write the eyes of a stereo camera as separate images
+    split_eyes: bool,
+}
+
+/// The scene and what is built from it before rendering, kept between frames.
+struct Scene {
+    world: Arc<HittableList>,
+    light_sampler: Arc<dyn LightSampler>,
+    bdpt: Arc<Bdpt>,
+    path: Arc<PathTracer<Color>>,
+    spectral: Arc<SpectralPath>,
+    sppm: Option<Sppm>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> CameraParams {
+        CameraParams {
+            aspect_ratio: 1.0,
+            image_width: 16,
+            samples_per_pixel: 1,
+            max_depth: 4,
+            background: Color::zeros(),
+            lookfrom: Point3::new(0.0, 0.0, 10.0),
+            lookat: Point3::zeros(),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+        }
+    }
+
+    #[test]
+    fn test_refocusing_keeps_lens_radius() {
+        let mut camera = Camera::new(&params(), 40.0, 2.0, 10.0);
+        let radius = camera.defocus_disk_v.length();
+        assert!((radius - 10.0 * 1.0_f64.to_radians().tan()).abs() < 1e-12);
+
+        let view = CameraView {
+            focus_dist: 25.0,
+            ..camera.view
+        };
+        camera.set_view(&view);
+        assert!((camera.defocus_disk_v.length() - radius).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_stereo_eyes_converge() {
+        const INTEROCULAR: f64 = 0.065;
+        const CONVERGENCE: f64 = 4.0;
+        for layout in [StereoLayout::SideBySide, StereoLayout::OverUnder] {
+            let mut camera = Camera::new(&params(), 40.0, 0.0, 10.0);
+            camera.set_stereo(&StereoRig::new(INTEROCULAR, CONVERGENCE, layout));
+            // the same film position in the image of the other eye
+            let (dx, dy) = match layout {
+                StereoLayout::SideBySide => (16.0, 0.0),
+                StereoLayout::OverUnder => (0.0, 16.0),
+            };
+
+            for (x, y) in [(8.0, 8.0), (2.5, 13.0), (15.5, 0.5)] {
+                let mut weight = 0.0;
+                let left = camera.ray_through(x, y, &mut weight).unwrap();
+                let right = camera.ray_through(x + dx, y + dy, &mut weight).unwrap();
+                assert_eq!(weight, 1.0);
+
+                // the eyes sit half the interocular distance either side
+                let half = camera.u * (INTEROCULAR / 2.0);
+                assert!((*left.origin() - (camera.center - half)).length() < 1e-12);
+                assert!((*right.origin() - (camera.center + half)).length() < 1e-12);
+
+                // without parallax at the convergence distance, with some elsewhere
+                let at_depth = |r: &Ray, depth: f64| r.at(depth / (-camera.w * *r.direction()));
+                let parallax =
+                    |depth: f64| (at_depth(&left, depth) - at_depth(&right, depth)).length();
+                assert!(parallax(CONVERGENCE) < 1e-12);
+                assert!((parallax(2.0 * CONVERGENCE) - INTEROCULAR).abs() < 1e-12);
             }
-        } else {
-            Color::zeros()
         }
     }
 }
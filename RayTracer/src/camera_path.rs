@@ -0,0 +1,264 @@
+use crate::vec3::{Point3, Vec3};
+use std::{f64::consts::PI, fs};
+
+/// Where a camera stands and looks, the part of it that can be animated.
+#[derive(Clone, Copy)]
+pub struct CameraView {
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    /// vertical field of view in degrees
+    pub vfov: f64,
+    pub focus_dist: f64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    /// Catmull-Rom like cubic through the keyframes, with tangents from the
+    /// neighbouring keys, so the camera doesn't jerk at them
+    Smooth,
+}
+
+/// Camera views at keyframes, interpolated for the frames in between.
+#[derive(Clone)]
+pub struct CameraPath {
+    /// sorted by frame
+    keys: Vec<(f64, CameraView)>,
+    interpolation: Interpolation,
+}
+
+impl CameraPath {
+    pub fn new(interpolation: Interpolation) -> Self {
+        Self {
+            keys: Vec::new(),
+            interpolation,
+        }
+    }
+
+    /// One orbit of `frames` frames around the vertical axis through the
+    /// look-at point of `view`, starting at its look-from point. The last
+    /// frame stops one step short of the first, so the sequence loops.
+    pub fn turntable(view: &CameraView, frames: u32) -> Self {
+        let mut path = Self::new(Interpolation::Linear);
+        let frames = frames.max(1);
+        let offset = view.lookfrom - view.lookat;
+        for frame in 0..frames {
+            let (sin_theta, cos_theta) = (2.0 * PI * frame as f64 / frames as f64).sin_cos();
+            let rotated = Vec3::new(
+                cos_theta * offset.x + sin_theta * offset.z,
+                offset.y,
+                -sin_theta * offset.x + cos_theta * offset.z,
+            );
+            path.add(
+                frame as f64,
+                &CameraView {
+                    lookfrom: view.lookat + rotated,
+                    ..*view
+                },
+            );
+        }
+        path
+    }
+
+    /// Reads keyframes from the assets directory, one per line as the frame,
+    /// look-from point, look-at point, vertical field of view and focus
+    /// distance, with `#` comments. A line reading `smooth` or `linear` picks
+    /// the interpolation, linear by default.
+    pub fn load(filename: &str) -> Self {
+        let path = String::from("assets/") + filename;
+        let mut camera_path = Self::new(Interpolation::Linear);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => {
+                eprintln!("ERROR: Could not load camera path file '{}'.", path);
+                return camera_path;
+            }
+        };
+
+        for line in text
+            .lines()
+            .map(|line| line.split('#').next().unwrap().trim())
+        {
+            let values: Vec<f64> = line
+                .split_whitespace()
+                .filter_map(|value| value.parse().ok())
+                .collect();
+            match (line, &values[..]) {
+                ("", _) => {}
+                ("linear", _) => camera_path.interpolation = Interpolation::Linear,
+                ("smooth", _) => camera_path.interpolation = Interpolation::Smooth,
+                (_, &[frame, fx, fy, fz, ax, ay, az, vfov, focus_dist]) => camera_path.add(
+                    frame,
+                    &CameraView {
+                        lookfrom: Point3::new(fx, fy, fz),
+                        lookat: Point3::new(ax, ay, az),
+                        vfov,
+                        focus_dist,
+                    },
+                ),
+                _ => eprintln!("Skipping the camera path line '{}' of '{}'", line, path),
+            }
+        }
+        camera_path
+    }
+
+    /// Adds a keyframe, replacing one already at `frame`.
+    pub fn add(&mut self, frame: f64, view: &CameraView) {
+        match self.keys.binary_search_by(|(f, _)| f.total_cmp(&frame)) {
+            Ok(index) => self.keys[index].1 = *view,
+            Err(index) => self.keys.insert(index, (frame, *view)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The frames of the first and last keyframe.
+    pub fn frame_range(&self) -> (f64, f64) {
+        match (self.keys.first(), self.keys.last()) {
+            (Some(first), Some(last)) => (first.0, last.0),
+            _ => (0.0, 0.0),
+        }
+    }
+
+    /// The view at `frame`, held at the first and last keyframes outside of
+    /// them.
+    pub fn view_at(&self, frame: f64) -> CameraView {
+        assert!(!self.is_empty(), "camera path without keyframes");
+
+        let last = self.keys.len() - 1;
+        if frame <= self.keys[0].0 {
+            return self.keys[0].1;
+        }
+        if frame >= self.keys[last].0 {
+            return self.keys[last].1;
+        }
+
+        // the key starting the segment `frame` is in
+        let k = self.keys.partition_point(|(f, _)| *f <= frame) - 1;
+        let (f0, f1) = (self.keys[k].0, self.keys[k + 1].0);
+        let t = (frame - f0) / (f1 - f0);
+
+        let interpolate = |value: fn(&CameraView) -> Point3| match self.interpolation {
+            Interpolation::Linear => lerp(value(&self.keys[k].1), value(&self.keys[k + 1].1), t),
+            Interpolation::Smooth => self.hermite(k, t, value),
+        };
+        let lookfrom = interpolate(|view| view.lookfrom);
+        let lookat = interpolate(|view| view.lookat);
+        let scalars = interpolate(|view| Vec3::new(view.vfov, view.focus_dist, 0.0));
+
+        CameraView {
+            lookfrom,
+            lookat,
+            vfov: scalars.x,
+            focus_dist: scalars.y,
+        }
+    }
+
+    /// Cubic Hermite interpolation between keys `k` and `k + 1`, with the
+    /// tangent at every key the slope between its neighbours (one sided at
+    /// the ends of the path).
+    fn hermite(&self, k: usize, t: f64, value: fn(&CameraView) -> Vec3) -> Vec3 {
+        let last = self.keys.len() - 1;
+        let tangent = |i: usize| {
+            let (before, after) = (i.saturating_sub(1), (i + 1).min(last));
+            (value(&self.keys[after].1) - value(&self.keys[before].1))
+                / (self.keys[after].0 - self.keys[before].0)
+        };
+
+        let dt = self.keys[k + 1].0 - self.keys[k].0;
+        let (p0, p1) = (value(&self.keys[k].1), value(&self.keys[k + 1].1));
+        let (m0, m1) = (tangent(k) * dt, tangent(k + 1) * dt);
+
+        let t2 = t * t;
+        let t3 = t2 * t;
+        p0 * (2.0 * t3 - 3.0 * t2 + 1.0)
+            + m0 * (t3 - 2.0 * t2 + t)
+            + p1 * (-2.0 * t3 + 3.0 * t2)
+            + m1 * (t3 - t2)
+    }
+}
+
+fn lerp(a: Vec3, b: Vec3, t: f64) -> Vec3 {
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(x: f64, vfov: f64) -> CameraView {
+        CameraView {
+            lookfrom: Point3::new(x, 1.0, 10.0),
+            lookat: Point3::new(x * 0.5, 0.0, 0.0),
+            vfov,
+            focus_dist: 10.0 + x,
+        }
+    }
+
+    fn assert_views_eq(a: &CameraView, b: &CameraView) {
+        assert!((a.lookfrom - b.lookfrom).length() < 1e-9);
+        assert!((a.lookat - b.lookat).length() < 1e-9);
+        assert!((a.vfov - b.vfov).abs() < 1e-9);
+        assert!((a.focus_dist - b.focus_dist).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_interpolation_passes_through_keyframes() {
+        let keys = [
+            (0.0, view(0.0, 40.0)),
+            (10.0, view(4.0, 30.0)),
+            (25.0, view(-2.0, 50.0)),
+        ];
+        for interpolation in [Interpolation::Linear, Interpolation::Smooth] {
+            let mut path = CameraPath::new(interpolation);
+            // added out of order
+            for (frame, key) in keys.iter().rev() {
+                path.add(*frame, key);
+            }
+            assert_eq!(path.frame_range(), (0.0, 25.0));
+            for (frame, key) in &keys {
+                assert_views_eq(&path.view_at(*frame), key);
+            }
+            // held beyond the ends
+            assert_views_eq(&path.view_at(-5.0), &keys[0].1);
+            assert_views_eq(&path.view_at(30.0), &keys[2].1);
+        }
+    }
+
+    #[test]
+    fn test_linear_interpolation_between_keyframes() {
+        let mut path = CameraPath::new(Interpolation::Linear);
+        path.add(0.0, &view(0.0, 40.0));
+        path.add(10.0, &view(4.0, 30.0));
+        assert_views_eq(&path.view_at(2.5), &view(1.0, 37.5));
+    }
+
+    #[test]
+    fn test_turntable_orbits_look_at() {
+        let start = view(0.0, 40.0);
+        let path = CameraPath::turntable(&start, 4);
+        assert_views_eq(&path.view_at(0.0), &start);
+        // a quarter turn later
+        let quarter = path.view_at(1.0);
+        assert!((quarter.lookfrom - Point3::new(10.0, 1.0, 0.0)).length() < 1e-9);
+        assert_eq!(quarter.lookat, start.lookat);
+    }
+
+    #[test]
+    fn test_load_reads_keyframes() {
+        let path = CameraPath::load("paths/flyby.path");
+        assert!(path.interpolation == Interpolation::Smooth);
+        assert_eq!(path.frame_range(), (0.0, 48.0));
+        assert_views_eq(
+            &path.view_at(0.0),
+            &CameraView {
+                lookfrom: Point3::new(1.5, 1.0, 4.0),
+                lookat: Point3::new(0.0, 0.5, -6.0),
+                vfov: 30.0,
+                focus_dist: 7.0,
+            },
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! The linear RGB space colors are rendered in, how texture values get into
+//! it and how rendered radiance gets out of it into an image.
+
+use crate::color::Color;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Linear Rec.709 to ACEScg, adapted from D65 to the ACES white by Bradford.
+const REC709_TO_ACESCG: [[f64; 3]; 3] = [
+    [0.6130973, 0.3395228, 0.0473793],
+    [0.0701942, 0.9163556, 0.0134526],
+    [0.0206156, 0.1095698, 0.8698151],
+];
+/// The inverse of `REC709_TO_ACESCG`.
+const ACESCG_TO_REC709: [[f64; 3]; 3] = [
+    [1.7050515, -0.6217907, -0.0832584],
+    [-0.1302571, 1.1408029, -0.0105485],
+    [-0.0240033, -0.1289688, 1.1529717],
+];
+
+static WORKING_SPACE: AtomicU8 = AtomicU8::new(WorkingSpace::Rec709 as u8);
+
+/// The primaries of the linear RGB colors the renderer works with.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WorkingSpace {
+    /// the primaries of sRGB and HD video
+    Rec709,
+    /// the wider AP1 primaries of ACES, which mix saturated colors more like
+    /// spectra do
+    AcesCg,
+}
+
+/// How the values stored in an image texture are to be read.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ColorEncoding {
+    /// colors with the sRGB transfer curve, as most 8-bit images are painted
+    #[default]
+    Srgb,
+    /// linear Rec.709 colors
+    Linear,
+    /// data like normals, roughness or heights, used as stored
+    Raw,
+    /// the alpha channel as a gray value, for cutout masks
+    Alpha,
+}
+
+/// How radiance in the working space becomes the values of an 8-bit image.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum OutputTransform {
+    /// the sRGB transfer curve, for computer displays
+    #[default]
+    Srgb,
+    /// the Rec.709 camera curve, for video
+    Rec709,
+    /// a plain square root
+    Gamma2,
+    /// linear Rec.709, clamped
+    Linear,
+}
+
+/// Sets the working space. Scene colors are given in it, so it should be set
+/// before the scene is built and its textures loaded.
+pub fn set_working_space(space: WorkingSpace) {
+    WORKING_SPACE.store(space as u8, Ordering::Relaxed);
+}
+
+pub fn working_space() -> WorkingSpace {
+    match WORKING_SPACE.load(Ordering::Relaxed) {
+        x if x == WorkingSpace::AcesCg as u8 => WorkingSpace::AcesCg,
+        _ => WorkingSpace::Rec709,
+    }
+}
+
+/// Linear Rec.709 `c` in the working space.
+pub fn from_rec709(c: &Color) -> Color {
+    match working_space() {
+        WorkingSpace::Rec709 => *c,
+        WorkingSpace::AcesCg => transform(&REC709_TO_ACESCG, c),
+    }
+}
+
+/// Working space `c` in linear Rec.709.
+pub fn to_rec709(c: &Color) -> Color {
+    match working_space() {
+        WorkingSpace::Rec709 => *c,
+        WorkingSpace::AcesCg => transform(&ACESCG_TO_REC709, c),
+    }
+}
+
+impl ColorEncoding {
+    /// The working space color of a stored value in [0, 1].
+    pub fn decode(&self, c: &Color) -> Color {
+        match self {
+            ColorEncoding::Srgb => from_rec709(&Color::new(
+                srgb_to_linear(c.x),
+                srgb_to_linear(c.y),
+                srgb_to_linear(c.z),
+            )),
+            ColorEncoding::Linear => from_rec709(c),
+            ColorEncoding::Raw | ColorEncoding::Alpha => *c,
+        }
+    }
+}
+
+impl OutputTransform {
+    /// The display value in [0, 1] of the working space radiance `c`.
+    pub fn encode(&self, c: &Color) -> Color {
+        let curve: fn(f64) -> f64 = match self {
+            OutputTransform::Srgb => linear_to_srgb,
+            OutputTransform::Rec709 => linear_to_rec709,
+            OutputTransform::Gamma2 => f64::sqrt,
+            OutputTransform::Linear => |x| x,
+        };
+        let c = to_rec709(c);
+        let apply = |x: f64| curve(x.clamp(0.0, 1.0));
+        Color::new(apply(c.x), apply(c.y), apply(c.z))
+    }
+}
+
+fn transform(m: &[[f64; 3]; 3], c: &Color) -> Color {
+    Color::new(
+        m[0][0] * c.x + m[0][1] * c.y + m[0][2] * c.z,
+        m[1][0] * c.x + m[1][1] * c.y + m[1][2] * c.z,
+        m[2][0] * c.x + m[2][1] * c.y + m[2][2] * c.z,
+    )
+}
+
+fn srgb_to_linear(x: f64) -> f64 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(x: f64) -> f64 {
+    if x <= 0.0031308 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn linear_to_rec709(x: f64) -> f64 {
+    if x < 0.018 {
+        4.5 * x
+    } else {
+        1.099 * x.powf(0.45) - 0.099
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the working space is global, so these use the matrices directly and
+    // leave it at Rec.709
+    #[test]
+    fn test_acescg_round_trip() {
+        for c in [
+            Color::new(1.0, 1.0, 1.0),
+            Color::new(0.8, 0.1, 0.05),
+            Color::new(0.02, 0.3, 0.9),
+        ] {
+            let back = transform(&ACESCG_TO_REC709, &transform(&REC709_TO_ACESCG, &c));
+            assert!((back - c).length() < 1e-6);
+        }
+        // both are white balanced to the same white
+        let white = transform(&REC709_TO_ACESCG, &Color::new(1.0, 1.0, 1.0));
+        assert!((white - Color::new(1.0, 1.0, 1.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_srgb_round_trip() {
+        for x in [0.0, 0.002, 0.01, 0.2, 0.5, 1.0] {
+            assert!((srgb_to_linear(linear_to_srgb(x)) - x).abs() < 1e-12);
+        }
+        let c = Color::new(0.001, 0.18, 0.9);
+        let stored = OutputTransform::Srgb.encode(&c);
+        assert!((ColorEncoding::Srgb.decode(&stored) - c).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_output_clamps() {
+        let c = OutputTransform::Rec709.encode(&Color::new(-1.0, 0.5, 7.0));
+        assert_eq!(c.x, 0.0);
+        assert_eq!(c.z, 1.0);
+        assert!((c.y - linear_to_rec709(0.5)).abs() < 1e-12);
+    }
+}
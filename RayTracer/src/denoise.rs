@@ -0,0 +1,208 @@
+use crate::{aov::AovSample, color::Color};
+
+/// Number of à-trous passes, each doubling the distance between taps.
+const ITERATIONS: u32 = 5;
+/// B3 spline weights of the taps at offsets -2..=2.
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+const COLOR_SIGMA: f64 = 0.6;
+const NORMAL_POWER: i32 = 64;
+/// Relative change of depth per pixel still considered the same surface.
+const DEPTH_SIGMA: f64 = 0.05;
+
+/// Edge-avoiding à-trous wavelet filter (Dammertz et al. 2010) guided by the
+/// first-hit albedo, normal and depth of every pixel.
+///
+/// The albedo is divided out before filtering so that textures stay sharp,
+/// and multiplied back in afterwards.
+pub fn denoise(pixels: &[Color], guides: &[AovSample], width: u32, height: u32) -> Vec<Color> {
+    let albedo: Vec<_> = guides
+        .iter()
+        .map(|guide| {
+            let a = guide.albedo;
+            Color::new(demodulator(a.x), demodulator(a.y), demodulator(a.z))
+        })
+        .collect();
+
+    let mut irradiance: Vec<_> = pixels
+        .iter()
+        .zip(&albedo)
+        .map(|(c, a)| Color::new(c.x / a.x, c.y / a.y, c.z / a.z))
+        .collect();
+
+    let mut color_sigma = COLOR_SIGMA;
+    for iteration in 0..ITERATIONS {
+        irradiance = atrous_pass(
+            &irradiance,
+            guides,
+            width,
+            height,
+            1 << iteration,
+            color_sigma,
+        );
+        color_sigma *= 0.5;
+    }
+
+    irradiance
+        .iter()
+        .zip(&albedo)
+        .map(|(c, a)| c.elemul(a))
+        .collect()
+}
+
+/// Black channels of the albedo (emitters, the background) are left as is.
+fn demodulator(albedo: f64) -> f64 {
+    if albedo > 1e-3 {
+        albedo
+    } else {
+        1.0
+    }
+}
+
+fn atrous_pass(
+    input: &[Color],
+    guides: &[AovSample],
+    width: u32,
+    height: u32,
+    step: i64,
+    color_sigma: f64,
+) -> Vec<Color> {
+    let (width, height) = (width as i64, height as i64);
+    let mut output = vec![Color::zeros(); input.len()];
+
+    for y in 0..height {
+        for x in 0..width {
+            let p = (y * width + x) as usize;
+            let c_p = tone_map(&input[p]);
+            let g_p = &guides[p];
+
+            let mut sum = Color::zeros();
+            let mut weight_sum = 0.0;
+            for (ky, hy) in KERNEL.iter().enumerate() {
+                for (kx, hx) in KERNEL.iter().enumerate() {
+                    let qx = x + (kx as i64 - 2) * step;
+                    let qy = y + (ky as i64 - 2) * step;
+                    if qx < 0 || qy < 0 || qx >= width || qy >= height {
+                        continue;
+                    }
+                    let q = (qy * width + qx) as usize;
+                    let g_q = &guides[q];
+                    if (g_p.depth == 0.0) != (g_q.depth == 0.0) {
+                        continue;
+                    }
+
+                    let w_color = (-(tone_map(&input[q]) - c_p).squared_length()
+                        / (color_sigma * color_sigma))
+                        .exp();
+                    let w_normal = if g_p.depth == 0.0 {
+                        1.0
+                    } else {
+                        (g_p.normal * g_q.normal).max(0.0).powi(NORMAL_POWER)
+                    };
+                    let offset = (((qx - x).pow(2) + (qy - y).pow(2)) as f64).sqrt();
+                    let w_depth = if g_p.depth == 0.0 {
+                        1.0
+                    } else {
+                        (-(g_p.depth - g_q.depth).abs() / (DEPTH_SIGMA * g_p.depth * offset + 1e-8))
+                            .exp()
+                    };
+
+                    let weight = hx * hy * w_color * w_normal * w_depth;
+                    sum += input[q] * weight;
+                    weight_sum += weight;
+                }
+            }
+
+            output[p] = if weight_sum > 0.0 {
+                sum / weight_sum
+            } else {
+                input[p]
+            };
+        }
+    }
+
+    output
+}
+
+/// Compares colors after a gamma curve close to the one images are written
+/// with, so the color weight behaves alike in dark and bright regions.
+fn tone_map(c: &Color) -> Color {
+    Color::new(
+        c.x.max(0.0).sqrt(),
+        c.y.max(0.0).sqrt(),
+        c.z.max(0.0).sqrt(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::Vec3;
+
+    const WIDTH: u32 = 16;
+    const HEIGHT: u32 = 12;
+
+    /// Guides of a plane facing the camera, with the albedo and normal given
+    /// by which half of the image a pixel is in.
+    fn guides(albedo: [Color; 2], normal: [Vec3; 2]) -> Vec<AovSample> {
+        (0..WIDTH * HEIGHT)
+            .map(|index| {
+                let half = ((index % WIDTH) >= WIDTH / 2) as usize;
+                AovSample {
+                    albedo: albedo[half],
+                    normal: normal[half],
+                    depth: 2.0,
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    fn pixels(color: [Color; 2]) -> Vec<Color> {
+        (0..WIDTH * HEIGHT)
+            .map(|index| color[((index % WIDTH) >= WIDTH / 2) as usize])
+            .collect()
+    }
+
+    fn assert_close(a: &[Color], b: &[Color]) {
+        for (a, b) in a.iter().zip(b) {
+            assert!((*a - *b).length() < 1e-9, "{a:?} vs {b:?}");
+        }
+    }
+
+    #[test]
+    fn test_constant_image_is_unchanged() {
+        let up = Vec3::new(0.0, 0.0, 1.0);
+        let albedo = Color::new(0.5, 0.4, 0.3);
+        let input = pixels([Color::new(0.2, 0.3, 0.4); 2]);
+        let output = denoise(&input, &guides([albedo; 2], [up; 2]), WIDTH, HEIGHT);
+        assert_close(&output, &input);
+    }
+
+    #[test]
+    fn test_normal_edges_are_kept() {
+        // close enough in color to be blurred together on one surface
+        let input = pixels([Color::new(0.5, 0.5, 0.5), Color::new(0.6, 0.6, 0.6)]);
+        let normals = [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0)];
+        let output = denoise(&input, &guides([Color::ones(); 2], normals), WIDTH, HEIGHT);
+        assert_close(&output, &input);
+
+        let output = denoise(
+            &input,
+            &guides([Color::ones(); 2], [normals[1]; 2]),
+            WIDTH,
+            HEIGHT,
+        );
+        let (left, right) = (output[WIDTH as usize / 2 - 1], output[WIDTH as usize / 2]);
+        assert!(left.x > 0.51 && right.x < 0.59);
+    }
+
+    #[test]
+    fn test_albedo_edges_are_kept() {
+        // evenly lit, the image is its albedo
+        let up = Vec3::new(0.0, 0.0, 1.0);
+        let albedo = [Color::new(0.1, 0.2, 0.1), Color::new(0.8, 0.7, 0.9)];
+        let input = pixels(albedo);
+        let output = denoise(&input, &guides(albedo, [up; 2]), WIDTH, HEIGHT);
+        assert_close(&output, &input);
+    }
+}
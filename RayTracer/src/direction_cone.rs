@@ -0,0 +1,86 @@
+use crate::vec3::Vec3;
+use std::f64::consts::PI;
+
+/// A cone of directions around `w`, containing every direction within
+/// `acos(cos_theta)` of it.
+#[derive(Clone, Copy)]
+pub struct DirectionCone {
+    pub w: Vec3,
+    pub cos_theta: f64,
+}
+
+impl DirectionCone {
+    pub fn new(w: &Vec3, cos_theta: f64) -> Self {
+        Self {
+            w: w.unit(),
+            cos_theta,
+        }
+    }
+
+    pub fn from_direction(w: &Vec3) -> Self {
+        Self::new(w, 1.0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cos_theta == f64::INFINITY
+    }
+
+    pub fn from_cones(a: &Self, b: &Self) -> Self {
+        if a.is_empty() {
+            return *b;
+        }
+        if b.is_empty() {
+            return *a;
+        }
+
+        let theta_a = safe_acos(a.cos_theta);
+        let theta_b = safe_acos(b.cos_theta);
+        let theta_d = safe_acos(a.w * b.w);
+        if (theta_d + theta_b).min(PI) <= theta_a {
+            return *a;
+        }
+        if (theta_d + theta_a).min(PI) <= theta_b {
+            return *b;
+        }
+
+        let theta_o = (theta_a + theta_d + theta_b) / 2.0;
+        if theta_o >= PI {
+            return Self::ENTIRE_SPHERE;
+        }
+
+        let axis = a.w.cross(&b.w);
+        if axis.squared_length() == 0.0 {
+            return Self::ENTIRE_SPHERE;
+        }
+        let w = rotate(&a.w, &axis.unit(), theta_o - theta_a);
+
+        Self::new(&w, theta_o.cos())
+    }
+
+    pub const EMPTY: Self = Self {
+        w: Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        },
+        cos_theta: f64::INFINITY,
+    };
+    pub const ENTIRE_SPHERE: Self = Self {
+        w: Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        },
+        cos_theta: -1.0,
+    };
+}
+
+fn safe_acos(x: f64) -> f64 {
+    x.clamp(-1.0, 1.0).acos()
+}
+
+/// Rodrigues' rotation of `v` by `theta` radians around the unit vector `axis`.
+fn rotate(v: &Vec3, axis: &Vec3, theta: f64) -> Vec3 {
+    let (sin_theta, cos_theta) = theta.sin_cos();
+    *v * cos_theta + axis.cross(v) * sin_theta + *axis * (*axis * *v) * (1.0 - cos_theta)
+}
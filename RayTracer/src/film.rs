@@ -0,0 +1,178 @@
+use crate::{
+    color::{self, Color},
+    color_space::OutputTransform,
+    filter::Filter,
+};
+use image::DynamicImage;
+use std::sync::Arc;
+
+/// Accumulates radiance samples, weighted by the reconstruction filter onto
+/// every pixel within its radius, plus contributions splatted onto arbitrary
+/// pixels such as those found by tracing paths from the lights.
+///
+/// The weighted sums are normalized by the filter integral and the number of
+/// samples rather than by the sum of the weights (pbrt-v4), which filters
+/// with negative lobes can bring to zero or below.
+pub struct Film {
+    width: u32,
+    height: u32,
+    filter: Arc<dyn Filter>,
+    filter_integral: f64,
+    pixels: Vec<Color>,
+    splats: Vec<Color>,
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32, filter: &Arc<dyn Filter>) -> Self {
+        let size = (width * height) as usize;
+        Self {
+            width,
+            height,
+            filter: filter.clone(),
+            filter_integral: filter.integral(),
+            pixels: vec![Color::zeros(); size],
+            splats: vec![Color::zeros(); size],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Adds a sample taken at film position (`x`, `y`), in pixels from the
+    /// top left corner, so that pixel (i, j) has its center at (i + 0.5, j + 0.5).
+    ///
+    /// Samples near an edge are also added mirrored across it, standing in
+    /// for the samples beyond the film that the pixels there would get.
+    pub fn add_sample(&mut self, x: f64, y: f64, color: &Color) {
+        let radius = self.filter.radius();
+        for x in mirrored(x, self.width as f64, radius) {
+            for y in mirrored(y, self.height as f64, radius) {
+                self.add_weighted(x, y, color);
+            }
+        }
+    }
+
+    fn add_weighted(&mut self, x: f64, y: f64, color: &Color) {
+        let radius = self.filter.radius();
+        let (x, y) = (x - 0.5, y - 0.5);
+        let i_min = (x - radius).ceil().max(0.0) as u32;
+        let j_min = (y - radius).ceil().max(0.0) as u32;
+        let i_max = ((x + radius).floor() as i64).min(self.width as i64 - 1);
+        let j_max = ((y + radius).floor() as i64).min(self.height as i64 - 1);
+        if i_max < 0 || j_max < 0 {
+            return;
+        }
+
+        for j in j_min..=j_max as u32 {
+            for i in i_min..=i_max as u32 {
+                let weight = self.filter.evaluate(i as f64 - x, j as f64 - y);
+                if weight != 0.0 {
+                    let index = self.index(i, j);
+                    self.pixels[index] += *color * weight;
+                }
+            }
+        }
+    }
+
+    pub fn add_splat(&mut self, i: u32, j: u32, color: &Color) {
+        let index = self.index(i, j);
+        self.splats[index] += *color;
+    }
+
+    /// Adds up the samples and splats of a film of the same size, e.g. one
+    /// filled by a single render thread.
+    pub fn merge(&mut self, other: &Film) {
+        for index in 0..self.pixels.len() {
+            self.pixels[index] += other.pixels[index];
+            self.splats[index] += other.splats[index];
+        }
+    }
+
+    /// The radiance estimate of every pixel, `scale` being one over the
+    /// number of samples taken per pixel.
+    pub fn resolve(&self, scale: f64) -> Vec<Color> {
+        let sample_scale = scale / self.filter_integral;
+        (0..self.pixels.len())
+            .map(|index| self.pixels[index] * sample_scale + self.splats[index] * scale)
+            .collect()
+    }
+
+    fn index(&self, i: u32, j: u32) -> usize {
+        (j * self.width + i) as usize
+    }
+}
+
+/// `x` and its mirror images across the edges of [0, `size`] it is within
+/// `radius` of.
+fn mirrored(x: f64, size: f64, radius: f64) -> impl Iterator<Item = f64> {
+    [
+        Some(x),
+        (x < radius).then_some(-x),
+        (x > size - radius).then_some(2.0 * size - x),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+/// Writes radiance values, one per pixel in row order, into an image.
+pub fn to_image(
+    pixels: &[Color],
+    width: u32,
+    height: u32,
+    transform: OutputTransform,
+) -> DynamicImage {
+    let mut img = DynamicImage::new_rgb8(width, height);
+    for j in 0..height {
+        for i in 0..width {
+            color::write_color(&pixels[(j * width + i) as usize], transform, &mut img, i, j);
+        }
+    }
+    img
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{BoxFilter, LanczosFilter, MitchellFilter};
+
+    /// A film of constant radiance `c`, stratified samples per pixel.
+    fn constant_film(filter: &Arc<dyn Filter>, c: &Color, sqrt_spp: u32) -> Vec<Color> {
+        let (width, height) = (8, 6);
+        let mut film = Film::new(width, height, filter);
+        for j in 0..height {
+            for i in 0..width {
+                for s_j in 0..sqrt_spp {
+                    for s_i in 0..sqrt_spp {
+                        let x = i as f64 + (s_i as f64 + 0.5) / sqrt_spp as f64;
+                        let y = j as f64 + (s_j as f64 + 0.5) / sqrt_spp as f64;
+                        film.add_sample(x, y, c);
+                    }
+                }
+            }
+        }
+        film.resolve(1.0 / (sqrt_spp * sqrt_spp) as f64)
+    }
+
+    #[test]
+    fn test_negative_lobes_keep_pixels() {
+        let c = Color::new(0.2, 0.5, 1.0);
+        let filters: [Arc<dyn Filter>; 3] = [
+            Arc::new(BoxFilter::default()),
+            Arc::new(MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0)),
+            Arc::new(LanczosFilter::new(3.0, 3.0)),
+        ];
+        for filter in &filters {
+            // a single sample per pixel is where weight sums can cancel out
+            for sqrt_spp in [1, 4] {
+                for pixel in constant_film(filter, &c, sqrt_spp) {
+                    assert!((pixel - c).length() < 0.05 * c.length());
+                }
+            }
+        }
+    }
+}
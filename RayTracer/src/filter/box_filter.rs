@@ -0,0 +1,32 @@
+use super::Filter;
+
+pub struct BoxFilter {
+    radius: f64,
+}
+
+impl BoxFilter {
+    pub fn new(radius: f64) -> Self {
+        Self { radius }
+    }
+}
+
+impl Default for BoxFilter {
+    /// Averages the samples taken within each pixel.
+    fn default() -> Self {
+        Self::new(0.5)
+    }
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, _x: f64, _y: f64) -> f64 {
+        1.0
+    }
+
+    fn integral(&self) -> f64 {
+        (2.0 * self.radius).powi(2)
+    }
+}
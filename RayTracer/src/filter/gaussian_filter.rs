@@ -0,0 +1,33 @@
+use super::Filter;
+
+pub struct GaussianFilter {
+    radius: f64,
+    alpha: f64,
+    /// the gaussian at `radius`, subtracted so the filter falls to zero there
+    edge: f64,
+}
+
+impl GaussianFilter {
+    /// `alpha` is the falloff rate, exp(-alpha * x^2).
+    pub fn new(radius: f64, alpha: f64) -> Self {
+        Self {
+            radius,
+            alpha,
+            edge: (-alpha * radius * radius).exp(),
+        }
+    }
+
+    fn gaussian(&self, x: f64) -> f64 {
+        ((-self.alpha * x * x).exp() - self.edge).max(0.0)
+    }
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.gaussian(x) * self.gaussian(y)
+    }
+}
@@ -0,0 +1,84 @@
+mod box_filter;
+mod gaussian_filter;
+mod lanczos_filter;
+mod mitchell_filter;
+mod tent_filter;
+
+pub use box_filter::BoxFilter;
+pub use gaussian_filter::GaussianFilter;
+pub use lanczos_filter::LanczosFilter;
+pub use mitchell_filter::MitchellFilter;
+pub use tent_filter::TentFilter;
+
+/// Pixel reconstruction filter, weighting a sample by its offset in pixels
+/// from the center of the pixel it is splatted onto.
+pub trait Filter: Send + Sync {
+    /// Offsets beyond this (along either axis) get no weight.
+    fn radius(&self) -> f64;
+    fn evaluate(&self, x: f64, y: f64) -> f64;
+
+    /// Integral over the support, which the film divides by, worked out
+    /// numerically unless a filter knows it.
+    fn integral(&self) -> f64 {
+        const STEPS: usize = 256;
+        let radius = self.radius();
+        let step = 2.0 * radius / STEPS as f64;
+        let mut sum = 0.0;
+        for j in 0..STEPS {
+            let y = -radius + (j as f64 + 0.5) * step;
+            for i in 0..STEPS {
+                sum += self.evaluate(-radius + (i as f64 + 0.5) * step, y);
+            }
+        }
+        sum * step * step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters() -> Vec<Box<dyn Filter>> {
+        vec![
+            Box::new(BoxFilter::new(0.5)),
+            Box::new(TentFilter::new(1.5)),
+            Box::new(GaussianFilter::new(1.5, 2.0)),
+            Box::new(MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0)),
+            Box::new(LanczosFilter::new(3.0, 3.0)),
+        ]
+    }
+
+    #[test]
+    fn test_normalized_filters_integrate_to_one() {
+        const STEPS: usize = 600;
+        for filter in filters() {
+            let radius = filter.radius();
+            let step = 2.0 * radius / STEPS as f64;
+            let mut sum = 0.0;
+            for j in 0..STEPS {
+                for i in 0..STEPS {
+                    let x = -radius + (i as f64 + 0.5) * step;
+                    let y = -radius + (j as f64 + 0.5) * step;
+                    sum += filter.evaluate(x, y);
+                }
+            }
+            let normalized = sum * step * step / filter.integral();
+            assert!((normalized - 1.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_filters_are_symmetric() {
+        for filter in filters() {
+            let radius = filter.radius();
+            for (x, y) in [(0.1, 0.3), (0.4, 0.0), (0.7, 1.1), (1.3, 0.2)] {
+                let (x, y) = (x * radius / 1.5, y * radius / 1.5);
+                let value = filter.evaluate(x, y);
+                assert_eq!(filter.evaluate(-x, y), value);
+                assert_eq!(filter.evaluate(x, -y), value);
+                assert!((filter.evaluate(y, x) - value).abs() < 1e-12);
+            }
+            assert!(filter.evaluate(0.0, 0.0) > 0.0);
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use super::{hittable_list::HittableList, HitRecord, Hittable};
-use crate::{aabb::Aabb, interval::Interval, ray::Ray};
+use crate::{aabb::Aabb, interval::Interval, material::Material, ray::Ray};
 use std::{cmp::Ordering, sync::Arc};
 
 pub struct BvhNode {
@@ -11,8 +11,8 @@ pub struct BvhNode {
 impl BvhNode {
     fn from_objects(objects: &mut Vec<Arc<dyn Hittable>>, start: usize, end: usize) -> Self {
         let mut bbox = Aabb::EMPTY;
-        for object_index in start..end {
-            bbox = Aabb::from_aabbs(&bbox, &objects[object_index].bounding_box());
+        for object in &objects[start..end] {
+            bbox = Aabb::from_aabbs(&bbox, &object.bounding_box());
         }
 
         let axis = bbox.longest_axis();
@@ -71,6 +71,11 @@ impl Hittable for BvhNode {
             false
         }
     }
+
+    fn for_each_material(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        self.left.for_each_material(f);
+        self.right.for_each_material(f);
+    }
 }
 
 fn box_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>, axis_index: u8) -> Ordering {
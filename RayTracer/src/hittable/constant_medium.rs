@@ -7,7 +7,7 @@ use crate::{
     rtweekend,
     vec3::Vec3,
 };
-use std::{f64::INFINITY, sync::Arc};
+use std::sync::Arc;
 
 pub struct ConstantMedium {
     boundary: Arc<dyn Hittable>,
@@ -49,7 +49,7 @@ impl Hittable for ConstantMedium {
         if !self.boundary.hit(r, &Interval::UNIVERSE, &mut rec1)
             || !self
                 .boundary
-                .hit(r, &Interval::new(rec1.t + 0.0001, INFINITY), &mut rec2)
+                .hit(r, &Interval::new(rec1.t + 0.0001, f64::INFINITY), &mut rec2)
         {
             return false;
         }
@@ -82,7 +82,12 @@ impl Hittable for ConstantMedium {
         rec.normal = Vec3::new(1.0, 0.0, 0.0);
         rec.front_face = true;
         rec.mat = Some(self.phase_function.clone());
+        rec.time = r.time();
 
         true
     }
+
+    fn for_each_material(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        f(&self.phase_function);
+    }
 }
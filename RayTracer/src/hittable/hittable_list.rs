@@ -1,11 +1,22 @@
 use super::{HitRecord, Hittable};
-use crate::{aabb::Aabb, interval::Interval, ray::Ray};
-use std::sync::Arc;
+use crate::{
+    aabb::Aabb,
+    direction_cone::DirectionCone,
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    rtweekend,
+    vec3::{Point3, Vec3},
+};
+use std::{collections::HashMap, sync::Arc};
 
 #[derive(Clone, Default)]
 pub struct HittableList {
     pub objects: Vec<Arc<dyn Hittable>>,
     bbox: Aabb,
+    /// numbers of the materials of the objects in the order they were added,
+    /// keyed by address, which stays unique while the objects keep them alive
+    material_ids: HashMap<usize, u32>,
 }
 
 impl HittableList {
@@ -22,6 +33,19 @@ impl HittableList {
     pub fn add(&mut self, object: &Arc<dyn Hittable>) {
         self.objects.push(object.clone());
         self.bbox = Aabb::from_aabbs(&self.bbox, &object.bounding_box());
+        object.for_each_material(&mut |mat| {
+            let next_id = self.material_ids.len() as u32;
+            self.material_ids
+                .entry(Arc::as_ptr(mat) as *const () as usize)
+                .or_insert(next_id);
+        });
+    }
+
+    /// The number of `mat` among the materials of the list, the same from run
+    /// to run of a scene built alike, `None` for materials not in the list.
+    pub fn material_id(&self, mat: &Arc<dyn Material>) -> Option<u32> {
+        let address = Arc::as_ptr(mat) as *const () as usize;
+        self.material_ids.get(&address).copied()
     }
 }
 
@@ -35,14 +59,93 @@ impl Hittable for HittableList {
         let mut hit_anything = false;
         let mut closest_so_far = ray_t.max;
 
-        for object in &self.objects {
+        for (index, object) in self.objects.iter().enumerate() {
             if object.hit(r, &Interval::new(ray_t.min, closest_so_far), &mut temp_rec) {
                 hit_anything = true;
                 closest_so_far = temp_rec.t;
                 *rec = temp_rec.clone();
+                rec.object_id = index as u32;
             }
         }
+        if hit_anything {
+            rec.time = r.time();
+        }
 
         hit_anything
     }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let weight = 1.0 / self.objects.len() as f64;
+
+        self.objects
+            .iter()
+            .map(|object| weight * object.pdf_value(origin, direction))
+            .sum()
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        let index = rtweekend::random_int_in_range(0, self.objects.len() as i32) as usize;
+        self.objects[index].random(origin)
+    }
+
+    fn area(&self) -> f64 {
+        self.objects.iter().map(|object| object.area()).sum()
+    }
+
+    fn random_point(&self) -> Point3 {
+        let mut target = rtweekend::random_double() * self.area();
+        for object in &self.objects {
+            let area = object.area();
+            if target < area {
+                return object.random_point();
+            }
+            target -= area;
+        }
+
+        match self.objects.last() {
+            Some(object) => object.random_point(),
+            None => Point3::zeros(),
+        }
+    }
+
+    fn normal_cone(&self) -> DirectionCone {
+        self.objects
+            .iter()
+            .fold(DirectionCone::EMPTY, |cone, object| {
+                DirectionCone::from_cones(&cone, &object.normal_cone())
+            })
+    }
+
+    fn for_each_material(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        for object in &self.objects {
+            object.for_each_material(f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::Color, hittable::Sphere, material::Lambertian};
+
+    #[test]
+    fn test_material_ids_follow_scene_order() {
+        let red: Arc<dyn Material> = Arc::new(Lambertian::from_color(&Color::new(1.0, 0.0, 0.0)));
+        let blue: Arc<dyn Material> = Arc::new(Lambertian::from_color(&Color::new(0.0, 0.0, 1.0)));
+        let sphere = |x: f64, mat: &Arc<dyn Material>| -> Arc<dyn Hittable> {
+            Arc::new(Sphere::new(&Point3::new(x, 0.0, 0.0), 1.0, mat))
+        };
+
+        let mut inner = HittableList::default();
+        inner.add(&sphere(0.0, &blue));
+        inner.add(&sphere(3.0, &red));
+        let mut world = HittableList::default();
+        world.add(&sphere(-3.0, &red));
+        world.add(&(Arc::new(inner) as Arc<dyn Hittable>));
+
+        assert_eq!(world.material_id(&red), Some(0));
+        assert_eq!(world.material_id(&blue), Some(1));
+        let other: Arc<dyn Material> = Arc::new(Lambertian::from_color(&Color::ones()));
+        assert_eq!(world.material_id(&other), None);
+    }
 }
@@ -4,21 +4,28 @@ mod hittable_list;
 mod quad;
 mod rotate_y;
 mod sphere;
+mod subsurface;
 mod translate;
+mod triangle;
 
 pub use bvh::BvhNode;
 pub use constant_medium::ConstantMedium;
 pub use hittable_list::HittableList;
-pub use quad::{get_box, Quad};
+pub use quad::{get_box, get_displaced_quad, Quad};
 pub use rotate_y::RotateY;
 pub use sphere::Sphere;
+pub use subsurface::Subsurface;
 pub use translate::Translate;
+pub use triangle::Triangle;
 
 use crate::{
     aabb::Aabb,
+    direction_cone::DirectionCone,
     interval::Interval,
     material::Material,
+    onb::Onb,
     ray::Ray,
+    rtweekend,
     vec3::{Point3, Vec3},
 };
 use std::sync::Arc;
@@ -35,6 +42,23 @@ pub trait Hittable: Send + Sync {
     fn random(&self, origin: &Point3) -> Vec3 {
         Vec3::new(1.0, 0.0, 0.0)
     }
+
+    fn area(&self) -> f64 {
+        0.0
+    }
+
+    /// A point uniformly distributed over the surface area.
+    fn random_point(&self) -> Point3 {
+        Point3::zeros()
+    }
+
+    /// Bounds the outward normals of the surface, used to orient emitters.
+    fn normal_cone(&self) -> DirectionCone {
+        DirectionCone::ENTIRE_SPHERE
+    }
+
+    /// Calls `f` with every material hits on the object can report.
+    fn for_each_material(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {}
 }
 
 #[derive(Clone, Default)]
@@ -46,6 +70,25 @@ pub struct HitRecord {
     pub u: f64,
     pub v: f64,
     pub front_face: bool,
+    /// index of the object hit within the outermost `HittableList`
+    pub object_id: u32,
+    /// time of the ray that hit, for animated textures
+    pub time: f64,
+    /// partial derivatives of the point with respect to u and v
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    /// unit shading frame around the normal, the tangent along dpdu and the
+    /// bitangent on the side of dpdv
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    /// change of the point, u and v to the neighbouring pixels, zero without
+    /// ray differentials
+    pub dpdx: Vec3,
+    pub dpdy: Vec3,
+    pub dudx: f64,
+    pub dvdx: f64,
+    pub dudy: f64,
+    pub dvdy: f64,
 }
 
 impl HitRecord {
@@ -57,4 +100,91 @@ impl HitRecord {
             -*outward_normal
         }
     }
+
+    /// Whether the surface is there at this hit of `r`, drawn against the
+    /// alpha of its material so that rays pass through cutouts, and through
+    /// partly transparent surfaces in proportion. Rays ignoring alpha always
+    /// pass.
+    pub fn passes_alpha_test(&self, r: &Ray) -> bool {
+        let Some(mat) = &self.mat else {
+            return true;
+        };
+        if r.ignores_alpha() {
+            return true;
+        }
+        let alpha = mat.alpha(self);
+        alpha >= 1.0 || (alpha > 0.0 && rtweekend::random_double() < alpha)
+    }
+
+    /// The normal of the surface hit, zero for scattering inside a medium,
+    /// which has no surface to take cosines at or orient light sampling by.
+    pub fn surface_normal(&self) -> Vec3 {
+        match &self.mat {
+            Some(mat) if mat.is_volumetric() => Vec3::zeros(),
+            _ => self.normal,
+        }
+    }
+
+    /// Fills in what shading reads beyond the geometry: the tangent frame and
+    /// the footprint of `r`. Integrators call it once for the hits they shade,
+    /// so that shadow rays and probes don't pay for it.
+    pub fn prepare_shading(&mut self, r: &Ray) {
+        self.compute_differentials(r);
+        self.set_tangent_frame();
+    }
+
+    /// Builds the tangent frame from dpdu, or around the normal alone when the
+    /// primitive has no parameterization.
+    pub fn set_tangent_frame(&mut self) {
+        let n = self.normal;
+        let tangent = self.dpdu - n * (n * self.dpdu);
+        self.tangent = if tangent.squared_length() > 1e-16 {
+            tangent.unit()
+        } else {
+            let mut uvw = Onb::new();
+            uvw.build_from_w(&n);
+            uvw[0]
+        };
+        self.bitangent = n.cross(&self.tangent);
+        if self.bitangent * self.dpdv < 0.0 {
+            self.bitangent = -self.bitangent;
+        }
+    }
+
+    /// Finds where the differentials of `r` meet the tangent plane at the hit
+    /// point and solves for the change of u and v there (pbrt).
+    pub fn compute_differentials(&mut self, r: &Ray) {
+        (self.dpdx, self.dpdy) = (Vec3::zeros(), Vec3::zeros());
+        (self.dudx, self.dvdx, self.dudy, self.dvdy) = (0.0, 0.0, 0.0, 0.0);
+        let Some(differentials) = r.differentials() else {
+            return;
+        };
+
+        let n = self.normal;
+        let plane_d = n * self.p;
+        let tx = (plane_d - n * differentials.rx_origin) / (n * differentials.rx_direction);
+        let ty = (plane_d - n * differentials.ry_origin) / (n * differentials.ry_direction);
+        if !tx.is_finite() || !ty.is_finite() {
+            return;
+        }
+        self.dpdx = differentials.rx_origin + differentials.rx_direction * tx - self.p;
+        self.dpdy = differentials.ry_origin + differentials.ry_direction * ty - self.p;
+
+        // least squares solution of dpdx = dpdu dudx + dpdv dvdx, and alike for y
+        let ata00 = self.dpdu * self.dpdu;
+        let ata01 = self.dpdu * self.dpdv;
+        let ata11 = self.dpdv * self.dpdv;
+        let inv_det = 1.0 / (ata00 * ata11 - ata01 * ata01);
+        if !inv_det.is_finite() {
+            return;
+        }
+        let solve = |dp: Vec3| {
+            let (atb0, atb1) = (self.dpdu * dp, self.dpdv * dp);
+            let du = (ata11 * atb0 - ata01 * atb1) * inv_det;
+            let dv = (ata00 * atb1 - ata01 * atb0) * inv_det;
+            (du.clamp(-1e8, 1e8), dv.clamp(-1e8, 1e8))
+        };
+        (self.dudx, self.dvdx) = solve(self.dpdx);
+        (self.dudy, self.dvdy) = solve(self.dpdy);
+    }
 }
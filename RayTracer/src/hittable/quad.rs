@@ -1,13 +1,15 @@
-use super::{HitRecord, Hittable, HittableList};
+use super::{BvhNode, HitRecord, Hittable, HittableList, Triangle};
 use crate::{
     aabb::Aabb,
+    direction_cone::DirectionCone,
     interval::Interval,
     material::Material,
     ray::Ray,
     rtweekend,
+    texture::Texture,
     vec3::{Point3, Vec3},
 };
-use std::{f64::INFINITY, sync::Arc};
+use std::sync::Arc;
 
 pub struct Quad {
     q: Point3,
@@ -23,7 +25,7 @@ pub struct Quad {
 
 impl Quad {
     pub fn new(q: &Point3, u: &Vec3, v: &Vec3, mat: &Arc<dyn Material>) -> Self {
-        let n = u.cross(&v);
+        let n = u.cross(v);
         let normal = n.unit();
         let d = normal * *q;
         let w = n / (n * n);
@@ -66,13 +68,24 @@ impl Hittable for Quad {
                 let alpha = self.w * planar_hitpt_vector.cross(&self.v);
                 let beta = self.w * self.u.cross(&planar_hitpt_vector);
 
-                if is_interiior(alpha, beta, rec) {
-                    rec.t = t;
-                    rec.p = intersection;
-                    rec.mat = Some(self.mat.clone());
-                    rec.set_face_normal(r, &self.normal);
+                // filled in apart, so that a hit failing the alpha test
+                // leaves rec alone
+                let mut hit_rec = HitRecord::default();
+                if is_interiior(alpha, beta, &mut hit_rec) {
+                    hit_rec.t = t;
+                    hit_rec.p = intersection;
+                    hit_rec.time = r.time();
+                    hit_rec.mat = Some(self.mat.clone());
+                    hit_rec.set_face_normal(r, &self.normal);
+                    hit_rec.dpdu = self.u;
+                    hit_rec.dpdv = self.v;
 
-                    true
+                    if hit_rec.passes_alpha_test(r) {
+                        *rec = hit_rec;
+                        true
+                    } else {
+                        false
+                    }
                 } else {
                     false
                 }
@@ -85,8 +98,8 @@ impl Hittable for Quad {
     fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
         let mut rec = HitRecord::default();
         if self.hit(
-            &Ray::new(origin, direction),
-            &Interval::new(0.001, INFINITY),
+            &Ray::new(origin, direction).ignoring_alpha(),
+            &Interval::new(0.001, f64::INFINITY),
             &mut rec,
         ) {
             let distance_squared = rec.t.powi(2) * direction.squared_length();
@@ -99,9 +112,23 @@ impl Hittable for Quad {
     }
 
     fn random(&self, origin: &Point3) -> Vec3 {
-        let p =
-            self.q + (self.u * rtweekend::random_double()) + (self.v * rtweekend::random_double());
-        p - *origin
+        self.random_point() - *origin
+    }
+
+    fn for_each_material(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        f(&self.mat);
+    }
+
+    fn area(&self) -> f64 {
+        self.area
+    }
+
+    fn random_point(&self) -> Point3 {
+        self.q + (self.u * rtweekend::random_double()) + (self.v * rtweekend::random_double())
+    }
+
+    fn normal_cone(&self) -> DirectionCone {
+        DirectionCone::from_direction(&self.normal)
     }
 }
 
@@ -154,3 +181,110 @@ pub fn get_box(a: &Point3, b: &Point3, mat: &Arc<dyn Material>) -> Arc<HittableL
 
     Arc::new(sides)
 }
+
+/// The quad at `q` spanned by `u` and `v`, diced into a `resolution` by
+/// `resolution` grid of triangle pairs whose vertices are pushed along the
+/// normal u x v by `scale` times the luminance of `height`, for displacement that
+/// changes silhouettes and shadows unlike a bump map.
+pub fn get_displaced_quad(
+    q: &Point3,
+    u: &Vec3,
+    v: &Vec3,
+    resolution: usize,
+    height: &Arc<dyn Texture>,
+    scale: f64,
+    mat: &Arc<dyn Material>,
+) -> Arc<BvhNode> {
+    let resolution = resolution.max(1);
+    let normal = u.cross(v).unit();
+    let vertex = |i: usize, j: usize| {
+        let (s, t) = (i as f64 / resolution as f64, j as f64 / resolution as f64);
+        let p = *q + *u * s + *v * t;
+        let displacement = height.value(s, t, &p).luminance() * scale;
+        (p + normal * displacement, (s, t))
+    };
+
+    let mut triangles = HittableList::default();
+    for j in 0..resolution {
+        for i in 0..resolution {
+            let (p00, uv00) = vertex(i, j);
+            let (p10, uv10) = vertex(i + 1, j);
+            let (p01, uv01) = vertex(i, j + 1);
+            let (p11, uv11) = vertex(i + 1, j + 1);
+            triangles.add(
+                &(Arc::new(Triangle::new_with_uvs(
+                    &p00,
+                    &p10,
+                    &p11,
+                    &[uv00, uv10, uv11],
+                    mat,
+                )) as Arc<dyn Hittable>),
+            );
+            triangles.add(
+                &(Arc::new(Triangle::new_with_uvs(
+                    &p00,
+                    &p11,
+                    &p01,
+                    &[uv00, uv11, uv01],
+                    mat,
+                )) as Arc<dyn Hittable>),
+            );
+        }
+    }
+
+    Arc::new(BvhNode::from_hittable_list(&mut triangles))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::Color,
+        material::{AlphaMasked, Lambertian},
+        texture::SolidColor,
+    };
+
+    fn quad_at(z: f64, alpha: f64) -> Arc<dyn Hittable> {
+        let lambertian: Arc<dyn Material> = Arc::new(Lambertian::from_color(&Color::ones()));
+        let mask: Arc<dyn Texture> = Arc::new(SolidColor::new(&Color::new(alpha, alpha, alpha)));
+        let mat: Arc<dyn Material> = Arc::new(AlphaMasked::new(&lambertian, &mask));
+        Arc::new(Quad::new(
+            &Point3::new(-1.0, -1.0, z),
+            &Vec3::new(2.0, 0.0, 0.0),
+            &Vec3::new(0.0, 2.0, 0.0),
+            &mat,
+        ))
+    }
+
+    #[test]
+    fn test_cutout_hit_leaves_record_alone() {
+        let r = Ray::new(&Point3::zeros(), &Vec3::new(0.0, 0.0, 1.0));
+        let mut rec = HitRecord {
+            t: 7.0,
+            ..Default::default()
+        };
+        assert!(!quad_at(1.0, 0.0).hit(&r, &Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert_eq!(rec.t, 7.0);
+        assert!(rec.mat.is_none());
+    }
+
+    #[test]
+    fn test_bvh_keeps_hit_behind_cutout() {
+        let mut list = HittableList::default();
+        list.add(&quad_at(2.0, 1.0));
+        list.add(&quad_at(1.0, 0.0));
+        let bvh = BvhNode::from_hittable_list(&mut list);
+
+        let r = Ray::new(&Point3::zeros(), &Vec3::new(0.0, 0.0, 1.0));
+        let mut rec = HitRecord::default();
+        assert!(bvh.hit(&r, &Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert!((rec.t - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pdf_ignores_cutouts() {
+        let quad = quad_at(1.0, 0.0);
+        let pdf = quad.pdf_value(&Point3::zeros(), &Vec3::new(0.0, 0.0, 1.0));
+        assert!((pdf - 0.25).abs() < 1e-9);
+    }
+}
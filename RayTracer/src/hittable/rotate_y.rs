@@ -1,14 +1,13 @@
 use super::{HitRecord, Hittable};
 use crate::{
     aabb::Aabb,
+    direction_cone::DirectionCone,
     interval::Interval,
+    material::Material,
     ray::Ray,
     vec3::{Point3, Vec3},
 };
-use std::{
-    f64::{INFINITY, NEG_INFINITY},
-    sync::Arc,
-};
+use std::sync::Arc;
 
 pub struct RotateY {
     object: Arc<dyn Hittable>,
@@ -24,8 +23,8 @@ impl RotateY {
         let cos_theta = radians.cos();
         let bbox = object.bounding_box();
 
-        let mut min = Point3::new(INFINITY, INFINITY, INFINITY);
-        let mut max = Point3::new(NEG_INFINITY, NEG_INFINITY, NEG_INFINITY);
+        let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
 
         for i in 0..2 {
             for j in 0..2 {
@@ -54,6 +53,22 @@ impl RotateY {
             bbox: Aabb::from_endpoints(&min, &max),
         }
     }
+
+    fn to_object(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x - self.sin_theta * v.z,
+            v.y,
+            self.sin_theta * v.x + self.cos_theta * v.z,
+        )
+    }
+
+    fn to_world(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x + self.sin_theta * v.z,
+            v.y,
+            -self.sin_theta * v.x + self.cos_theta * v.z,
+        )
+    }
 }
 
 impl Hittable for RotateY {
@@ -71,7 +86,7 @@ impl Hittable for RotateY {
         direction.x = self.cos_theta * r.direction().x - self.sin_theta * r.direction().z;
         direction.z = self.sin_theta * r.direction().x + self.cos_theta * r.direction().z;
 
-        let rotated_r = Ray::new_with_time(&origin, &direction, r.time());
+        let rotated_r = r.transformed(&origin, &direction);
 
         if self.object.hit(&rotated_r, ray_t, rec) {
             let mut p = rec.p;
@@ -84,10 +99,40 @@ impl Hittable for RotateY {
 
             rec.p = p;
             rec.normal = normal;
+            rec.dpdu = self.to_world(&rec.dpdu);
+            rec.dpdv = self.to_world(&rec.dpdv);
 
             true
         } else {
             false
         }
     }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object
+            .pdf_value(&self.to_object(origin), &self.to_object(direction))
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.to_world(&self.object.random(&self.to_object(origin)))
+    }
+
+    fn area(&self) -> f64 {
+        self.object.area()
+    }
+
+    fn random_point(&self) -> Point3 {
+        self.to_world(&self.object.random_point())
+    }
+
+    fn normal_cone(&self) -> DirectionCone {
+        let cone = self.object.normal_cone();
+        DirectionCone {
+            w: self.to_world(&cone.w),
+            ..cone
+        }
+    }
+    fn for_each_material(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        self.object.for_each_material(f);
+    }
 }
@@ -3,6 +3,9 @@ use crate::{
     aabb::Aabb,
     interval::Interval,
     material::Material,
+    onb::Onb,
+    ray::Ray,
+    rtweekend,
     vec3::{Point3, Vec3},
 };
 use std::{f64::consts::PI, sync::Arc};
@@ -58,7 +61,7 @@ impl Hittable for Sphere {
         self.bbox
     }
 
-    fn hit(&self, r: &crate::ray::Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
+    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
         let center = if self.is_moving {
             self.sphere_center(r.time())
         } else {
@@ -76,23 +79,95 @@ impl Hittable for Sphere {
 
         let sqrtd = discriminant.sqrt();
 
-        let mut root = (h - sqrtd) / a;
-        if !ray_t.surrounds(root) {
-            root = (h + sqrtd) / a;
+        // the far side shows through where the near one is cut out
+        for root in [(h - sqrtd) / a, (h + sqrtd) / a] {
             if !ray_t.surrounds(root) {
-                return false;
+                continue;
+            }
+
+            // filled in apart, so that a hit failing the alpha test leaves rec alone
+            let mut hit_rec = HitRecord {
+                t: root,
+                p: r.at(root),
+                time: r.time(),
+                mat: Some(self.mat.clone()),
+                ..Default::default()
+            };
+            let outward_normal = (hit_rec.p - center) / self.raduis;
+            hit_rec.set_face_normal(r, &outward_normal);
+            get_sphere_uv(&outward_normal, &mut hit_rec.u, &mut hit_rec.v);
+            get_sphere_dpduv(
+                &outward_normal,
+                self.raduis,
+                &mut hit_rec.dpdu,
+                &mut hit_rec.dpdv,
+            );
+
+            if hit_rec.passes_alpha_test(r) {
+                *rec = hit_rec;
+                return true;
             }
         }
 
-        rec.t = root;
-        rec.p = r.at(root);
-        let outward_normal = (rec.p - center) / self.raduis;
-        rec.set_face_normal(r, &outward_normal);
-        get_sphere_uv(&outward_normal, &mut rec.u, &mut rec.v);
-        rec.mat = Some(self.mat.clone());
+        false
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let mut rec = HitRecord::default();
+        if self.hit(
+            &Ray::new(origin, direction).ignoring_alpha(),
+            &Interval::new(0.001, f64::INFINITY),
+            &mut rec,
+        ) {
+            let distance_squared = (self.center1 - *origin).squared_length();
+            let cos_theta_max = (1.0 - self.raduis.powi(2) / distance_squared).sqrt();
+            let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+
+            1.0 / solid_angle
+        } else {
+            0.0
+        }
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        let direction = self.center1 - *origin;
+        let distance_squared = direction.squared_length();
+        let mut uvw = Onb::new();
+        uvw.build_from_w(&direction);
+        uvw.local_with_vec3(&random_to_sphere(self.raduis, distance_squared))
+    }
 
-        true
+    fn for_each_material(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        f(&self.mat);
     }
+
+    fn area(&self) -> f64 {
+        4.0 * PI * self.raduis.powi(2)
+    }
+
+    fn random_point(&self) -> Point3 {
+        self.center1 + Vec3::random_unit_vector() * self.raduis
+    }
+}
+
+fn random_to_sphere(raduis: f64, distance_squared: f64) -> Vec3 {
+    let r1 = rtweekend::random_double();
+    let r2 = rtweekend::random_double();
+    let z = 1.0 + r2 * ((1.0 - raduis.powi(2) / distance_squared).sqrt() - 1.0);
+
+    let phi = 2.0 * PI * r1;
+    let x = phi.cos() * (1.0 - z * z).sqrt();
+    let y = phi.sin() * (1.0 - z * z).sqrt();
+
+    Vec3::new(x, y, z)
+}
+
+/// Derivatives of the point on the sphere with respect to the u and v of
+/// `get_sphere_uv`, at unit normal `n`.
+fn get_sphere_dpduv(n: &Vec3, raduis: f64, dpdu: &mut Vec3, dpdv: &mut Vec3) {
+    let sin_theta = (1.0 - n.y * n.y).max(0.0).sqrt().max(1e-8);
+    *dpdu = Vec3::new(n.z, 0.0, -n.x) * (2.0 * PI * raduis);
+    *dpdv = Vec3::new(-n.y * n.x / sin_theta, sin_theta, -n.y * n.z / sin_theta) * (PI * raduis);
 }
 
 fn get_sphere_uv(p: &Point3, u: &mut f64, v: &mut f64) {
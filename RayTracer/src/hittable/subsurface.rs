@@ -0,0 +1,231 @@
+use super::{HitRecord, Hittable};
+use crate::{
+    aabb::Aabb,
+    color::Color,
+    interval::Interval,
+    material::{Isotropic, Lambertian, Material, Metal},
+    ray::Ray,
+    rtweekend,
+    texture::{SolidColor, Texture},
+    vec3::{Point3, Vec3},
+};
+use std::sync::Arc;
+
+/// A translucent solid like marble, wax or skin, rendered by random walks
+/// (Chiang et al. 2016): light enters the closed `boundary`, whose own
+/// material is ignored, scatters isotropically inside it every
+/// `mean_free_path` on average, and leaves diffusely where the walk reaches
+/// the surface again, unless it is absorbed on the way. The surface reflects
+/// the walk back in by the same Fresnel reflectance as light arriving from
+/// outside, without total internal reflection.
+pub struct Subsurface {
+    boundary: Arc<dyn Hittable>,
+    mean_free_path: f64,
+    ior: f64,
+    /// the Fresnel reflection off the surface
+    reflection: Arc<dyn Material>,
+    /// diffuse transmission through the surface, into or out of the solid
+    transmission: Arc<dyn Material>,
+    phase_function: Arc<dyn Material>,
+}
+
+impl Subsurface {
+    /// `albedo` is the color the solid shows once light has scattered
+    /// through it many times, from which the albedo of single scattering
+    /// events is derived. `ior` sets how much light the smooth surface
+    /// reflects instead of letting it in.
+    pub fn new(
+        boundary: &Arc<dyn Hittable>,
+        mean_free_path: f64,
+        albedo: &Arc<dyn Texture>,
+        ior: f64,
+    ) -> Self {
+        let single_scattering: Arc<dyn Texture> = Arc::new(SingleScatteringAlbedo {
+            albedo: albedo.clone(),
+        });
+        Self {
+            boundary: boundary.clone(),
+            mean_free_path,
+            ior,
+            reflection: Arc::new(Metal::new(&Color::ones(), 0.0)),
+            transmission: Arc::new(Lambertian::from_color(&Color::ones())),
+            phase_function: Arc::new(Isotropic::new(&single_scattering)),
+        }
+    }
+
+    pub fn new_with_color(
+        boundary: &Arc<dyn Hittable>,
+        mean_free_path: f64,
+        albedo: &Color,
+        ior: f64,
+    ) -> Self {
+        let albedo: Arc<dyn Texture> = Arc::new(SolidColor::new(albedo));
+        Self::new(boundary, mean_free_path, &albedo, ior)
+    }
+}
+
+impl Subsurface {
+    /// Schlick's approximation of the Fresnel reflectance of the surface for
+    /// `r` hitting it at `rec`, whose normal faces the ray.
+    fn reflectance(&self, r: &Ray, rec: &HitRecord) -> f64 {
+        let cosine = (-r.direction().unit() * rec.normal).min(1.0);
+        let r0 = ((1.0 - self.ior) / (1.0 + self.ior)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+    }
+}
+
+impl Hittable for Subsurface {
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+
+    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
+        if !self.boundary.hit(r, ray_t, rec) {
+            return false;
+        }
+
+        // rays from outside reach the front of the boundary first, and are
+        // reflected or let in as the Fresnel reflectance picks
+        if rec.front_face {
+            if rtweekend::random_double() < self.reflectance(r, rec) {
+                rec.mat = Some(self.reflection.clone());
+            } else {
+                // scattering into the solid, around the inward normal
+                rec.normal = -rec.normal;
+                rec.mat = Some(self.transmission.clone());
+            }
+            return true;
+        }
+
+        // inside, the walk scatters before reaching the surface again when
+        // its exponentially distributed free flight is shorter
+        let ray_length = r.direction().length();
+        let distance = -self.mean_free_path * (1.0 - rtweekend::random_double()).ln();
+        let t = distance / ray_length;
+        if t >= rec.t || !ray_t.surrounds(t) {
+            // the walk reflects back in off the inside as often as light
+            // coming in at its angle is reflected off the outside
+            if rtweekend::random_double() < self.reflectance(r, rec) {
+                rec.mat = Some(self.reflection.clone());
+                return true;
+            }
+            // leaving around the outward normal, so lights can be sampled
+            rec.normal = -rec.normal;
+            rec.front_face = true;
+            rec.mat = Some(self.transmission.clone());
+            return true;
+        }
+
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.normal = -r.direction().unit();
+        rec.front_face = true;
+        rec.mat = Some(self.phase_function.clone());
+        (rec.dpdu, rec.dpdv) = (Vec3::zeros(), Vec3::zeros());
+        true
+    }
+
+    fn for_each_material(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        f(&self.reflection);
+        f(&self.transmission);
+        f(&self.phase_function);
+    }
+}
+
+/// The albedo of each scattering event that makes a random walk in a thick
+/// medium come out with `albedo` after many events, by the fit of Chiang et
+/// al. 2016 per channel.
+struct SingleScatteringAlbedo {
+    albedo: Arc<dyn Texture>,
+}
+
+impl SingleScatteringAlbedo {
+    fn invert(albedo: &Color) -> Color {
+        let invert = |a: f64| {
+            let a = a.clamp(0.0, 0.999);
+            let root = (9.59217 + 41.6808 * a + 17.7126 * a * a).sqrt();
+            1.0 - (4.09712 + 4.20863 * a - root).powi(2)
+        };
+        Color::new(invert(albedo.x), invert(albedo.y), invert(albedo.z))
+    }
+}
+
+impl Texture for SingleScatteringAlbedo {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        Self::invert(&self.albedo.value(u, v, p))
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        Self::invert(&self.albedo.value_at(rec))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::Sphere;
+
+    /// A unit sphere at the origin.
+    fn solid(mean_free_path: f64, ior: f64) -> Subsurface {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::from_color(&Color::ones()));
+        let boundary: Arc<dyn Hittable> = Arc::new(Sphere::new(&Point3::zeros(), 1.0, &mat));
+        Subsurface::new_with_color(&boundary, mean_free_path, &Color::new(0.8, 0.5, 0.2), ior)
+    }
+
+    /// A walk from the center along +x.
+    fn walk(solid: &Subsurface) -> HitRecord {
+        let r = Ray::new(&Point3::zeros(), &Vec3::new(1.0, 0.0, 0.0));
+        let mut rec = HitRecord::default();
+        assert!(solid.hit(&r, &Interval::new(0.0, f64::INFINITY), &mut rec));
+        rec
+    }
+
+    fn is(rec: &HitRecord, mat: &Arc<dyn Material>) -> bool {
+        Arc::ptr_eq(rec.mat.as_ref().unwrap(), mat)
+    }
+
+    #[test]
+    fn test_albedo_inversion() {
+        let invert = |a: f64| SingleScatteringAlbedo::invert(&Color::new(a, a, a)).x;
+        // the fit's coefficients are rounded
+        assert!(invert(0.0).abs() < 1e-4);
+        let mut last = invert(0.0);
+        for i in 1..=100 {
+            let single = invert(i as f64 / 100.0);
+            assert!(single > last);
+            assert!(single < 1.0);
+            last = single;
+        }
+        // single scattering has to keep more light than many events leave
+        assert!(invert(0.5) > 0.5);
+    }
+
+    #[test]
+    fn test_walk_exits_through_the_boundary() {
+        // a walk that never scatters, through a surface that never reflects
+        let solid = solid(1e9, 1.0);
+        let rec = walk(&solid);
+        assert!((rec.t - 1.0).abs() < 1e-9);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+        assert!(is(&rec, &solid.transmission));
+    }
+
+    #[test]
+    fn test_walk_reflects_off_the_inside() {
+        // a surface that reflects nearly everything sends the walk back in
+        let solid = solid(1e9, 1e9);
+        let rec = walk(&solid);
+        assert!((rec.t - 1.0).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-9);
+        assert!(is(&rec, &solid.reflection));
+    }
+
+    #[test]
+    fn test_walk_scatters_inside() {
+        let solid = solid(0.01, 1.0);
+        let rec = walk(&solid);
+        assert!(rec.t < 0.1);
+        assert!(is(&rec, &solid.phase_function));
+    }
+}
@@ -1,5 +1,12 @@
 use super::{HitRecord, Hittable};
-use crate::{aabb::Aabb, interval::Interval, ray::Ray, vec3::Vec3};
+use crate::{
+    aabb::Aabb,
+    direction_cone::DirectionCone,
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
 use std::sync::Arc;
 
 pub struct Translate {
@@ -25,7 +32,7 @@ impl Hittable for Translate {
     }
 
     fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
-        let offset_r = Ray::new_with_time(&(*r.origin() - self.offset), r.direction(), r.time());
+        let offset_r = r.transformed(&(*r.origin() - self.offset), r.direction());
 
         if self.object.hit(&offset_r, ray_t, rec) {
             rec.p += self.offset;
@@ -34,4 +41,27 @@ impl Hittable for Translate {
             false
         }
     }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object.pdf_value(&(*origin - self.offset), direction)
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.object.random(&(*origin - self.offset))
+    }
+
+    fn area(&self) -> f64 {
+        self.object.area()
+    }
+
+    fn random_point(&self) -> Point3 {
+        self.object.random_point() + self.offset
+    }
+
+    fn normal_cone(&self) -> DirectionCone {
+        self.object.normal_cone()
+    }
+    fn for_each_material(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        self.object.for_each_material(f);
+    }
 }
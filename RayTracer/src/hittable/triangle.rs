@@ -0,0 +1,222 @@
+use super::{HitRecord, Hittable};
+use crate::{
+    aabb::Aabb,
+    direction_cone::DirectionCone,
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    rtweekend,
+    vec3::{Point3, Vec3},
+};
+use std::sync::Arc;
+
+pub struct Triangle {
+    a: Point3,
+    /// edges from `a` to the other two vertices
+    e1: Vec3,
+    e2: Vec3,
+    /// texture coordinates of the vertices
+    uvs: [(f64, f64); 3],
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+    normal: Vec3,
+    dpdu: Vec3,
+    dpdv: Vec3,
+    area: f64,
+}
+
+impl Triangle {
+    /// A triangle whose vertices have the texture coordinates (0, 0), (1, 0)
+    /// and (0, 1).
+    pub fn new(a: &Point3, b: &Point3, c: &Point3, mat: &Arc<dyn Material>) -> Self {
+        Self::new_with_uvs(a, b, c, &[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)], mat)
+    }
+
+    pub fn new_with_uvs(
+        a: &Point3,
+        b: &Point3,
+        c: &Point3,
+        uvs: &[(f64, f64); 3],
+        mat: &Arc<dyn Material>,
+    ) -> Self {
+        let e1 = *b - *a;
+        let e2 = *c - *a;
+        let n = e1.cross(&e2);
+
+        // solve e1 = dpdu du1 + dpdv dv1 and e2 = dpdu du2 + dpdv dv2
+        let (du1, dv1) = (uvs[1].0 - uvs[0].0, uvs[1].1 - uvs[0].1);
+        let (du2, dv2) = (uvs[2].0 - uvs[0].0, uvs[2].1 - uvs[0].1);
+        let det = du1 * dv2 - dv1 * du2;
+        let (dpdu, dpdv) = if det.abs() > 1e-12 {
+            ((e1 * dv2 - e2 * dv1) / det, (e2 * du1 - e1 * du2) / det)
+        } else {
+            (e1, e2)
+        };
+
+        let bbox = Aabb::from_aabbs(&Aabb::from_endpoints(a, b), &Aabb::from_endpoints(a, c));
+
+        Self {
+            a: *a,
+            e1,
+            e2,
+            uvs: *uvs,
+            mat: mat.clone(),
+            bbox,
+            normal: n.unit(),
+            dpdu,
+            dpdv,
+            area: n.length() / 2.0,
+        }
+    }
+}
+
+impl Hittable for Triangle {
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    /// Möller-Trumbore intersection.
+    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
+        let pvec = r.direction().cross(&self.e2);
+        let det = self.e1 * pvec;
+        if det.abs() < 1e-12 {
+            return false;
+        }
+        let inv_det = 1.0 / det;
+
+        let tvec = *r.origin() - self.a;
+        let b1 = tvec * pvec * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return false;
+        }
+        let qvec = tvec.cross(&self.e1);
+        let b2 = *r.direction() * qvec * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return false;
+        }
+        let t = self.e2 * qvec * inv_det;
+        if !ray_t.contains(t) {
+            return false;
+        }
+
+        // filled in apart, so that a hit failing the alpha test leaves rec alone
+        let b0 = 1.0 - b1 - b2;
+        let mut hit_rec = HitRecord {
+            t,
+            p: r.at(t),
+            u: b0 * self.uvs[0].0 + b1 * self.uvs[1].0 + b2 * self.uvs[2].0,
+            v: b0 * self.uvs[0].1 + b1 * self.uvs[1].1 + b2 * self.uvs[2].1,
+            mat: Some(self.mat.clone()),
+            time: r.time(),
+            dpdu: self.dpdu,
+            dpdv: self.dpdv,
+            ..Default::default()
+        };
+        hit_rec.set_face_normal(r, &self.normal);
+
+        if !hit_rec.passes_alpha_test(r) {
+            return false;
+        }
+        *rec = hit_rec;
+        true
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let mut rec = HitRecord::default();
+        if self.hit(
+            &Ray::new(origin, direction).ignoring_alpha(),
+            &Interval::new(0.001, f64::INFINITY),
+            &mut rec,
+        ) {
+            let distance_squared = rec.t.powi(2) * direction.squared_length();
+            let cosine = (*direction * rec.normal / direction.length()).abs();
+
+            distance_squared / (cosine * self.area)
+        } else {
+            0.0
+        }
+    }
+
+    fn random(&self, origin: &Point3) -> Vec3 {
+        self.random_point() - *origin
+    }
+
+    fn for_each_material(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        f(&self.mat);
+    }
+
+    fn area(&self) -> f64 {
+        self.area
+    }
+
+    fn random_point(&self) -> Point3 {
+        // fold the unit square onto the triangle
+        let (mut b1, mut b2) = (rtweekend::random_double(), rtweekend::random_double());
+        if b1 + b2 > 1.0 {
+            (b1, b2) = (1.0 - b1, 1.0 - b2);
+        }
+        self.a + self.e1 * b1 + self.e2 * b2
+    }
+
+    fn normal_cone(&self) -> DirectionCone {
+        DirectionCone::from_direction(&self.normal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::Color, material::Lambertian};
+
+    fn triangle() -> Triangle {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::from_color(&Color::ones()));
+        Triangle::new_with_uvs(
+            &Point3::zeros(),
+            &Point3::new(2.0, 0.0, 0.0),
+            &Point3::new(0.0, 2.0, 0.0),
+            &[(0.5, 0.5), (1.0, 0.5), (0.5, 1.0)],
+            &mat,
+        )
+    }
+
+    #[test]
+    fn test_hit_interpolates_uvs() {
+        let tri = triangle();
+        let r = Ray::new(&Point3::new(0.5, 0.5, 1.0), &Vec3::new(0.0, 0.0, -1.0));
+        let mut rec = HitRecord::default();
+        assert!(tri.hit(&r, &Interval::new(0.001, f64::INFINITY), &mut rec));
+
+        assert!((rec.t - 1.0).abs() < 1e-12);
+        assert!((rec.p - Point3::new(0.5, 0.5, 0.0)).length() < 1e-12);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-12);
+        // a quarter of the way along both edges
+        assert!((rec.u - 0.625).abs() < 1e-12);
+        assert!((rec.v - 0.625).abs() < 1e-12);
+        // the uvs change by 0.5 over the 2 unit edges
+        assert!((rec.dpdu - Vec3::new(4.0, 0.0, 0.0)).length() < 1e-12);
+        assert!((rec.dpdv - Vec3::new(0.0, 4.0, 0.0)).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_misses_outside_the_edges() {
+        let tri = triangle();
+        let ray_t = Interval::new(0.001, f64::INFINITY);
+        let down = Vec3::new(0.0, 0.0, -1.0);
+        let mut rec = HitRecord::default();
+        // past the hypotenuse, and beside each leg
+        for origin in [
+            Point3::new(1.1, 1.1, 1.0),
+            Point3::new(-0.1, 1.0, 1.0),
+            Point3::new(1.0, -0.1, 1.0),
+        ] {
+            assert!(!tri.hit(&Ray::new(&origin, &down), &ray_t, &mut rec));
+        }
+        // parallel to the plane
+        let grazing = Ray::new(&Point3::new(-1.0, 0.5, 0.0), &Vec3::new(1.0, 0.0, 0.0));
+        assert!(!tri.hit(&grazing, &ray_t, &mut rec));
+        // behind the origin
+        let away = Ray::new(&Point3::new(0.5, 0.5, 1.0), &Vec3::new(0.0, 0.0, 1.0));
+        assert!(!tri.hit(&away, &ray_t, &mut rec));
+    }
+}
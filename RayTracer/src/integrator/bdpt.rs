@@ -0,0 +1,752 @@
+use crate::{
+    aabb::Aabb,
+    camera::{Camera, ImportanceSample},
+    color::Color,
+    film::Film,
+    hittable::{HitRecord, Hittable, HittableList},
+    interval::Interval,
+    light::{EmissionSample, LightList, LightSample, PowerLightSampler},
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum VertexKind {
+    Camera,
+    Light,
+    Surface,
+}
+
+#[derive(Clone)]
+struct Vertex {
+    kind: VertexKind,
+    p: Point3,
+    /// geometric normal, zero for points not on a surface (pinholes, point lights)
+    n: Vec3,
+    /// unit direction towards the previous vertex of a surface vertex
+    wo: Vec3,
+    rec: HitRecord,
+    /// index of the light of a light vertex
+    light: usize,
+    beta: Color,
+    /// scattered by a specular material, so it can't be connected to
+    delta: bool,
+    is_delta_light: bool,
+    /// area densities of sampling this vertex from its neighbours, from the
+    /// direction the subpath was traced in and from the opposite one
+    pdf_fwd: f64,
+    pdf_rev: f64,
+    time: f64,
+}
+
+impl Vertex {
+    fn new(kind: VertexKind, p: &Point3, n: &Vec3, beta: &Color, time: f64) -> Self {
+        Self {
+            kind,
+            p: *p,
+            n: *n,
+            wo: Vec3::zeros(),
+            rec: HitRecord::default(),
+            light: 0,
+            beta: *beta,
+            delta: false,
+            is_delta_light: false,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+            time,
+        }
+    }
+
+    fn from_hit(rec: &HitRecord, r_in: &Ray, beta: &Color) -> Self {
+        let n = rec.surface_normal();
+        Self {
+            wo: -r_in.direction().unit(),
+            rec: rec.clone(),
+            ..Self::new(VertexKind::Surface, &rec.p, &n, beta, r_in.time())
+        }
+    }
+
+    fn is_on_surface(&self) -> bool {
+        self.n != Vec3::zeros()
+    }
+
+    fn is_connectible(&self) -> bool {
+        match self.kind {
+            VertexKind::Surface => match &self.rec.mat {
+                Some(mat) => !mat.is_specular(),
+                None => false,
+            },
+            _ => true,
+        }
+    }
+
+    /// Radiance emitted towards the previous vertex of a surface vertex.
+    fn le(&self) -> Color {
+        match (&self.rec.mat, self.kind) {
+            (Some(mat), VertexKind::Surface) => {
+                let r_in = Ray::new_with_time(&(self.p + self.wo), &-self.wo, self.time);
+                mat.emitted(&r_in, &self.rec, self.rec.u, self.rec.v, &self.p)
+            }
+            _ => Color::zeros(),
+        }
+    }
+
+    /// The surface seen from direction `w`, with the normal flipped towards it.
+    fn oriented_rec(&self, w: &Vec3) -> HitRecord {
+        let mut rec = self.rec.clone();
+        if rec.normal * *w < 0.0 {
+            rec.normal = -rec.normal;
+            rec.front_face = !rec.front_face;
+        }
+        rec
+    }
+
+    /// BSDF at a surface vertex for light between `wo` and the direction to `next`.
+    fn f(&self, next: &Vertex) -> Color {
+        let wn = next.p - self.p;
+        match &self.rec.mat {
+            Some(mat) if self.kind == VertexKind::Surface && !wn.near_zero() => mat.bsdf(
+                &Ray::new_with_time(&(self.p + self.wo), &-self.wo, self.time),
+                &self.oriented_rec(&self.wo),
+                &Ray::new_with_time(&self.p, &wn.unit(), self.time),
+            ),
+            _ => Color::zeros(),
+        }
+    }
+
+    /// Solid angle density of scattering from direction `wp` into direction `wn`.
+    fn scattering_pdf(&self, wp: &Vec3, wn: &Vec3) -> f64 {
+        match &self.rec.mat {
+            Some(mat) if !mat.is_specular() => mat.scattering_pdf(
+                &Ray::new_with_time(&(self.p + *wp), &-*wp, self.time),
+                &self.oriented_rec(wp),
+                &Ray::new_with_time(&self.p, wn, self.time),
+            ),
+            _ => 0.0,
+        }
+    }
+
+    /// Converts a solid angle density at this vertex into an area density at `next`.
+    fn convert_density(&self, pdf: f64, next: &Vertex) -> f64 {
+        let w = next.p - self.p;
+        if w.near_zero() {
+            return 0.0;
+        }
+
+        let inv_dist_squared = 1.0 / w.squared_length();
+        let mut pdf = pdf * inv_dist_squared;
+        if next.is_on_surface() {
+            pdf *= (next.n * (w * inv_dist_squared.sqrt())).abs();
+        }
+        pdf
+    }
+}
+
+/// Bidirectional path tracing: every prefix of a camera subpath is connected to
+/// every prefix of a light subpath, and the strategies are combined with
+/// multiple importance sampling. Connections straight to the camera are
+/// splatted onto the film.
+pub struct Bdpt {
+    lights: PowerLightSampler,
+    infinite: Vec<bool>,
+    world_radius: f64,
+}
+
+impl Bdpt {
+    pub fn new(lights: &LightList, world_bbox: &Aabb) -> Self {
+        Self {
+            lights: PowerLightSampler::new(lights, world_bbox),
+            infinite: lights
+                .lights
+                .iter()
+                .map(|light| light.bounds().is_none())
+                .collect(),
+            world_radius: world_bbox.diagonal().length() / 2.0,
+        }
+    }
+
+    /// Radiance arriving along `r`. Strategies seeing the light path straight
+    /// from the camera land on other pixels, so they are splatted onto `film`,
+    /// the film of the calling thread.
+    pub fn li(&self, cam: &Camera, r: &Ray, world: &HittableList, film: &mut Film) -> Color {
+        let max_depth = cam.max_depth() as usize;
+
+        let mut camera_path = Vec::with_capacity(max_depth + 2);
+        let mut color =
+            self.generate_camera_subpath(cam, r, world, max_depth + 2, &mut camera_path);
+        let mut light_path = Vec::with_capacity(max_depth + 1);
+        self.generate_light_subpath(world, r.time(), max_depth + 1, &mut light_path);
+
+        for t in 1..=camera_path.len() {
+            for s in 0..=light_path.len() {
+                let depth = s + t;
+                if (s == 1 && t == 1) || depth < 2 || depth - 2 > max_depth {
+                    continue;
+                }
+
+                let mut raster = None;
+                let color_from_strategy =
+                    self.connect(cam, world, &light_path, &camera_path, s, t, &mut raster);
+                if t == 1 {
+                    if let Some((i, j)) = raster {
+                        film.add_splat(i, j, &color_from_strategy);
+                    }
+                } else {
+                    color += color_from_strategy;
+                }
+            }
+        }
+
+        color
+    }
+
+    /// Returns the background radiance reached if the subpath escapes the scene.
+    fn generate_camera_subpath(
+        &self,
+        cam: &Camera,
+        r: &Ray,
+        world: &HittableList,
+        max_vertices: usize,
+        path: &mut Vec<Vertex>,
+    ) -> Color {
+        let beta = Color::ones();
+        path.push(Vertex::new(
+            VertexKind::Camera,
+            r.origin(),
+            &cam.forward(),
+            &beta,
+            r.time(),
+        ));
+
+        let mut pdf_pos = 0.0;
+        let mut pdf_dir = 0.0;
+        cam.pdf_we(r, &mut pdf_pos, &mut pdf_dir);
+        self.random_walk(world, r, &beta, pdf_dir, max_vertices, Some(cam), path)
+    }
+
+    fn generate_light_subpath(
+        &self,
+        world: &HittableList,
+        time: f64,
+        max_vertices: usize,
+        path: &mut Vec<Vertex>,
+    ) {
+        let mut index = 0;
+        let mut sample = EmissionSample::default();
+        if !self.lights.sample_index(&mut index)
+            || !self.lights.lights()[index].sample_le(&mut sample)
+            || sample.pdf_pos == 0.0
+            || sample.pdf_dir == 0.0
+            || sample.le == Color::zeros()
+        {
+            return;
+        }
+
+        let pmf = self.lights.pmf(index);
+        let mut vertex = Vertex::new(
+            VertexKind::Light,
+            &sample.origin,
+            &sample.normal,
+            &sample.le,
+            time,
+        );
+        vertex.light = index;
+        vertex.is_delta_light = sample.is_delta;
+        vertex.pdf_fwd = sample.pdf_pos * pmf;
+        path.push(vertex);
+
+        let cosine = if sample.normal == Vec3::zeros() {
+            1.0
+        } else {
+            (sample.normal * sample.direction).abs()
+        };
+        let beta = sample.le * cosine / (pmf * sample.pdf_pos * sample.pdf_dir);
+        let r = Ray::new_with_time(&sample.origin, &sample.direction, time);
+        self.random_walk(world, &r, &beta, sample.pdf_dir, max_vertices, None, path);
+    }
+
+    /// Extends `path` by repeatedly scattering `r`. Camera subpaths pass `cam`,
+    /// and get the background radiance back if they leave the scene.
+    #[allow(clippy::too_many_arguments)]
+    fn random_walk(
+        &self,
+        world: &HittableList,
+        r: &Ray,
+        beta: &Color,
+        pdf: f64,
+        max_vertices: usize,
+        cam: Option<&Camera>,
+        path: &mut Vec<Vertex>,
+    ) -> Color {
+        let mut r = *r;
+        let mut beta = *beta;
+        let mut pdf_fwd = pdf;
+
+        while path.len() < max_vertices {
+            let mut rec = HitRecord::default();
+            if !world.hit(&r, &Interval::new(0.001, f64::INFINITY), &mut rec) {
+                return match cam {
+                    Some(cam) => beta.elemul(&cam.background()),
+                    None => Color::zeros(),
+                };
+            }
+            rec.prepare_shading(&r);
+
+            let mat = match &rec.mat {
+                Some(mat) => mat.clone(),
+                None => break,
+            };
+            let mut vertex = Vertex::from_hit(&rec, &r, &beta);
+            vertex.pdf_fwd = path.last().unwrap().convert_density(pdf_fwd, &vertex);
+            path.push(vertex);
+            if path.len() >= max_vertices {
+                break;
+            }
+
+            let mut attenuation = Color::default();
+            let mut scattered = Ray::default();
+            if !mat.scatter(&r, &rec, &mut attenuation, &mut scattered) {
+                break;
+            }
+
+            let n = path.len();
+            let pdf_rev;
+            if mat.is_specular() {
+                path[n - 1].delta = true;
+                pdf_fwd = 0.0;
+                pdf_rev = 0.0;
+            } else {
+                pdf_fwd = mat.scattering_pdf(&r, &rec, &scattered);
+                if pdf_fwd <= 0.0 {
+                    break;
+                }
+                pdf_rev = path[n - 1]
+                    .scattering_pdf(&scattered.direction().unit(), &-r.direction().unit());
+            }
+
+            beta = beta.elemul(&attenuation);
+            path[n - 2].pdf_rev = path[n - 1].convert_density(pdf_rev, &path[n - 2]);
+            r = scattered;
+        }
+
+        Color::zeros()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn connect(
+        &self,
+        cam: &Camera,
+        world: &HittableList,
+        light_path: &[Vertex],
+        camera_path: &[Vertex],
+        s: usize,
+        t: usize,
+        raster: &mut Option<(u32, u32)>,
+    ) -> Color {
+        let mut sampled = None;
+        let color;
+
+        if s == 0 {
+            let pt = &camera_path[t - 1];
+            color = pt.beta.elemul(&pt.le());
+        } else if t == 1 {
+            let qs = &light_path[s - 1];
+            let mut sample = ImportanceSample::default();
+            if !qs.is_connectible() || !cam.sample_wi(&qs.p, &mut sample) {
+                return Color::zeros();
+            }
+
+            let vertex = Vertex::new(
+                VertexKind::Camera,
+                &sample.lens_point,
+                &cam.forward(),
+                &(Color::ones() * sample.we / sample.pdf),
+                qs.time,
+            );
+            let mut c = qs.beta.elemul(&qs.f(&vertex)).elemul(&vertex.beta);
+            if qs.is_on_surface() {
+                c *= (sample.wi * qs.n).abs();
+            }
+            if c == Color::zeros() || !unoccluded(world, &qs.p, &sample.wi, sample.distance) {
+                return Color::zeros();
+            }
+
+            *raster = Some((sample.i, sample.j));
+            color = c;
+            sampled = Some(vertex);
+        } else if s == 1 {
+            let pt = &camera_path[t - 1];
+            let mut index = 0;
+            let mut sample = LightSample::default();
+            if !pt.is_connectible()
+                || !self.lights.sample_index(&mut index)
+                || !self.lights.lights()[index].sample_li(&pt.p, &mut sample)
+                || sample.pdf <= 0.0
+            {
+                return Color::zeros();
+            }
+
+            let pmf = self.lights.pmf(index);
+            let distance = sample.distance.min(2.0 * self.world_radius);
+            let mut vertex = Vertex::new(
+                VertexKind::Light,
+                &(pt.p + sample.wi * distance),
+                &sample.normal,
+                &(sample.li / (sample.pdf * pmf)),
+                pt.time,
+            );
+            vertex.light = index;
+            vertex.is_delta_light = sample.is_delta;
+            vertex.pdf_fwd = self.pdf_light_origin(&vertex, pt);
+
+            let mut c = pt.beta.elemul(&pt.f(&vertex)).elemul(&vertex.beta);
+            if pt.is_on_surface() {
+                c *= (sample.wi * pt.n).abs();
+            }
+            if c == Color::zeros() || !unoccluded(world, &pt.p, &sample.wi, sample.distance) {
+                return Color::zeros();
+            }
+
+            color = c;
+            sampled = Some(vertex);
+        } else {
+            let qs = &light_path[s - 1];
+            let pt = &camera_path[t - 1];
+            if !qs.is_connectible() || !pt.is_connectible() {
+                return Color::zeros();
+            }
+
+            let c = qs.beta.elemul(&qs.f(pt)).elemul(&pt.f(qs)).elemul(&pt.beta);
+            if c == Color::zeros() {
+                return Color::zeros();
+            }
+            color = c * geometry_term(world, qs, pt);
+        }
+
+        if color == Color::zeros() {
+            color
+        } else {
+            color * self.mis_weight(cam, light_path, camera_path, sampled.as_ref(), s, t)
+        }
+    }
+
+    /// Balance heuristic weight of strategy (s, t), found by comparing the area
+    /// densities every other strategy would have generated the same path with.
+    fn mis_weight(
+        &self,
+        cam: &Camera,
+        light_path: &[Vertex],
+        camera_path: &[Vertex],
+        sampled: Option<&Vertex>,
+        s: usize,
+        t: usize,
+    ) -> f64 {
+        if s + t == 2 {
+            return 1.0;
+        }
+        // nothing but a shadow ray can reach a light infinitely far away
+        if s == 1 && sampled.is_some_and(|v| self.infinite[v.light]) {
+            return 1.0;
+        }
+
+        let qs = match (s, sampled) {
+            (0, _) => None,
+            (1, Some(v)) => Some(v),
+            _ => Some(&light_path[s - 1]),
+        };
+        let pt = match (t, sampled) {
+            (1, Some(v)) if s != 1 => v,
+            _ => &camera_path[t - 1],
+        };
+        let qs_minus = if s > 1 {
+            Some(&light_path[s - 2])
+        } else {
+            None
+        };
+        let pt_minus = if t > 1 {
+            Some(&camera_path[t - 2])
+        } else {
+            None
+        };
+
+        // (pdf_fwd, pdf_rev, delta) of each vertex as it is in the connected path
+        let mut light_pdfs: Vec<_> = light_path[..s]
+            .iter()
+            .map(|v| (v.pdf_fwd, v.pdf_rev, v.delta))
+            .collect();
+        let mut camera_pdfs: Vec<_> = camera_path[..t]
+            .iter()
+            .map(|v| (v.pdf_fwd, v.pdf_rev, v.delta))
+            .collect();
+
+        camera_pdfs[t - 1] = (
+            pt.pdf_fwd,
+            match qs {
+                Some(qs) => self.vertex_pdf(cam, qs, qs_minus, pt),
+                None => self.pdf_light_origin(pt, pt_minus.unwrap()),
+            },
+            false,
+        );
+        if let Some(pt_minus) = pt_minus {
+            camera_pdfs[t - 2].1 = match qs {
+                Some(qs) => self.vertex_pdf(cam, pt, Some(qs), pt_minus),
+                None => self.pdf_light(pt, pt_minus),
+            };
+        }
+        if let Some(qs) = qs {
+            light_pdfs[s - 1] = (qs.pdf_fwd, self.vertex_pdf(cam, pt, pt_minus, qs), false);
+            if let Some(qs_minus) = qs_minus {
+                light_pdfs[s - 2].1 = self.vertex_pdf(cam, qs, Some(pt), qs_minus);
+            }
+        }
+
+        let remap0 = |pdf: f64| if pdf != 0.0 { pdf } else { 1.0 };
+        let mut sum_ri = 0.0;
+
+        let mut ri = 1.0;
+        for i in (1..t).rev() {
+            ri *= remap0(camera_pdfs[i].1) / remap0(camera_pdfs[i].0);
+            if !camera_pdfs[i].2 && !camera_pdfs[i - 1].2 {
+                sum_ri += ri;
+            }
+        }
+
+        if let Some(qs) = qs {
+            let first = if s == 1 { qs } else { &light_path[0] };
+            let mut ri = 1.0;
+            for i in (0..s).rev() {
+                ri *= remap0(light_pdfs[i].1) / remap0(light_pdfs[i].0);
+                let is_delta_light_vertex = if i > 0 {
+                    light_pdfs[i - 1].2
+                } else {
+                    first.is_delta_light
+                };
+                if !light_pdfs[i].2 && !is_delta_light_vertex {
+                    sum_ri += ri;
+                }
+            }
+        }
+
+        1.0 / (1.0 + sum_ri)
+    }
+
+    /// Area density at `next` of `v` sampling the direction towards it.
+    fn vertex_pdf(&self, cam: &Camera, v: &Vertex, prev: Option<&Vertex>, next: &Vertex) -> f64 {
+        if v.kind == VertexKind::Light {
+            return self.pdf_light(v, next);
+        }
+
+        let wn = next.p - v.p;
+        if wn.near_zero() {
+            return 0.0;
+        }
+        let wn = wn.unit();
+
+        let pdf = match v.kind {
+            VertexKind::Camera => {
+                let mut pdf_pos = 0.0;
+                let mut pdf_dir = 0.0;
+                cam.pdf_we(&Ray::new(&v.p, &wn), &mut pdf_pos, &mut pdf_dir);
+                pdf_dir
+            }
+            _ => match prev {
+                Some(prev) => v.scattering_pdf(&(prev.p - v.p).unit(), &wn),
+                None => 0.0,
+            },
+        };
+
+        v.convert_density(pdf, next)
+    }
+
+    /// Finds the light a light vertex or an emissive surface vertex belongs to,
+    /// with the densities of it emitting along `w`.
+    fn find_light(
+        &self,
+        v: &Vertex,
+        w: &Vec3,
+        pdf_pos: &mut f64,
+        pdf_dir: &mut f64,
+    ) -> Option<usize> {
+        if v.kind == VertexKind::Light {
+            self.lights.lights()[v.light].pdf_le(&v.p, w, pdf_pos, pdf_dir);
+            return Some(v.light);
+        }
+
+        for (index, light) in self.lights.lights().iter().enumerate() {
+            light.pdf_le(&v.p, w, pdf_pos, pdf_dir);
+            if *pdf_pos > 0.0 {
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    /// Area density at `next` of the light at `v` emitting towards it.
+    fn pdf_light(&self, v: &Vertex, next: &Vertex) -> f64 {
+        let w = next.p - v.p;
+        if w.near_zero() {
+            return 0.0;
+        }
+        let inv_dist_squared = 1.0 / w.squared_length();
+        let w = w.unit();
+
+        let mut pdf_pos = 0.0;
+        let mut pdf_dir = 0.0;
+        if self.find_light(v, &w, &mut pdf_pos, &mut pdf_dir).is_none() {
+            return 0.0;
+        }
+
+        let mut pdf = pdf_dir * inv_dist_squared;
+        if next.is_on_surface() {
+            pdf *= (next.n * w).abs();
+        }
+        pdf
+    }
+
+    /// Area density of a light subpath starting at `v`.
+    fn pdf_light_origin(&self, v: &Vertex, next: &Vertex) -> f64 {
+        let w = next.p - v.p;
+        if w.near_zero() {
+            return 0.0;
+        }
+
+        let mut pdf_pos = 0.0;
+        let mut pdf_dir = 0.0;
+        match self.find_light(v, &w.unit(), &mut pdf_pos, &mut pdf_dir) {
+            Some(index) => self.lights.pmf(index) * pdf_pos,
+            None => 0.0,
+        }
+    }
+}
+
+fn unoccluded(world: &HittableList, p: &Point3, w: &Vec3, distance: f64) -> bool {
+    !world.hit(
+        &Ray::new(p, w),
+        &Interval::new(0.001, distance - 0.001),
+        &mut HitRecord::default(),
+    )
+}
+
+fn geometry_term(world: &HittableList, v0: &Vertex, v1: &Vertex) -> f64 {
+    let d = v0.p - v1.p;
+    let distance = d.length();
+    let d = d / distance;
+
+    let mut g = 1.0 / (distance * distance);
+    if v0.is_on_surface() {
+        g *= (v0.n * d).abs();
+    }
+    if v1.is_on_surface() {
+        g *= (v1.n * d).abs();
+    }
+
+    if unoccluded(world, &v1.p, &d, distance) {
+        g
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::CameraParams,
+        filter::{BoxFilter, Filter},
+        hittable::Quad,
+        integrator::PathTracer,
+        light::{AreaLight, Light, LightBvh, LightSampler},
+        material::{DiffuseLight, Lambertian, Material},
+    };
+    use std::sync::Arc;
+
+    /// Mean pixel of an 8x8 image of a diffuse corner lit by a panel above it.
+    fn mean_pixel(bidirectional: bool) -> Color {
+        let camera = Camera::new(
+            &CameraParams {
+                aspect_ratio: 1.0,
+                image_width: 8,
+                samples_per_pixel: 256,
+                max_depth: 5,
+                background: Color::zeros(),
+                lookfrom: Point3::new(0.0, 1.0, 4.0),
+                lookat: Point3::new(0.0, 0.5, 0.0),
+                vup: Vec3::new(0.0, 1.0, 0.0),
+            },
+            50.0,
+            0.0,
+            4.0,
+        );
+
+        let white: Arc<dyn Material> = Arc::new(Lambertian::from_color(&Color::new(0.7, 0.7, 0.7)));
+        let emission = Arc::new(DiffuseLight::from_color(&Color::new(4.0, 4.0, 4.0)));
+        let light_mat = emission.clone() as Arc<dyn Material>;
+        let quad = |q: Point3, u: Vec3, v: Vec3, mat: &Arc<dyn Material>| -> Arc<dyn Hittable> {
+            Arc::new(Quad::new(&q, &u, &v, mat))
+        };
+        let panel = quad(
+            Point3::new(-0.5, 2.0, -0.5),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            &light_mat,
+        );
+        let mut world = HittableList::default();
+        world.add(&quad(
+            Point3::new(-2.0, 0.0, 2.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -4.0),
+            &white,
+        ));
+        world.add(&quad(
+            Point3::new(-2.0, 0.0, -2.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 3.0, 0.0),
+            &white,
+        ));
+        world.add(&panel);
+        let mut lights = LightList::default();
+        lights.add(&(Arc::new(AreaLight::new(&panel, &emission)) as Arc<dyn Light>));
+
+        let filter: Arc<dyn Filter> = Arc::new(BoxFilter::default());
+        let mut film = Film::new(8, 8, &filter);
+        let bdpt = Bdpt::new(&lights, &world.bounding_box());
+        let path = PathTracer::<Color>::new();
+        let light_sampler = LightBvh::new(&lights);
+        for j in 0..8 {
+            for i in 0..8 {
+                for s_j in 0..16 {
+                    for s_i in 0..16 {
+                        let mut weight = 1.0;
+                        let r = camera.get_ray(i, j, s_i, s_j, &mut weight).unwrap();
+                        let color = if bidirectional {
+                            bdpt.li(&camera, &r, &world, &mut film)
+                        } else {
+                            let light_sampler: &dyn LightSampler = &light_sampler;
+                            path.li(&camera, &r, &world, light_sampler, &mut Color::zeros())
+                        };
+                        let (x, y) = (i as f64 + 0.5, j as f64 + 0.5);
+                        film.add_sample(x, y, &color);
+                    }
+                }
+            }
+        }
+
+        let pixels = film.resolve(1.0 / 256.0);
+        pixels
+            .iter()
+            .fold(Color::zeros(), |sum, pixel| sum + *pixel)
+            / pixels.len() as f64
+    }
+
+    #[test]
+    fn test_converges_to_path_tracer() {
+        let bdpt = mean_pixel(true);
+        let path = mean_pixel(false);
+        for c in 0..3 {
+            assert!(
+                (bdpt[c] - path[c]).abs() < 0.03 * path[c],
+                "{bdpt:?} vs {path:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,7 @@
+mod bdpt;
+mod path;
+mod sppm;
+
+pub use bdpt::Bdpt;
+pub use path::{PathTracer, SpectralPath};
+pub use sppm::Sppm;
@@ -0,0 +1,357 @@
+use crate::{
+    camera::Camera,
+    color::Color,
+    hittable::{HitRecord, Hittable, HittableList},
+    interval::Interval,
+    light::{LightSample, LightSampler},
+    material::Material,
+    pdf::{self, CosinePdf, Pdf, SpherePdf},
+    ray::Ray,
+    rtweekend,
+    spectrum::{SampledSpectrum, SampledWavelengths},
+};
+use std::{
+    marker::PhantomData,
+    ops::{Add, AddAssign, Div, Mul},
+    sync::Arc,
+};
+
+/// Bounces a path always survives before Russian roulette may end it.
+const RR_MIN_DEPTH: u32 = 3;
+
+/// What a path carries: RGB, or a spectrum at the wavelengths the path
+/// samples.
+pub trait Radiance:
+    Copy + Add<Output = Self> + AddAssign + Mul<f64, Output = Self> + Div<f64, Output = Self>
+{
+    /// what a path picks at its start to carry this radiance
+    type Wavelengths;
+
+    fn sample_wavelengths() -> Self::Wavelengths;
+    fn zeros() -> Self;
+    fn ones() -> Self;
+    fn from_rgb(rgb: &Color, lambda: &Self::Wavelengths) -> Self;
+    fn to_rgb(self, lambda: &Self::Wavelengths) -> Color;
+    /// componentwise product
+    fn elemul(&self, rhs: &Self) -> Self;
+    fn is_black(&self) -> bool;
+    fn max_component(&self) -> f64;
+    fn scatter(
+        mat: &dyn Material,
+        r_in: &Ray,
+        rec: &HitRecord,
+        lambda: &mut Self::Wavelengths,
+        attenuation: &mut Self,
+        scattered: &mut Ray,
+    ) -> bool;
+}
+
+impl Radiance for Color {
+    type Wavelengths = ();
+
+    fn sample_wavelengths() {}
+
+    fn zeros() -> Self {
+        Color::zeros()
+    }
+
+    fn ones() -> Self {
+        Color::ones()
+    }
+
+    fn from_rgb(rgb: &Color, _lambda: &()) -> Self {
+        *rgb
+    }
+
+    fn to_rgb(self, _lambda: &()) -> Color {
+        self
+    }
+
+    fn elemul(&self, rhs: &Self) -> Self {
+        Color::elemul(self, rhs)
+    }
+
+    fn is_black(&self) -> bool {
+        *self == Color::zeros()
+    }
+
+    fn max_component(&self) -> f64 {
+        Color::max_component(self)
+    }
+
+    fn scatter(
+        mat: &dyn Material,
+        r_in: &Ray,
+        rec: &HitRecord,
+        _lambda: &mut (),
+        attenuation: &mut Self,
+        scattered: &mut Ray,
+    ) -> bool {
+        mat.scatter(r_in, rec, attenuation, scattered)
+    }
+}
+
+impl Radiance for SampledSpectrum {
+    type Wavelengths = SampledWavelengths;
+
+    fn sample_wavelengths() -> SampledWavelengths {
+        SampledWavelengths::sample_visible()
+    }
+
+    fn zeros() -> Self {
+        SampledSpectrum::zeros()
+    }
+
+    fn ones() -> Self {
+        SampledSpectrum::ones()
+    }
+
+    fn from_rgb(rgb: &Color, lambda: &SampledWavelengths) -> Self {
+        SampledSpectrum::from_rgb(rgb, lambda)
+    }
+
+    fn to_rgb(self, lambda: &SampledWavelengths) -> Color {
+        SampledSpectrum::to_rgb(self, lambda)
+    }
+
+    fn elemul(&self, rhs: &Self) -> Self {
+        *self * *rhs
+    }
+
+    fn is_black(&self) -> bool {
+        SampledSpectrum::is_black(self)
+    }
+
+    fn max_component(&self) -> f64 {
+        SampledSpectrum::max_component(self)
+    }
+
+    fn scatter(
+        mat: &dyn Material,
+        r_in: &Ray,
+        rec: &HitRecord,
+        lambda: &mut SampledWavelengths,
+        attenuation: &mut Self,
+        scattered: &mut Ray,
+    ) -> bool {
+        mat.scatter_spectral(r_in, rec, lambda, attenuation, scattered)
+    }
+}
+
+/// The unidirectional path tracer with next event estimation. With `Color`
+/// radiance it traces in RGB; with `SampledSpectrum` at a few wavelengths per
+/// path, so glass can disperse light and metals take their color from
+/// measured optical constants, the RGB colors of the scene being upsampled.
+pub struct PathTracer<R: Radiance> {
+    radiance: PhantomData<R>,
+}
+
+pub type SpectralPath = PathTracer<SampledSpectrum>;
+
+impl<R: Radiance> Default for PathTracer<R> {
+    fn default() -> Self {
+        Self {
+            radiance: PhantomData,
+        }
+    }
+}
+
+impl<R: Radiance> PathTracer<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Traces a path iteratively, tracking its throughput `beta`. Past
+    /// `RR_MIN_DEPTH` bounces dim paths are ended by Russian roulette and the
+    /// survivors reweighted, so `max_depth` is only a safety bound.
+    ///
+    /// Returns the working space radiance arriving along `r`, the part from
+    /// emitters reached in at most one bounce also added to `direct`.
+    pub fn li(
+        &self,
+        cam: &Camera,
+        r: &Ray,
+        world: &HittableList,
+        lights: &dyn LightSampler,
+        direct: &mut Color,
+    ) -> Color {
+        let mut lambda = R::sample_wavelengths();
+        let mut l = R::zeros();
+        let mut l_direct = R::zeros();
+        let mut beta = R::ones();
+        let mut r = *r;
+        // surface normal and pdf of the last non-specular bounce
+        let mut prev_scatter = None;
+
+        for depth in 0..cam.max_depth() {
+            let mut rec = HitRecord::default();
+            if !world.hit(&r, &Interval::new(0.001, f64::INFINITY), &mut rec) {
+                let from_background = beta.elemul(&R::from_rgb(&cam.background(), &lambda));
+                l += from_background;
+                if depth <= 1 {
+                    l_direct += from_background;
+                }
+                break;
+            }
+            rec.prepare_shading(&r);
+            let mat = rec.mat.clone().unwrap();
+
+            let emitted = mat.emitted(&r, &rec, rec.u, rec.v, &rec.p);
+            if emitted != Color::zeros() {
+                let mut from_emission = beta.elemul(&R::from_rgb(&emitted, &lambda));
+                if let Some((normal, pdf)) = prev_scatter {
+                    // the light sampler may have found this emitter already
+                    let light_pdf = lights.pdf(r.origin(), &normal, r.direction());
+                    from_emission = from_emission * pdf::power_heuristic(pdf, light_pdf);
+                }
+                l += from_emission;
+                if depth <= 1 {
+                    l_direct += from_emission;
+                }
+            }
+
+            let mut scattered = Ray::default();
+            let mut attenuation = R::zeros();
+            if !R::scatter(
+                mat.as_ref(),
+                &r,
+                &rec,
+                &mut lambda,
+                &mut attenuation,
+                &mut scattered,
+            ) {
+                break;
+            }
+
+            if mat.is_specular() {
+                beta = beta.elemul(&attenuation);
+                prev_scatter = None;
+            } else {
+                let cosine_pdf = CosinePdf::new(&rec.normal);
+                let surface_pdf: &dyn Pdf = if mat.is_volumetric() {
+                    &SpherePdf
+                } else {
+                    &cosine_pdf
+                };
+                let from_lights = beta.elemul(&attenuation).elemul(&sample_lights(
+                    &r,
+                    &rec,
+                    &mat,
+                    surface_pdf,
+                    world,
+                    lights,
+                    &lambda,
+                ));
+                l += from_lights;
+                if depth == 0 {
+                    l_direct += from_lights;
+                }
+
+                scattered = Ray::new_with_time(&rec.p, &surface_pdf.generate(), r.time());
+                let pdf_val = surface_pdf.value(scattered.direction());
+                let scattering_pdf = mat.scattering_pdf(&r, &rec, &scattered);
+                beta = beta.elemul(&attenuation) * (scattering_pdf / pdf_val);
+                prev_scatter = Some((rec.surface_normal(), pdf_val));
+            }
+            r = scattered;
+
+            if beta.is_black() {
+                break;
+            }
+            if depth + 1 >= RR_MIN_DEPTH {
+                match russian_roulette(beta) {
+                    Some(survivor) => beta = survivor,
+                    None => break,
+                }
+            }
+        }
+
+        *direct += l_direct.to_rgb(&lambda);
+        l.to_rgb(&lambda)
+    }
+}
+
+/// Ends a path of throughput `beta` at random, the dimmer the likelier, and
+/// returns the throughput of a survivor, scaled up so its expected value is
+/// still `beta`.
+fn russian_roulette<R: Radiance>(beta: R) -> Option<R> {
+    let q = (1.0 - beta.max_component()).max(0.05);
+    if rtweekend::random_double() < q {
+        None
+    } else {
+        Some(beta / (1.0 - q))
+    }
+}
+
+/// Next event estimation: connects the hit point to one light chosen by the
+/// light sampler, MIS weighted against the surface pdf for area lights.
+fn sample_lights<R: Radiance>(
+    r: &Ray,
+    rec: &HitRecord,
+    mat: &Arc<dyn Material>,
+    surface_pdf: &dyn Pdf,
+    world: &HittableList,
+    lights: &dyn LightSampler,
+    lambda: &R::Wavelengths,
+) -> R {
+    let mut sample = LightSample::default();
+    if !lights.sample(&rec.p, &rec.surface_normal(), &mut sample) || sample.pdf <= 0.0 {
+        return R::zeros();
+    }
+
+    let shadow_ray = Ray::new_with_time(&rec.p, &sample.wi, r.time());
+    let scattering_pdf = mat.scattering_pdf(r, rec, &shadow_ray);
+    if scattering_pdf <= 0.0
+        || world.hit(
+            &shadow_ray,
+            &Interval::new(0.001, sample.distance - 0.001),
+            &mut HitRecord::default(),
+        )
+    {
+        return R::zeros();
+    }
+
+    let weight = if sample.is_delta {
+        1.0
+    } else {
+        pdf::power_heuristic(sample.pdf, surface_pdf.value(&sample.wi))
+    };
+    R::from_rgb(&sample.li, lambda) * (scattering_pdf * weight / sample.pdf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_russian_roulette_preserves_mean_throughput() {
+        const N: usize = 200_000;
+        for beta in [
+            Color::new(0.02, 0.01, 0.0),
+            Color::new(0.3, 0.2, 0.1),
+            Color::new(0.9, 0.5, 0.7),
+            Color::new(2.0, 1.0, 0.5),
+        ] {
+            let mut survivors = 0;
+            let mut sum = Color::zeros();
+            for _ in 0..N {
+                if let Some(survivor) = russian_roulette(beta) {
+                    survivors += 1;
+                    sum += survivor;
+                }
+            }
+            let mean = sum / N as f64;
+            // dim paths are mostly ended, bright ones mostly kept
+            let q = (1.0 - beta.max_component()).max(0.05);
+            assert!((survivors as f64 / N as f64 - (1.0 - q)).abs() < 0.01);
+            // five standard deviations of the estimate
+            let tolerance = 5.0 * (q / ((1.0 - q) * N as f64)).sqrt();
+            for c in 0..3 {
+                assert!(
+                    (mean[c] - beta[c]).abs() <= tolerance * beta[c],
+                    "{mean:?} vs {beta:?}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,489 @@
+use crate::{
+    aabb::Aabb,
+    camera::Camera,
+    color::Color,
+    film::Film,
+    hittable::{HitRecord, Hittable, HittableList},
+    interval::Interval,
+    light::{EmissionSample, LightList, LightSample, LightSampler, PowerLightSampler},
+    ray::Ray,
+    rtweekend,
+    vec3::{Point3, Vec3},
+};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::{collections::HashMap, f64::consts::PI, thread};
+
+/// Stochastic progressive photon mapping: every iteration traces one camera
+/// path per pixel to its first non-specular hit, then shoots photons from the
+/// lights and gathers those landing within a shrinking radius of the hit.
+pub struct Sppm {
+    lights: PowerLightSampler,
+    photons_per_iteration: usize,
+    initial_radius: f64,
+}
+
+/// Where a camera path stopped to gather photons.
+struct VisiblePoint {
+    rec: HitRecord,
+    r_in: Ray,
+    beta: Color,
+}
+
+struct SppmPixel {
+    radius: f64,
+    /// radiance reached through emission and direct lighting, summed over iterations
+    ld: Color,
+    vp: Option<VisiblePoint>,
+    /// photons gathered this iteration and their flux
+    m: u64,
+    phi: Color,
+    /// photon count and flux accumulated over the previous iterations
+    n: f64,
+    tau: Color,
+}
+
+/// Uniform grid hashing each visible point into every cell its radius covers.
+struct PhotonGrid {
+    cell_size: f64,
+    cells: HashMap<(i64, i64, i64), Vec<usize>>,
+}
+
+/// Fraction of the newly gathered photons kept by the progressive update.
+const ALPHA: f64 = 2.0 / 3.0;
+
+impl Sppm {
+    /// `initial_radius` is in world units; zero picks one from the scene size.
+    pub fn new(
+        lights: &LightList,
+        world_bbox: &Aabb,
+        photons_per_iteration: usize,
+        initial_radius: f64,
+    ) -> Self {
+        let initial_radius = if initial_radius > 0.0 {
+            initial_radius
+        } else {
+            world_bbox.diagonal().length() * 0.0025
+        };
+        Self {
+            lights: PowerLightSampler::new(lights, world_bbox),
+            photons_per_iteration,
+            initial_radius,
+        }
+    }
+
+    /// Runs `sqrt_spp * sqrt_spp` iterations and adds the final estimate of
+    /// every pixel to `film` once, so it should be converted with a scale of 1.
+    pub fn render(
+        &self,
+        cam: &Camera,
+        world: &HittableList,
+        light_sampler: &dyn LightSampler,
+        sqrt_spp: u32,
+        film: &mut Film,
+    ) {
+        let (width, height) = (film.width(), film.height());
+        let mut pixels: Vec<_> = (0..width * height)
+            .map(|_| SppmPixel {
+                radius: self.initial_radius,
+                ld: Color::zeros(),
+                vp: None,
+                m: 0,
+                phi: Color::zeros(),
+                n: 0.0,
+                tau: Color::zeros(),
+            })
+            .collect();
+
+        let iterations = sqrt_spp * sqrt_spp;
+        let bar = ProgressBar::new(iterations as u64).with_style(
+            ProgressStyle::with_template("[{elapsed_precise}] {bar:80} {percent}%").unwrap(),
+        );
+        let threads_num = thread::available_parallelism().unwrap().get();
+        let photons_per_thread = self.photons_per_iteration.div_ceil(threads_num);
+
+        for iteration in 0..iterations {
+            let (s_i, s_j) = (iteration % sqrt_spp, iteration / sqrt_spp);
+            let chunk_size = pixels.len().div_ceil(threads_num);
+            thread::scope(|scope| {
+                for (chunk_ind, chunk) in pixels.chunks_mut(chunk_size).enumerate() {
+                    scope.spawn(move || {
+                        for (k, pixel) in chunk.iter_mut().enumerate() {
+                            let index = (chunk_ind * chunk_size + k) as u32;
+                            let mut weight = 1.0;
+                            pixel.vp = cam
+                                .get_ray(index % width, index / width, s_i, s_j, &mut weight)
+                                .and_then(|r| {
+                                    self.trace_camera_path(
+                                        cam,
+                                        &r,
+                                        weight,
+                                        world,
+                                        light_sampler,
+                                        pixel,
+                                    )
+                                });
+                        }
+                    });
+                }
+            });
+
+            let grid = PhotonGrid::new(&pixels);
+            let gathered: Vec<_> = thread::scope(|scope| {
+                let handles: Vec<_> = (0..threads_num)
+                    .map(|_| {
+                        let (pixels, grid) = (&pixels, &grid);
+                        scope.spawn(move || {
+                            let mut m = vec![0; pixels.len()];
+                            let mut phi = vec![Color::zeros(); pixels.len()];
+                            for _ in 0..photons_per_thread {
+                                self.trace_photon(cam, world, pixels, grid, &mut m, &mut phi);
+                            }
+                            (m, phi)
+                        })
+                    })
+                    .collect();
+                handles.into_iter().map(|h| h.join().unwrap()).collect()
+            });
+            for (m, phi) in gathered {
+                for (pixel, (m, phi)) in pixels.iter_mut().zip(m.into_iter().zip(phi)) {
+                    pixel.m += m;
+                    pixel.phi += phi;
+                }
+            }
+
+            for pixel in &mut pixels {
+                pixel.update();
+            }
+            bar.inc(1);
+        }
+        bar.finish();
+
+        let total_photons = (iterations as usize * photons_per_thread * threads_num) as f64;
+        for (index, pixel) in pixels.iter().enumerate() {
+            let index = index as u32;
+            let l = pixel.ld / iterations as f64
+                + pixel.tau / (total_photons * PI * pixel.radius * pixel.radius);
+            film.add_sample(
+                (index % width) as f64 + 0.5,
+                (index / width) as f64 + 0.5,
+                &l,
+            );
+        }
+    }
+
+    /// Follows `r`, carrying `weight` times the radiance, through specular
+    /// bounces and scattering in media, adding emission and direct lighting to
+    /// `pixel.ld`, and returns the first non-specular surface hit. Photons are
+    /// only gathered on surfaces, as their density there is per area.
+    fn trace_camera_path(
+        &self,
+        cam: &Camera,
+        r: &Ray,
+        weight: f64,
+        world: &HittableList,
+        light_sampler: &dyn LightSampler,
+        pixel: &mut SppmPixel,
+    ) -> Option<VisiblePoint> {
+        let mut r = *r;
+        let mut beta = Color::ones() * weight;
+        // emission is counted unless the light sampled before already was
+        let mut count_emission = true;
+        for _ in 0..cam.max_depth() {
+            let mut rec = HitRecord::default();
+            if !world.hit(&r, &Interval::new(0.001, f64::INFINITY), &mut rec) {
+                pixel.ld += beta.elemul(&cam.background());
+                return None;
+            }
+            rec.prepare_shading(&r);
+            let mat = rec.mat.clone().unwrap();
+
+            // only camera, specular and medium bounces get here, emission seen
+            // after a diffuse bounce is carried by the photons
+            if count_emission {
+                pixel.ld += beta.elemul(&mat.emitted(&r, &rec, rec.u, rec.v, &rec.p));
+            }
+
+            let mut scattered = Ray::default();
+            let mut attenuation = Color::default();
+            if !mat.scatter(&r, &rec, &mut attenuation, &mut scattered) {
+                return None;
+            }
+            if mat.is_specular() {
+                beta = beta.elemul(&attenuation);
+                r = scattered;
+                count_emission = true;
+                continue;
+            }
+
+            pixel.ld += beta.elemul(&attenuation).elemul(&Self::direct_lighting(
+                &r,
+                &rec,
+                world,
+                light_sampler,
+            ));
+            if mat.is_volumetric() {
+                beta = beta.elemul(&attenuation);
+                r = scattered;
+                count_emission = false;
+                continue;
+            }
+            return Some(VisiblePoint { rec, r_in: r, beta });
+        }
+        None
+    }
+
+    /// Light sampled direct illumination, still to be multiplied by the
+    /// attenuation of the material like in the path tracer.
+    fn direct_lighting(
+        r: &Ray,
+        rec: &HitRecord,
+        world: &HittableList,
+        light_sampler: &dyn LightSampler,
+    ) -> Color {
+        let mat = rec.mat.as_ref().unwrap();
+        let mut sample = LightSample::default();
+        if !light_sampler.sample(&rec.p, &rec.surface_normal(), &mut sample) || sample.pdf <= 0.0 {
+            return Color::zeros();
+        }
+
+        let shadow_ray = Ray::new_with_time(&rec.p, &sample.wi, r.time());
+        let scattering_pdf = mat.scattering_pdf(r, rec, &shadow_ray);
+        if scattering_pdf <= 0.0
+            || world.hit(
+                &shadow_ray,
+                &Interval::new(0.001, sample.distance - 0.001),
+                &mut HitRecord::default(),
+            )
+        {
+            return Color::zeros();
+        }
+        sample.li * scattering_pdf / sample.pdf
+    }
+
+    /// Emits one photon and deposits its flux at every visible point near the
+    /// surfaces it bounces off, skipping the first hit already covered by the
+    /// direct lighting of the camera pass. Scattering in media deposits
+    /// nothing, as no visible points are there.
+    fn trace_photon(
+        &self,
+        cam: &Camera,
+        world: &HittableList,
+        pixels: &[SppmPixel],
+        grid: &PhotonGrid,
+        m: &mut [u64],
+        phi: &mut [Color],
+    ) {
+        let mut index = 0;
+        let mut sample = EmissionSample::default();
+        if !self.lights.sample_index(&mut index)
+            || !self.lights.lights()[index].sample_le(&mut sample)
+            || sample.pdf_pos == 0.0
+            || sample.pdf_dir == 0.0
+            || sample.le == Color::zeros()
+        {
+            return;
+        }
+
+        let cosine = if sample.normal == Vec3::zeros() {
+            1.0
+        } else {
+            (sample.normal * sample.direction).abs()
+        };
+        let mut beta =
+            sample.le * cosine / (self.lights.pmf(index) * sample.pdf_pos * sample.pdf_dir);
+        let mut r = Ray::new_with_time(&sample.origin, &sample.direction, cam.sample_time());
+
+        for depth in 0..cam.max_depth() {
+            let mut rec = HitRecord::default();
+            if !world.hit(&r, &Interval::new(0.001, f64::INFINITY), &mut rec) {
+                return;
+            }
+            rec.prepare_shading(&r);
+
+            let mat = rec.mat.clone().unwrap();
+            if depth > 0 && !mat.is_volumetric() {
+                let wi = Ray::new_with_time(&rec.p, &-r.direction().unit(), r.time());
+                for k in grid.within(pixels, &rec.p) {
+                    let vp = pixels[k].vp.as_ref().unwrap();
+                    let wi = Ray::new_with_time(&vp.rec.p, wi.direction(), r.time());
+                    let f = vp.rec.mat.as_ref().unwrap().bsdf(&vp.r_in, &vp.rec, &wi);
+                    phi[k] += beta.elemul(&f);
+                    m[k] += 1;
+                }
+            }
+
+            let mut scattered = Ray::default();
+            let mut attenuation = Color::default();
+            if !mat.scatter(&r, &rec, &mut attenuation, &mut scattered) {
+                return;
+            }
+
+            // Russian roulette keeps the photon flux roughly constant
+            let beta_new = beta.elemul(&attenuation);
+            let q = (1.0 - beta_new.luminance() / beta.luminance()).max(0.0);
+            if rtweekend::random_double() < q {
+                return;
+            }
+            beta = beta_new / (1.0 - q);
+            r = scattered;
+        }
+    }
+}
+
+impl SppmPixel {
+    /// Shrinks the radius so that only `ALPHA` of the new photons count,
+    /// rescaling the accumulated flux to the smaller disc.
+    fn update(&mut self) {
+        if self.m > 0 {
+            let n_new = self.n + ALPHA * self.m as f64;
+            let radius_new = self.radius * (n_new / (self.n + self.m as f64)).sqrt();
+            let beta = self.vp.as_ref().unwrap().beta;
+            self.tau = (self.tau + beta.elemul(&self.phi)) * (radius_new * radius_new)
+                / (self.radius * self.radius);
+            self.n = n_new;
+            self.radius = radius_new;
+        }
+        self.m = 0;
+        self.phi = Color::zeros();
+        self.vp = None;
+    }
+}
+
+impl PhotonGrid {
+    fn new(pixels: &[SppmPixel]) -> Self {
+        let cell_size = pixels
+            .iter()
+            .filter(|pixel| pixel.vp.is_some())
+            .map(|pixel| pixel.radius)
+            .fold(0.0, f64::max)
+            .max(1e-4);
+
+        let mut grid = Self {
+            cell_size,
+            cells: HashMap::new(),
+        };
+        for (k, pixel) in pixels.iter().enumerate() {
+            if let Some(vp) = &pixel.vp {
+                let radius = Vec3::new(pixel.radius, pixel.radius, pixel.radius);
+                let lo = grid.cell(&(vp.rec.p - radius));
+                let hi = grid.cell(&(vp.rec.p + radius));
+                for x in lo.0..=hi.0 {
+                    for y in lo.1..=hi.1 {
+                        for z in lo.2..=hi.2 {
+                            grid.cells.entry((x, y, z)).or_default().push(k);
+                        }
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    fn cell(&self, p: &Point3) -> (i64, i64, i64) {
+        (
+            (p.x / self.cell_size).floor() as i64,
+            (p.y / self.cell_size).floor() as i64,
+            (p.z / self.cell_size).floor() as i64,
+        )
+    }
+
+    /// Indices of the pixels whose visible point may lie within reach of `p`.
+    fn candidates(&self, p: &Point3) -> &[usize] {
+        self.cells
+            .get(&self.cell(p))
+            .map_or(&[], |cell| cell.as_slice())
+    }
+
+    /// Indices of the pixels whose visible point has `p` within its radius.
+    fn within<'a>(
+        &'a self,
+        pixels: &'a [SppmPixel],
+        p: &'a Point3,
+    ) -> impl Iterator<Item = usize> + 'a {
+        self.candidates(p).iter().copied().filter(move |&k| {
+            let pixel = &pixels[k];
+            let vp = pixel.vp.as_ref().unwrap();
+            (vp.rec.p - *p).squared_length() <= pixel.radius * pixel.radius
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(p: &Point3, radius: f64) -> SppmPixel {
+        let rec = HitRecord {
+            p: *p,
+            ..Default::default()
+        };
+        SppmPixel {
+            radius,
+            ld: Color::zeros(),
+            vp: Some(VisiblePoint {
+                rec,
+                r_in: Ray::default(),
+                beta: Color::ones(),
+            }),
+            m: 0,
+            phi: Color::zeros(),
+            n: 0.0,
+            tau: Color::zeros(),
+        }
+    }
+
+    #[test]
+    fn test_grid_finds_exactly_the_points_in_reach() {
+        let pixels: Vec<SppmPixel> = (0..200)
+            .map(|_| {
+                pixel(
+                    &Vec3::random_in_range(-2.0, 2.0),
+                    rtweekend::random_double_in_range(0.05, 0.5),
+                )
+            })
+            .collect();
+        let grid = PhotonGrid::new(&pixels);
+
+        for _ in 0..1000 {
+            let p = Vec3::random_in_range(-2.5, 2.5);
+            let mut found: Vec<usize> = grid.within(&pixels, &p).collect();
+            found.sort_unstable();
+            let expected: Vec<usize> = (0..pixels.len())
+                .filter(|&k| {
+                    let vp = pixels[k].vp.as_ref().unwrap();
+                    (vp.rec.p - p).length() <= pixels[k].radius
+                })
+                .collect();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn test_update_keeps_alpha_of_the_new_photons() {
+        let mut pixel = pixel(&Point3::zeros(), 1.0);
+        pixel.m = 9;
+        pixel.phi = Color::new(3.0, 3.0, 3.0);
+        pixel.update();
+        assert!((pixel.n - 9.0 * ALPHA).abs() < 1e-12);
+        assert!((pixel.radius - ALPHA.sqrt()).abs() < 1e-12);
+        // the flux shrinks with the area of the disc
+        assert!((pixel.tau.x - 3.0 * ALPHA).abs() < 1e-12);
+        assert!(pixel.m == 0 && pixel.phi == Color::zeros() && pixel.vp.is_none());
+
+        // an iteration without photons leaves it as it is
+        let radius = pixel.radius;
+        pixel.update();
+        assert_eq!(pixel.radius, radius);
+
+        pixel.vp = Some(VisiblePoint {
+            rec: HitRecord::default(),
+            r_in: Ray::default(),
+            beta: Color::ones(),
+        });
+        pixel.m = 3;
+        let n = pixel.n;
+        pixel.update();
+        let ratio = (n + ALPHA * 3.0) / (n + 3.0);
+        assert!((pixel.radius - radius * ratio.sqrt()).abs() < 1e-12);
+    }
+}
@@ -1,7 +1,4 @@
-use std::{
-    f64::{INFINITY, NEG_INFINITY},
-    ops::Add,
-};
+use std::ops::Add;
 
 #[derive(Clone, Copy)]
 pub struct Interval {
@@ -12,8 +9,8 @@ pub struct Interval {
 impl Default for Interval {
     fn default() -> Self {
         Self {
-            min: INFINITY,
-            max: NEG_INFINITY,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
         }
     }
 }
@@ -57,8 +54,8 @@ impl Interval {
         Self::new(self.min - padding, self.max + padding)
     }
 
-    pub const EMPTY: Self = Self::new(INFINITY, NEG_INFINITY);
-    pub const UNIVERSE: Self = Self::new(NEG_INFINITY, INFINITY);
+    pub const EMPTY: Self = Self::new(f64::INFINITY, f64::NEG_INFINITY);
+    pub const UNIVERSE: Self = Self::new(f64::NEG_INFINITY, f64::INFINITY);
 }
 
 impl Add<f64> for Interval {
@@ -0,0 +1,487 @@
+use crate::{
+    ray::Ray,
+    rtweekend,
+    vec3::{Point3, Vec3},
+};
+use std::fs;
+
+/// One spherical interface of a lens prescription, in millimeters, listed
+/// from the front (scene side) of the lens towards the film. A zero
+/// curvature radius marks the aperture stop.
+#[derive(Clone, Copy)]
+pub struct LensElement {
+    pub curvature_radius: f64,
+    /// distance to the next interface, or to the film for the last one
+    pub thickness: f64,
+    /// index of refraction behind the interface, zero counting as air
+    pub eta: f64,
+    pub aperture_radius: f64,
+}
+
+/// Number of film radii the exit pupil is bounded at.
+const PUPIL_BOUNDS: usize = 64;
+/// Grid resolution of the rear element points tried per film radius.
+const PUPIL_SAMPLES: usize = 128;
+
+/// Axis aligned rectangle on the plane of the rear element.
+#[derive(Clone, Copy)]
+struct PupilBounds {
+    min: (f64, f64),
+    max: (f64, f64),
+}
+
+impl PupilBounds {
+    fn area(&self) -> f64 {
+        (self.max.0 - self.min.0) * (self.max.1 - self.min.1)
+    }
+}
+
+/// A camera lens made of spherical elements that rays are refracted through,
+/// after the realistic camera of pbrt. Works in a camera space measured in
+/// millimeters with the film at z = 0 and the scene towards +z.
+pub struct LensSystem {
+    elements: Vec<LensElement>,
+    sensor_width: f64,
+    sensor_height: f64,
+    focal_length: f64,
+    /// of the exit pupil as seen from film points at increasing distance
+    /// from the center, along the x axis
+    pupil_bounds: Vec<PupilBounds>,
+    mm_to_scene: f64,
+}
+
+impl LensSystem {
+    /// Reads a prescription from the assets directory, in the format of pbrt
+    /// lens files: radius, thickness, index of refraction and aperture
+    /// diameter per line, with `#` comments.
+    pub fn load(filename: &str) -> Vec<LensElement> {
+        let path = String::from("assets/") + filename;
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => {
+                eprintln!("ERROR: Could not load lens file '{}'.", path);
+                return Vec::new();
+            }
+        };
+
+        text.lines()
+            .map(|line| line.split('#').next().unwrap())
+            .filter_map(|line| {
+                let values: Vec<f64> = line
+                    .split_whitespace()
+                    .filter_map(|value| value.parse().ok())
+                    .collect();
+                match values[..] {
+                    [curvature_radius, thickness, eta, aperture_diameter] => Some(LensElement {
+                        curvature_radius,
+                        thickness,
+                        eta,
+                        aperture_radius: aperture_diameter / 2.0,
+                    }),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Sets up the lens with its stop opened to `aperture_diameter` (mm, no
+    /// wider than the prescription allows) and focuses it at `focus_dist`
+    /// scene units by moving the film.
+    pub fn new(
+        elements: &[LensElement],
+        aperture_diameter: f64,
+        sensor_width: f64,
+        aspect_ratio: f64,
+        focus_dist: f64,
+        units_per_meter: f64,
+    ) -> Self {
+        let mut elements = elements.to_vec();
+        for element in &mut elements {
+            if element.curvature_radius == 0.0 {
+                if aperture_diameter / 2.0 > element.aperture_radius {
+                    eprintln!(
+                        "Aperture diameter {} is wider than the lens allows, using {}",
+                        aperture_diameter,
+                        element.aperture_radius * 2.0
+                    );
+                } else {
+                    element.aperture_radius = aperture_diameter / 2.0;
+                }
+            }
+        }
+
+        let mut lens = Self {
+            elements,
+            sensor_width,
+            sensor_height: sensor_width / aspect_ratio,
+            focal_length: 0.0,
+            pupil_bounds: Vec::new(),
+            mm_to_scene: units_per_meter / 1000.0,
+        };
+
+        let (pz, fz) = lens.thick_lens_approximation();
+        lens.focal_length = fz[0] - pz[0];
+        let film_distance = lens.focus_thick_lens(focus_dist / lens.mm_to_scene, &pz, &fz);
+        if let Some(last) = lens.elements.last_mut() {
+            last.thickness = film_distance;
+        }
+
+        let half_diagonal = lens.half_diagonal();
+        lens.pupil_bounds = (0..PUPIL_BOUNDS)
+            .map(|k| {
+                lens.bound_exit_pupil(
+                    k as f64 / PUPIL_BOUNDS as f64 * half_diagonal,
+                    (k + 1) as f64 / PUPIL_BOUNDS as f64 * half_diagonal,
+                )
+            })
+            .collect();
+        lens
+    }
+
+    /// Vertical field of view in degrees of a thin lens of the same focal length.
+    pub fn vfov(&self) -> f64 {
+        2.0 * (self.sensor_height / (2.0 * self.focal_length))
+            .atan()
+            .to_degrees()
+    }
+
+    pub fn mm_to_scene(&self) -> f64 {
+        self.mm_to_scene
+    }
+
+    /// A ray leaving the front of the lens for film position (`s`, `t`) in
+    /// [0, 1]^2 from the top left of the image, and its weight accounting for
+    /// the falloff of irradiance towards the film edges. `None` when the
+    /// sampled ray is blocked inside the lens, which causes vignetting.
+    pub fn generate_ray(&self, s: f64, t: f64, weight: &mut f64) -> Option<Ray> {
+        // the image on the film is upside down
+        let p_film = Point3::new(
+            (0.5 - s) * self.sensor_width,
+            (t - 0.5) * self.sensor_height,
+            0.0,
+        );
+        let mut area = 0.0;
+        let p_rear = self.sample_exit_pupil(&p_film, &mut area);
+        let r_film = Ray::new(&p_film, &(p_rear - p_film));
+
+        let r = self.trace_from_film(&r_film)?;
+        let cos_theta = r_film.direction().unit().z;
+        *weight = cos_theta.powi(4) * area / self.pupil_bounds[0].area();
+        Some(r)
+    }
+
+    fn half_diagonal(&self) -> f64 {
+        (self.sensor_width.powi(2) + self.sensor_height.powi(2)).sqrt() / 2.0
+    }
+
+    fn lens_rear_z(&self) -> f64 {
+        self.elements
+            .last()
+            .map_or(0.0, |element| element.thickness)
+    }
+
+    fn lens_front_z(&self) -> f64 {
+        self.elements.iter().map(|element| element.thickness).sum()
+    }
+
+    fn rear_element_radius(&self) -> f64 {
+        self.elements
+            .last()
+            .map_or(0.0, |element| element.aperture_radius)
+    }
+
+    /// Follows a ray from the film side out through every element.
+    fn trace_from_film(&self, r_camera: &Ray) -> Option<Ray> {
+        let mut element_z = 0.0;
+        let mut r = to_lens_space(r_camera);
+        for (i, element) in self.elements.iter().enumerate().rev() {
+            element_z -= element.thickness;
+            let eta_t = if i > 0 && self.elements[i - 1].eta != 0.0 {
+                self.elements[i - 1].eta
+            } else {
+                1.0
+            };
+            r = self.refract_at(element, element_z, &r, element.eta, eta_t)?;
+        }
+        Some(to_lens_space(&r))
+    }
+
+    /// Follows a ray from the scene side in through every element.
+    fn trace_from_scene(&self, r_camera: &Ray) -> Option<Ray> {
+        let mut element_z = -self.lens_front_z();
+        let mut r = to_lens_space(r_camera);
+        for (i, element) in self.elements.iter().enumerate() {
+            let eta_i = if i == 0 || self.elements[i - 1].eta == 0.0 {
+                1.0
+            } else {
+                self.elements[i - 1].eta
+            };
+            let eta_t = if element.eta != 0.0 { element.eta } else { 1.0 };
+            r = self.refract_at(element, element_z, &r, eta_i, eta_t)?;
+            element_z += element.thickness;
+        }
+        Some(to_lens_space(&r))
+    }
+
+    /// Intersects a lens space ray with the interface at `element_z`, and
+    /// bends it unless the interface is the aperture stop.
+    fn refract_at(
+        &self,
+        element: &LensElement,
+        element_z: f64,
+        r: &Ray,
+        eta_i: f64,
+        eta_t: f64,
+    ) -> Option<Ray> {
+        let origin = *r.origin();
+        let direction = *r.direction();
+
+        let is_stop = element.curvature_radius == 0.0;
+        let (t, normal) = if is_stop {
+            if direction.z == 0.0 {
+                return None;
+            }
+            ((element_z - origin.z) / direction.z, Vec3::zeros())
+        } else {
+            let z_center = element_z + element.curvature_radius;
+            intersect_spherical_element(element.curvature_radius, z_center, r)?
+        };
+        if t < 0.0 {
+            return None;
+        }
+
+        let p_hit = r.at(t);
+        if p_hit.x * p_hit.x + p_hit.y * p_hit.y > element.aperture_radius.powi(2) {
+            return None;
+        }
+        if is_stop {
+            return Some(Ray::new(&p_hit, &direction));
+        }
+
+        let wt = refract(&-direction.unit(), &normal, eta_i / eta_t)?;
+        Some(Ray::new(&p_hit, &wt))
+    }
+
+    /// Principal plane and focal point z of the lens on either side, found
+    /// by tracing rays parallel to the axis through it.
+    fn thick_lens_approximation(&self) -> ([f64; 2], [f64; 2]) {
+        let x = 0.001 * 2.0 * self.half_diagonal();
+        let mut pz = [0.0; 2];
+        let mut fz = [0.0; 2];
+
+        let r_scene = Ray::new(
+            &Point3::new(x, 0.0, self.lens_front_z() + 1.0),
+            &Vec3::new(0.0, 0.0, -1.0),
+        );
+        match self.trace_from_scene(&r_scene) {
+            Some(r_film) => (pz[0], fz[0]) = cardinal_points(&r_scene, &r_film),
+            None => eprintln!("Unable to trace a ray from the scene through the lens"),
+        }
+
+        let r_film = Ray::new(
+            &Point3::new(x, 0.0, self.lens_rear_z() - 1.0),
+            &Vec3::new(0.0, 0.0, 1.0),
+        );
+        match self.trace_from_film(&r_film) {
+            Some(r_scene) => (pz[1], fz[1]) = cardinal_points(&r_film, &r_scene),
+            None => eprintln!("Unable to trace a ray from the film through the lens"),
+        }
+
+        (pz, fz)
+    }
+
+    /// Distance from the rear element to the film that brings a point
+    /// `focus_distance` millimeters in front of the film into focus.
+    fn focus_thick_lens(&self, focus_distance: f64, pz: &[f64; 2], fz: &[f64; 2]) -> f64 {
+        let f = fz[0] - pz[0];
+        let z = -focus_distance;
+        let c = (pz[1] - z - pz[0]) * (pz[1] - z - 4.0 * f - pz[0]);
+        if c <= 0.0 {
+            eprintln!(
+                "Focus distance {} is too short for a lens of focal length {}",
+                focus_distance, f
+            );
+            return self.lens_rear_z();
+        }
+        let delta = 0.5 * (pz[1] - z + pz[0] - c.sqrt());
+        self.lens_rear_z() + delta
+    }
+
+    /// Bounds of the points on the rear element plane that film points with
+    /// x in [x0, x1] (and y = 0) see through the whole lens.
+    fn bound_exit_pupil(&self, x0: f64, x1: f64) -> PupilBounds {
+        let rear_radius = 1.5 * self.rear_element_radius();
+        let rear_z = self.lens_rear_z();
+        let mut bounds: Option<PupilBounds> = None;
+
+        for i in 0..PUPIL_SAMPLES {
+            for j in 0..PUPIL_SAMPLES {
+                let p_film = Point3::new(x0 + (x1 - x0) * rtweekend::random_double(), 0.0, 0.0);
+                let p_rear = Point3::new(
+                    rear_radius * (2.0 * (i as f64 + 0.5) / PUPIL_SAMPLES as f64 - 1.0),
+                    rear_radius * (2.0 * (j as f64 + 0.5) / PUPIL_SAMPLES as f64 - 1.0),
+                    rear_z,
+                );
+                if self
+                    .trace_from_film(&Ray::new(&p_film, &(p_rear - p_film)))
+                    .is_some()
+                {
+                    let b = bounds.get_or_insert(PupilBounds {
+                        min: (p_rear.x, p_rear.y),
+                        max: (p_rear.x, p_rear.y),
+                    });
+                    b.min = (b.min.0.min(p_rear.x), b.min.1.min(p_rear.y));
+                    b.max = (b.max.0.max(p_rear.x), b.max.1.max(p_rear.y));
+                }
+            }
+        }
+
+        match bounds {
+            // one grid cell of slack for points between the samples
+            Some(b) => {
+                let pad = 2.0 * rear_radius / PUPIL_SAMPLES as f64;
+                PupilBounds {
+                    min: (b.min.0 - pad, b.min.1 - pad),
+                    max: (b.max.0 + pad, b.max.1 + pad),
+                }
+            }
+            None => PupilBounds {
+                min: (-rear_radius, -rear_radius),
+                max: (rear_radius, rear_radius),
+            },
+        }
+    }
+
+    /// A point on the rear element plane within the exit pupil bounds of
+    /// `p_film`, rotated from the x axis to the film point's angle.
+    fn sample_exit_pupil(&self, p_film: &Point3, area: &mut f64) -> Point3 {
+        let r_film = (p_film.x * p_film.x + p_film.y * p_film.y).sqrt();
+        let index =
+            ((r_film / self.half_diagonal() * PUPIL_BOUNDS as f64) as usize).min(PUPIL_BOUNDS - 1);
+        let bounds = &self.pupil_bounds[index];
+        *area = bounds.area();
+
+        let x = bounds.min.0 + (bounds.max.0 - bounds.min.0) * rtweekend::random_double();
+        let y = bounds.min.1 + (bounds.max.1 - bounds.min.1) * rtweekend::random_double();
+        let (sin_theta, cos_theta) = if r_film != 0.0 {
+            (p_film.y / r_film, p_film.x / r_film)
+        } else {
+            (0.0, 1.0)
+        };
+        Point3::new(
+            cos_theta * x - sin_theta * y,
+            sin_theta * x + cos_theta * y,
+            self.lens_rear_z(),
+        )
+    }
+}
+
+/// Camera and lens space differ by the sign of z.
+fn to_lens_space(r: &Ray) -> Ray {
+    let (o, d) = (r.origin(), r.direction());
+    Ray::new(&Point3::new(o.x, o.y, -o.z), &Vec3::new(d.x, d.y, -d.z))
+}
+
+/// Hit distance and normal, facing against the ray, of the sphere of the
+/// interface centered on the axis at `z_center`.
+fn intersect_spherical_element(radius: f64, z_center: f64, r: &Ray) -> Option<(f64, Vec3)> {
+    let o = *r.origin() - Vec3::new(0.0, 0.0, z_center);
+    let d = *r.direction();
+    let a = d.squared_length();
+    let b = 2.0 * (d * o);
+    let c = o.squared_length() - radius * radius;
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let sqrt_d = discriminant.sqrt();
+    let t0 = (-b - sqrt_d) / (2.0 * a);
+    let t1 = (-b + sqrt_d) / (2.0 * a);
+    // the interface is the half of the sphere facing the ray's side
+    let use_closer = (d.z > 0.0) ^ (radius < 0.0);
+    let t = if use_closer { t0.min(t1) } else { t0.max(t1) };
+    if t < 0.0 {
+        return None;
+    }
+
+    let n = (o + d * t).unit();
+    Some((t, if n * d > 0.0 { -n } else { n }))
+}
+
+/// Snell's law for `wi` pointing away from the surface, `None` on total
+/// internal reflection.
+fn refract(wi: &Vec3, n: &Vec3, eta: f64) -> Option<Vec3> {
+    let cos_theta_i = *n * *wi;
+    let sin2_theta_i = (1.0 - cos_theta_i * cos_theta_i).max(0.0);
+    let sin2_theta_t = eta * eta * sin2_theta_i;
+    if sin2_theta_t >= 1.0 {
+        return None;
+    }
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+    Some(-*wi * eta + *n * (eta * cos_theta_i - cos_theta_t))
+}
+
+/// Principal plane and focal point z from a ray parallel to the axis and the
+/// ray it leaves the lens as.
+fn cardinal_points(r_in: &Ray, r_out: &Ray) -> (f64, f64) {
+    let tf = -r_out.origin().x / r_out.direction().x;
+    let fz = -r_out.at(tf).z;
+    let tp = (r_in.origin().x - r_out.origin().x) / r_out.direction().x;
+    let pz = -r_out.at(tp).z;
+    (pz, fz)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The double Gauss lens stopped down to `aperture` mm, focused at
+    /// `focus_dist` meters.
+    fn lens(aperture: f64, focus_dist: f64) -> LensSystem {
+        let elements = LensSystem::load("lenses/dgauss.50mm.dat");
+        LensSystem::new(&elements, aperture, 36.0, 1.5, focus_dist, 1.0)
+    }
+
+    /// Spread on the film of rays from the on-axis point `distance` mm in
+    /// front of it, through points of the front element up to `radius` off axis.
+    fn blur(lens: &LensSystem, distance: f64, radius: f64) -> f64 {
+        let p = Point3::new(0.0, 0.0, distance);
+        let front_z = lens.lens_front_z();
+        let mut spread: f64 = 0.0;
+        let mut traced = 0;
+        for i in 0..=10 {
+            let target = Point3::new(radius * i as f64 / 10.0, 0.0, front_z);
+            let Some(r) = lens.trace_from_scene(&Ray::new(&p, &(target - p))) else {
+                continue;
+            };
+            // where it crosses the film at z = 0
+            let x = r.at(-r.origin().z / r.direction().z).x;
+            spread = spread.max(x.abs());
+            traced += 1;
+        }
+        assert!(traced > 5);
+        spread
+    }
+
+    #[test]
+    fn test_focal_length() {
+        let lens = lens(17.1, 7.0);
+        assert!((lens.focal_length - 50.0).abs() < 1.0);
+        // focusing closer moves the film away from the lens
+        assert!(lens.lens_rear_z() > 0.0);
+        assert!(self::lens(17.1, 1.0).lens_rear_z() > lens.lens_rear_z());
+    }
+
+    #[test]
+    fn test_focused_point_images_on_film() {
+        for focus_dist in [1.0, 3.0, 10.0] {
+            let lens = lens(4.0, focus_dist);
+            let in_focus = blur(&lens, focus_dist * 1000.0, 2.0);
+            assert!(in_focus < 0.01, "{in_focus} mm at {focus_dist} m");
+            // points nearer or farther are blurred
+            assert!(blur(&lens, focus_dist * 500.0, 2.0) > 10.0 * in_focus);
+            assert!(blur(&lens, focus_dist * 2000.0, 2.0) > 10.0 * in_focus);
+        }
+    }
+}
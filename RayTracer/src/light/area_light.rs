@@ -0,0 +1,201 @@
+use super::{EmissionSample, Light, LightBounds, LightSample};
+use crate::{
+    aabb::Aabb,
+    color::Color,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::DiffuseLight,
+    onb::Onb,
+    pdf::{HittablePdf, Pdf},
+    ray::Ray,
+    rtweekend,
+    vec3::{Point3, Vec3},
+};
+use std::{f64::consts::PI, sync::Arc};
+
+/// Makes an emissive shape available to explicit light sampling. The shape keeps
+/// its own material, `emission` is only used to estimate the emitted power.
+pub struct AreaLight {
+    shape: Arc<dyn Hittable>,
+    power: f64,
+    two_sided: bool,
+}
+
+impl AreaLight {
+    pub fn new(shape: &Arc<dyn Hittable>, emission: &DiffuseLight) -> Self {
+        Self {
+            shape: shape.clone(),
+            power: emission.power(shape.area()),
+            two_sided: emission.is_two_sided(),
+        }
+    }
+
+    /// Hits the surface point `p` with a short ray arriving from direction `w`.
+    fn probe(&self, p: &Point3, w: &Vec3, rec: &mut HitRecord) -> bool {
+        const DELTA: f64 = 0.001;
+        let r = Ray::new(&(*p + *w * DELTA), &-*w).ignoring_alpha();
+        self.shape.hit(&r, &Interval::new(0.0, 2.0 * DELTA), rec)
+    }
+
+    fn emitted_towards(&self, p: &Point3, w: &Vec3) -> Color {
+        let mut rec = HitRecord::default();
+        if !self.probe(p, w, &mut rec) {
+            return Color::zeros();
+        }
+
+        match &rec.mat {
+            Some(mat) => mat.emitted(&Ray::new(&(*p + *w), &-*w), &rec, rec.u, rec.v, &rec.p),
+            None => Color::zeros(),
+        }
+    }
+}
+
+impl Light for AreaLight {
+    fn sample_li(&self, origin: &Point3, sample: &mut LightSample) -> bool {
+        let shape_pdf = HittablePdf::new(&self.shape, origin);
+        let direction = shape_pdf.generate();
+        let r = Ray::new(origin, &direction).ignoring_alpha();
+        let mut rec = HitRecord::default();
+        if !self
+            .shape
+            .hit(&r, &Interval::new(0.001, f64::INFINITY), &mut rec)
+        {
+            return false;
+        }
+
+        sample.pdf = shape_pdf.value(&direction);
+        if sample.pdf <= 0.0 {
+            return false;
+        }
+
+        // the ray sees through no cutout, so the emission is weighted by the
+        // alpha instead
+        sample.li = match &rec.mat {
+            Some(mat) => mat.emitted(&r, &rec, rec.u, rec.v, &rec.p) * mat.alpha(&rec),
+            None => return false,
+        };
+        sample.distance = rec.t * direction.length();
+        sample.wi = direction.unit();
+        sample.normal = rec.normal;
+        sample.is_delta = false;
+        true
+    }
+
+    fn power(&self, _world_bbox: &Aabb) -> f64 {
+        self.power
+    }
+
+    fn pdf_li(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        HittablePdf::new(&self.shape, origin).value(direction)
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        Some(LightBounds {
+            bbox: self.shape.bounding_box(),
+            phi: self.power,
+            cone: self.shape.normal_cone(),
+            cos_theta_e: 0.0,
+            two_sided: self.two_sided,
+        })
+    }
+
+    fn sample_le(&self, sample: &mut EmissionSample) -> bool {
+        let p = self.shape.random_point();
+
+        let mut rec = HitRecord::default();
+        if !self.probe(&p, &Vec3::random_unit_vector(), &mut rec) {
+            return false;
+        }
+        let mut normal = if rec.front_face {
+            rec.normal
+        } else {
+            -rec.normal
+        };
+        if self.two_sided && rtweekend::random_double() < 0.5 {
+            normal = -normal;
+        }
+
+        let mut uvw = Onb::new();
+        uvw.build_from_w(&normal);
+        let local = Vec3::random_cosine_direction();
+        let direction = uvw.local_with_vec3(&local);
+
+        sample.origin = p;
+        sample.direction = direction;
+        sample.normal = normal;
+        sample.le = self.emitted_towards(&p, &direction);
+        sample.pdf_pos = 1.0 / self.shape.area();
+        sample.pdf_dir = local.z / PI * if self.two_sided { 0.5 } else { 1.0 };
+        sample.is_delta = false;
+        sample.pdf_dir > 0.0
+    }
+
+    fn pdf_le(&self, p: &Point3, w: &Vec3, pdf_pos: &mut f64, pdf_dir: &mut f64) {
+        let mut rec = HitRecord::default();
+        if !self.probe(p, w, &mut rec) || !(rec.front_face || self.two_sided) {
+            *pdf_pos = 0.0;
+            *pdf_dir = 0.0;
+            return;
+        }
+
+        *pdf_pos = 1.0 / self.shape.area();
+        *pdf_dir = (rec.normal * *w).max(0.0) / PI * if self.two_sided { 0.5 } else { 1.0 };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hittable::{Quad, Sphere},
+        material::Material,
+    };
+
+    fn lights() -> Vec<AreaLight> {
+        let emission = Arc::new(DiffuseLight::from_color(&Color::new(4.0, 4.0, 4.0)));
+        let mat = emission.clone() as Arc<dyn Material>;
+        let shapes: [Arc<dyn Hittable>; 2] = [
+            Arc::new(Quad::new(
+                &Point3::new(-1.0, 1.0, -1.0),
+                &Vec3::new(2.0, 0.0, 0.0),
+                &Vec3::new(0.0, 0.0, 2.0),
+                &mat,
+            )),
+            Arc::new(Sphere::new(&Point3::new(0.0, 2.0, 0.0), 1.0, &mat)),
+        ];
+        shapes
+            .iter()
+            .map(|shape| AreaLight::new(shape, &emission))
+            .collect()
+    }
+
+    #[test]
+    fn test_sample_li_density_matches_pdf_li() {
+        for light in lights() {
+            for _ in 0..1000 {
+                let mut sample = LightSample::default();
+                if !light.sample_li(&Point3::zeros(), &mut sample) {
+                    continue;
+                }
+                let pdf = light.pdf_li(&Point3::zeros(), &sample.wi);
+                assert!((sample.pdf - pdf).abs() <= 1e-6 * pdf);
+                assert!(!sample.is_delta && sample.li == Color::new(4.0, 4.0, 4.0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_pdf_li_integrates_to_one() {
+        const N: usize = 200_000;
+        for light in lights() {
+            // over uniformly sampled directions
+            let integral = (0..N)
+                .map(|_| light.pdf_li(&Point3::zeros(), &Vec3::random_unit_vector()))
+                .sum::<f64>()
+                * 4.0
+                * PI
+                / N as f64;
+            assert!((integral - 1.0).abs() < 0.03);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use super::{Light, LightSample};
+use crate::{
+    aabb::Aabb,
+    color::Color,
+    vec3::{Point3, Vec3},
+};
+use std::f64::consts::PI;
+
+pub struct DirectionalLight {
+    direction: Vec3,
+    radiance: Color,
+}
+
+impl DirectionalLight {
+    /// `direction` is the direction the light travels in.
+    pub fn new(direction: &Vec3, radiance: &Color) -> Self {
+        Self {
+            direction: direction.unit(),
+            radiance: *radiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample_li(&self, _origin: &Point3, sample: &mut LightSample) -> bool {
+        sample.wi = -self.direction;
+        sample.distance = f64::INFINITY;
+        sample.li = self.radiance;
+        sample.normal = Vec3::zeros();
+        sample.pdf = 1.0;
+        sample.is_delta = true;
+        true
+    }
+
+    fn power(&self, world_bbox: &Aabb) -> f64 {
+        let world_radius = world_bbox.diagonal().length() / 2.0;
+        PI * world_radius.powi(2) * self.radiance.luminance()
+    }
+}
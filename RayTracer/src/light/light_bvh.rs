@@ -0,0 +1,430 @@
+use super::{Light, LightList, LightSample, LightSampler};
+use crate::{
+    aabb::Aabb,
+    direction_cone::DirectionCone,
+    interval::Interval,
+    ray::Ray,
+    rtweekend,
+    vec3::{Point3, Vec3},
+};
+use std::{cmp::Ordering, sync::Arc};
+
+/// Spatial and directional bounds of the emission of one or more lights.
+#[derive(Clone, Copy)]
+pub struct LightBounds {
+    pub bbox: Aabb,
+    pub phi: f64,
+    /// bounds the main emission directions
+    pub cone: DirectionCone,
+    /// how far past `cone` the emission falls off to zero
+    pub cos_theta_e: f64,
+    pub two_sided: bool,
+}
+
+impl LightBounds {
+    pub fn from_bounds(a: &Self, b: &Self) -> Self {
+        if a.phi == 0.0 {
+            return *b;
+        }
+        if b.phi == 0.0 {
+            return *a;
+        }
+
+        Self {
+            bbox: Aabb::from_aabbs(&a.bbox, &b.bbox),
+            phi: a.phi + b.phi,
+            cone: DirectionCone::from_cones(&a.cone, &b.cone),
+            cos_theta_e: a.cos_theta_e.min(b.cos_theta_e),
+            two_sided: a.two_sided || b.two_sided,
+        }
+    }
+
+    /// Conservative estimate of the contribution of the bounded lights to a
+    /// point `p` with surface normal `n`.
+    fn importance(&self, p: &Point3, n: &Vec3) -> f64 {
+        let pc = self.bbox.centroid();
+        let radius = self.bbox.diagonal().length() / 2.0;
+        let to_p = *p - pc;
+        let distance_squared = to_p.squared_length().max(radius);
+
+        let wi = if to_p.near_zero() {
+            Vec3::zeros()
+        } else {
+            to_p.unit()
+        };
+        let mut cos_theta_w = self.cone.w * wi;
+        if self.two_sided {
+            cos_theta_w = cos_theta_w.abs();
+        }
+        let sin_theta_w = safe_sqrt(1.0 - cos_theta_w.powi(2));
+
+        // angle subtended by the bounding sphere of the lights
+        let cos_theta_b = if to_p.squared_length() < radius.powi(2) {
+            -1.0
+        } else {
+            safe_sqrt(1.0 - radius.powi(2) / to_p.squared_length())
+        };
+        let sin_theta_b = safe_sqrt(1.0 - cos_theta_b.powi(2));
+
+        let cos_theta_o = self.cone.cos_theta;
+        let sin_theta_o = safe_sqrt(1.0 - cos_theta_o.powi(2));
+
+        let cos_theta_x = cos_sub_clamped(sin_theta_w, cos_theta_w, sin_theta_o, cos_theta_o);
+        let sin_theta_x = sin_sub_clamped(sin_theta_w, cos_theta_w, sin_theta_o, cos_theta_o);
+        let cos_theta_p = cos_sub_clamped(sin_theta_x, cos_theta_x, sin_theta_b, cos_theta_b);
+        if cos_theta_p <= self.cos_theta_e {
+            return 0.0;
+        }
+
+        let mut importance = self.phi * cos_theta_p / distance_squared;
+
+        if *n != Vec3::zeros() {
+            let cos_theta_i = (wi * *n).abs();
+            let sin_theta_i = safe_sqrt(1.0 - cos_theta_i.powi(2));
+            importance *= cos_sub_clamped(sin_theta_i, cos_theta_i, sin_theta_b, cos_theta_b);
+        }
+
+        importance.max(0.0)
+    }
+}
+
+enum LightBvhNode {
+    Leaf(Arc<dyn Light>, LightBounds),
+    Interior(Box<LightBvhNode>, Box<LightBvhNode>, LightBounds),
+}
+
+impl LightBvhNode {
+    fn from_lights(lights: &mut [(Arc<dyn Light>, LightBounds)]) -> Self {
+        if lights.len() == 1 {
+            return Self::Leaf(lights[0].0.clone(), lights[0].1);
+        }
+
+        let mut centroid_bbox = Aabb::EMPTY;
+        for (_, bounds) in lights.iter() {
+            let centroid = bounds.bbox.centroid();
+            centroid_bbox =
+                Aabb::from_aabbs(&centroid_bbox, &Aabb::from_endpoints(&centroid, &centroid));
+        }
+        let axis = centroid_bbox.longest_axis();
+
+        lights.sort_unstable_by(|a, b| {
+            a.1.bbox.centroid()[axis]
+                .partial_cmp(&b.1.bbox.centroid()[axis])
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let (left, right) = lights.split_at_mut(lights.len() / 2);
+        let left = Self::from_lights(left);
+        let right = Self::from_lights(right);
+        let bounds = LightBounds::from_bounds(left.bounds(), right.bounds());
+
+        Self::Interior(Box::new(left), Box::new(right), bounds)
+    }
+
+    fn bounds(&self) -> &LightBounds {
+        match self {
+            Self::Leaf(_, bounds) => bounds,
+            Self::Interior(_, _, bounds) => bounds,
+        }
+    }
+
+    fn pdf(&self, r: &Ray, normal: &Vec3) -> f64 {
+        match self {
+            Self::Leaf(light, _) => light.pdf_li(r.origin(), r.direction()),
+            Self::Interior(left, right, _) => {
+                let importance = [
+                    left.bounds().importance(r.origin(), normal),
+                    right.bounds().importance(r.origin(), normal),
+                ];
+                let total = importance[0] + importance[1];
+                if total == 0.0 {
+                    return 0.0;
+                }
+
+                [left, right]
+                    .iter()
+                    .zip(importance)
+                    .filter(|(child, c)| {
+                        *c > 0.0
+                            && child
+                                .bounds()
+                                .bbox
+                                .hit(r, Interval::new(0.001, f64::INFINITY))
+                    })
+                    .map(|(child, c)| c / total * child.pdf(r, normal))
+                    .sum()
+            }
+        }
+    }
+}
+
+/// Picks lights by their estimated contribution to the shading point, walking a
+/// hierarchy of light bounds. Lights without bounds are picked uniformly.
+pub struct LightBvh {
+    root: Option<LightBvhNode>,
+    infinite_lights: Vec<Arc<dyn Light>>,
+}
+
+impl LightBvh {
+    pub fn new(lights: &LightList) -> Self {
+        let mut bounded_lights = Vec::new();
+        let mut infinite_lights = Vec::new();
+        for light in &lights.lights {
+            match light.bounds() {
+                Some(bounds) => {
+                    if bounds.phi > 0.0 {
+                        bounded_lights.push((light.clone(), bounds));
+                    }
+                }
+                None => infinite_lights.push(light.clone()),
+            }
+        }
+
+        Self {
+            root: if bounded_lights.is_empty() {
+                None
+            } else {
+                Some(LightBvhNode::from_lights(&mut bounded_lights))
+            },
+            infinite_lights,
+        }
+    }
+
+    fn infinite_probability(&self) -> f64 {
+        let n = self.infinite_lights.len() as f64;
+        if self.root.is_some() {
+            n / (n + 1.0)
+        } else if n > 0.0 {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+impl LightSampler for LightBvh {
+    fn sample(&self, origin: &Point3, normal: &Vec3, sample: &mut LightSample) -> bool {
+        let p_infinite = self.infinite_probability();
+        let u = rtweekend::random_double();
+
+        if u < p_infinite {
+            let n = self.infinite_lights.len();
+            let index = ((u / p_infinite * n as f64) as usize).min(n - 1);
+            if !self.infinite_lights[index].sample_li(origin, sample) {
+                return false;
+            }
+            sample.pdf *= p_infinite / n as f64;
+            return true;
+        }
+
+        let mut node = match &self.root {
+            Some(root) => root,
+            None => return false,
+        };
+        let mut pmf = 1.0 - p_infinite;
+
+        loop {
+            match node {
+                LightBvhNode::Interior(left, right, _) => {
+                    let c0 = left.bounds().importance(origin, normal);
+                    let c1 = right.bounds().importance(origin, normal);
+                    if c0 == 0.0 && c1 == 0.0 {
+                        return false;
+                    }
+
+                    let p_left = c0 / (c0 + c1);
+                    if rtweekend::random_double() < p_left {
+                        node = left;
+                        pmf *= p_left;
+                    } else {
+                        node = right;
+                        pmf *= 1.0 - p_left;
+                    }
+                }
+                LightBvhNode::Leaf(light, bounds) => {
+                    if bounds.importance(origin, normal) == 0.0 || !light.sample_li(origin, sample)
+                    {
+                        return false;
+                    }
+                    sample.pdf *= pmf;
+                    return true;
+                }
+            }
+        }
+    }
+
+    fn pdf(&self, origin: &Point3, normal: &Vec3, direction: &Vec3) -> f64 {
+        let p_infinite = self.infinite_probability();
+
+        let infinite_pdf: f64 = self
+            .infinite_lights
+            .iter()
+            .map(|light| light.pdf_li(origin, direction))
+            .sum::<f64>()
+            * p_infinite
+            / self.infinite_lights.len().max(1) as f64;
+
+        let bvh_pdf = match &self.root {
+            Some(root) => (1.0 - p_infinite) * root.pdf(&Ray::new(origin, direction), normal),
+            None => 0.0,
+        };
+
+        infinite_pdf + bvh_pdf
+    }
+}
+
+fn safe_sqrt(x: f64) -> f64 {
+    x.max(0.0).sqrt()
+}
+
+/// cos(max(0, a - b)) from the sines and cosines of `a` and `b`
+fn cos_sub_clamped(sin_a: f64, cos_a: f64, sin_b: f64, cos_b: f64) -> f64 {
+    if cos_a > cos_b {
+        1.0
+    } else {
+        cos_a * cos_b + sin_a * sin_b
+    }
+}
+
+/// sin(max(0, a - b)) from the sines and cosines of `a` and `b`
+fn sin_sub_clamped(sin_a: f64, cos_a: f64, sin_b: f64, cos_b: f64) -> f64 {
+    if cos_a > cos_b {
+        0.0
+    } else {
+        sin_a * cos_b - cos_a * sin_b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::Color,
+        hittable::{Hittable, Quad},
+        light::{AreaLight, DirectionalLight},
+        material::{DiffuseLight, Material},
+    };
+
+    /// Overhead panels that never overlap as seen from the origin, each its own color.
+    fn lights() -> (LightList, Vec<Color>) {
+        let colors = vec![
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(0.0, 2.0, 0.0),
+            Color::new(0.0, 0.0, 3.0),
+            Color::new(4.0, 4.0, 0.0),
+            Color::new(0.5, 0.5, 0.5),
+        ];
+        let corners = [
+            Point3::new(-4.5, 2.0, -0.5),
+            Point3::new(-0.5, 2.0, -0.5),
+            Point3::new(3.5, 3.0, -0.5),
+            Point3::new(-0.5, 6.0, 4.0),
+        ];
+
+        let mut lights = LightList::default();
+        for (corner, color) in corners.iter().zip(&colors) {
+            let emission = Arc::new(DiffuseLight::from_color(color));
+            let mat = emission.clone() as Arc<dyn Material>;
+            let shape: Arc<dyn Hittable> = Arc::new(Quad::new(
+                corner,
+                &Vec3::new(1.0, 0.0, 0.0),
+                &Vec3::new(0.0, 0.0, 1.0),
+                &mat,
+            ));
+            lights.add(&(Arc::new(AreaLight::new(&shape, &emission)) as Arc<dyn Light>));
+        }
+        let sun: Arc<dyn Light> = Arc::new(DirectionalLight::new(
+            &Vec3::new(1.0, -1.0, 0.0),
+            &colors[4],
+        ));
+        lights.add(&sun);
+
+        (lights, colors)
+    }
+
+    /// Probability of the traversal reaching each leaf, left to right.
+    fn leaf_pmfs(node: &LightBvhNode, p: &Point3, n: &Vec3, pmf: f64, pmfs: &mut Vec<f64>) {
+        match node {
+            LightBvhNode::Leaf(..) => pmfs.push(pmf),
+            LightBvhNode::Interior(left, right, _) => {
+                let c0 = left.bounds().importance(p, n);
+                let c1 = right.bounds().importance(p, n);
+                leaf_pmfs(left, p, n, pmf * c0 / (c0 + c1), pmfs);
+                leaf_pmfs(right, p, n, pmf * c1 / (c0 + c1), pmfs);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pmf_sums_to_one() {
+        let (lights, _) = lights();
+        let bvh = LightBvh::new(&lights);
+        let p_infinite = bvh.infinite_probability();
+        assert!((p_infinite - 0.5).abs() < 1e-12);
+
+        for p in [Point3::zeros(), Point3::new(2.0, 1.0, 1.0)] {
+            let mut pmfs = Vec::new();
+            leaf_pmfs(
+                bvh.root.as_ref().unwrap(),
+                &p,
+                &Vec3::new(0.0, 1.0, 0.0),
+                1.0,
+                &mut pmfs,
+            );
+            assert_eq!(pmfs.len(), 4);
+            let total = p_infinite + (1.0 - p_infinite) * pmfs.iter().sum::<f64>();
+            assert!((total - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_sample_matches_pdf() {
+        const N: usize = 100_000;
+        let (lights, colors) = lights();
+        let bvh = LightBvh::new(&lights);
+        let origin = Point3::zeros();
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+
+        let mut counts = vec![0; colors.len()];
+        for _ in 0..N {
+            let mut sample = LightSample::default();
+            if !bvh.sample(&origin, &normal, &mut sample) {
+                continue;
+            }
+            counts[colors.iter().position(|c| *c == sample.li).unwrap()] += 1;
+            if !sample.is_delta {
+                let pdf = bvh.pdf(&origin, &normal, &sample.wi);
+                assert!((sample.pdf - pdf).abs() <= 1e-6 * pdf);
+            }
+        }
+
+        // how often each light is picked follows the pmf of its leaf
+        let p_infinite = bvh.infinite_probability();
+        let mut pmfs = Vec::new();
+        leaf_pmfs(bvh.root.as_ref().unwrap(), &origin, &normal, 1.0, &mut pmfs);
+        let mut expected: Vec<f64> = pmfs.iter().map(|pmf| pmf * (1.0 - p_infinite)).collect();
+        expected.push(p_infinite);
+        let mut leaves = Vec::new();
+        collect_leaves(bvh.root.as_ref().unwrap(), &mut leaves);
+        for (leaf, pmf) in leaves.iter().zip(&expected) {
+            let mut sample = LightSample::default();
+            // identify the leaf by the color it emits towards the origin
+            while !leaf.sample_li(&origin, &mut sample) {}
+            let index = colors.iter().position(|c| *c == sample.li).unwrap();
+            let frequency = counts[index] as f64 / N as f64;
+            assert!((frequency - pmf).abs() < 0.01, "{frequency} vs {pmf}");
+        }
+        let frequency = counts[4] as f64 / N as f64;
+        assert!((frequency - p_infinite).abs() < 0.01);
+    }
+
+    fn collect_leaves(node: &LightBvhNode, leaves: &mut Vec<Arc<dyn Light>>) {
+        match node {
+            LightBvhNode::Leaf(light, _) => leaves.push(light.clone()),
+            LightBvhNode::Interior(left, right, _) => {
+                collect_leaves(left, leaves);
+                collect_leaves(right, leaves);
+            }
+        }
+    }
+}
@@ -0,0 +1,13 @@
+use super::Light;
+use std::sync::Arc;
+
+#[derive(Clone, Default)]
+pub struct LightList {
+    pub lights: Vec<Arc<dyn Light>>,
+}
+
+impl LightList {
+    pub fn add(&mut self, light: &Arc<dyn Light>) {
+        self.lights.push(light.clone());
+    }
+}
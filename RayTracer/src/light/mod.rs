@@ -0,0 +1,84 @@
+mod area_light;
+mod directional_light;
+mod light_bvh;
+mod light_list;
+mod point_light;
+mod power_light_sampler;
+mod spot_light;
+
+pub use area_light::AreaLight;
+pub use directional_light::DirectionalLight;
+pub use light_bvh::{LightBounds, LightBvh};
+pub use light_list::LightList;
+pub use point_light::PointLight;
+pub use power_light_sampler::PowerLightSampler;
+pub use spot_light::SpotLight;
+
+use crate::{
+    aabb::Aabb,
+    color::Color,
+    vec3::{Point3, Vec3},
+};
+
+#[allow(unused_variables)]
+pub trait Light: Send + Sync {
+    fn sample_li(&self, origin: &Point3, sample: &mut LightSample) -> bool;
+    fn power(&self, world_bbox: &Aabb) -> f64;
+
+    /// Solid angle density of `sample_li` picking `direction`, zero for delta lights.
+    fn pdf_li(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    /// Bounds of the emission, `None` for lights infinitely far away.
+    fn bounds(&self) -> Option<LightBounds> {
+        None
+    }
+
+    /// Samples a ray leaving the light, the first segment of a light subpath.
+    fn sample_le(&self, sample: &mut EmissionSample) -> bool {
+        false
+    }
+
+    /// Area and solid angle densities of `sample_le` choosing a ray leaving `p` along `w`.
+    fn pdf_le(&self, p: &Point3, w: &Vec3, pdf_pos: &mut f64, pdf_dir: &mut f64) {
+        *pdf_pos = 0.0;
+        *pdf_dir = 0.0;
+    }
+}
+
+/// Picks one light per shading point and samples it.
+pub trait LightSampler: Send + Sync {
+    fn sample(&self, origin: &Point3, normal: &Vec3, sample: &mut LightSample) -> bool;
+
+    /// Density of `sample` choosing `direction`, summed over every light it could come from.
+    fn pdf(&self, origin: &Point3, normal: &Vec3, direction: &Vec3) -> f64;
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct LightSample {
+    /// unit direction from the shading point towards the light
+    pub wi: Vec3,
+    /// distance to the light along `wi`, infinite for distant lights
+    pub distance: f64,
+    /// incident radiance arriving at the shading point
+    pub li: Color,
+    /// surface normal at the sampled point, zero for point-like lights
+    pub normal: Vec3,
+    /// solid angle density of `wi`, or the selection probability for delta lights
+    pub pdf: f64,
+    pub is_delta: bool,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct EmissionSample {
+    pub origin: Point3,
+    /// unit direction the light leaves in
+    pub direction: Vec3,
+    /// surface normal at `origin`, zero for point-like lights
+    pub normal: Vec3,
+    pub le: Color,
+    pub pdf_pos: f64,
+    pub pdf_dir: f64,
+    pub is_delta: bool,
+}
@@ -0,0 +1,70 @@
+use super::{EmissionSample, Light, LightBounds, LightSample};
+use crate::{
+    aabb::Aabb,
+    color::Color,
+    direction_cone::DirectionCone,
+    vec3::{Point3, Vec3},
+};
+use std::f64::consts::PI;
+
+pub struct PointLight {
+    position: Point3,
+    intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: &Point3, intensity: &Color) -> Self {
+        Self {
+            position: *position,
+            intensity: *intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample_li(&self, origin: &Point3, sample: &mut LightSample) -> bool {
+        let to_light = self.position - *origin;
+        let distance_squared = to_light.squared_length();
+        if distance_squared == 0.0 {
+            return false;
+        }
+
+        sample.distance = distance_squared.sqrt();
+        sample.wi = to_light / sample.distance;
+        sample.li = self.intensity / distance_squared;
+        sample.normal = Vec3::zeros();
+        sample.pdf = 1.0;
+        sample.is_delta = true;
+        true
+    }
+
+    fn power(&self, _world_bbox: &Aabb) -> f64 {
+        4.0 * PI * self.intensity.luminance()
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        Some(LightBounds {
+            bbox: Aabb::from_endpoints(&self.position, &self.position),
+            phi: 4.0 * PI * self.intensity.luminance(),
+            cone: DirectionCone::ENTIRE_SPHERE,
+            cos_theta_e: 0.0,
+            two_sided: false,
+        })
+    }
+
+    fn sample_le(&self, sample: &mut EmissionSample) -> bool {
+        sample.origin = self.position;
+        sample.direction = Vec3::random_unit_vector();
+        sample.normal = Vec3::zeros();
+        sample.le = self.intensity;
+        sample.pdf_pos = 1.0;
+        sample.pdf_dir = 1.0 / (4.0 * PI);
+        sample.is_delta = true;
+        true
+    }
+
+    fn pdf_le(&self, _p: &Point3, _w: &Vec3, pdf_pos: &mut f64, pdf_dir: &mut f64) {
+        *pdf_pos = 0.0;
+        *pdf_dir = 1.0 / (4.0 * PI);
+    }
+}
@@ -0,0 +1,88 @@
+use super::{Light, LightList, LightSample, LightSampler};
+use crate::{
+    aabb::Aabb,
+    rtweekend,
+    vec3::{Point3, Vec3},
+};
+use std::sync::Arc;
+
+/// Chooses lights with probability proportional to their emitted power.
+pub struct PowerLightSampler {
+    lights: Vec<Arc<dyn Light>>,
+    pmf: Vec<f64>,
+    cdf: Vec<f64>,
+}
+
+impl PowerLightSampler {
+    pub fn new(lights: &LightList, world_bbox: &Aabb) -> Self {
+        let mut pmf: Vec<_> = lights
+            .lights
+            .iter()
+            .map(|light| light.power(world_bbox).max(0.0))
+            .collect();
+        let total: f64 = pmf.iter().sum();
+        for p in &mut pmf {
+            *p = if total > 0.0 {
+                *p / total
+            } else {
+                1.0 / lights.lights.len() as f64
+            };
+        }
+
+        let cdf = pmf
+            .iter()
+            .scan(0.0, |accum, p| {
+                *accum += p;
+                Some(*accum)
+            })
+            .collect();
+
+        Self {
+            lights: lights.lights.clone(),
+            pmf,
+            cdf,
+        }
+    }
+
+    pub fn lights(&self) -> &[Arc<dyn Light>] {
+        &self.lights
+    }
+
+    pub fn pmf(&self, index: usize) -> f64 {
+        self.pmf[index]
+    }
+
+    pub fn sample_index(&self, index: &mut usize) -> bool {
+        if self.lights.is_empty() {
+            return false;
+        }
+
+        let u = rtweekend::random_double();
+        *index = self
+            .cdf
+            .partition_point(|&c| c <= u)
+            .min(self.lights.len() - 1);
+        self.pmf[*index] > 0.0
+    }
+}
+
+impl LightSampler for PowerLightSampler {
+    fn sample(&self, origin: &Point3, _normal: &Vec3, sample: &mut LightSample) -> bool {
+        let mut index = 0;
+        if !self.sample_index(&mut index) || !self.lights[index].sample_li(origin, sample) {
+            return false;
+        }
+
+        sample.pdf *= self.pmf[index];
+        true
+    }
+
+    fn pdf(&self, origin: &Point3, _normal: &Vec3, direction: &Vec3) -> f64 {
+        self.lights
+            .iter()
+            .zip(&self.pmf)
+            .filter(|(_, &pmf)| pmf > 0.0)
+            .map(|(light, pmf)| pmf * light.pdf_li(origin, direction))
+            .sum()
+    }
+}
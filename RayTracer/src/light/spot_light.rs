@@ -0,0 +1,128 @@
+use super::{EmissionSample, Light, LightBounds, LightSample};
+use crate::{
+    aabb::Aabb,
+    color::Color,
+    direction_cone::DirectionCone,
+    onb::Onb,
+    rtweekend,
+    vec3::{Point3, Vec3},
+};
+use std::f64::consts::PI;
+
+pub struct SpotLight {
+    position: Point3,
+    direction: Vec3,
+    intensity: Color,
+    cos_total_width: f64,
+    cos_falloff_start: f64,
+}
+
+impl SpotLight {
+    /// `total_width` and `falloff_start` are half-angles of the cone in degrees.
+    pub fn new(
+        position: &Point3,
+        target: &Point3,
+        intensity: &Color,
+        total_width: f64,
+        falloff_start: f64,
+    ) -> Self {
+        Self {
+            position: *position,
+            direction: (*target - *position).unit(),
+            intensity: *intensity,
+            cos_total_width: total_width.to_radians().cos(),
+            cos_falloff_start: falloff_start.min(total_width).to_radians().cos(),
+        }
+    }
+
+    fn cone_pdf(&self) -> f64 {
+        1.0 / (2.0 * PI * (1.0 - self.cos_total_width))
+    }
+
+    fn falloff(&self, w: &Vec3) -> f64 {
+        let cos_theta = *w * self.direction;
+        if cos_theta < self.cos_total_width {
+            0.0
+        } else if cos_theta >= self.cos_falloff_start {
+            1.0
+        } else {
+            let delta = (cos_theta - self.cos_total_width)
+                / (self.cos_falloff_start - self.cos_total_width);
+            delta * delta * (3.0 - 2.0 * delta)
+        }
+    }
+}
+
+impl Light for SpotLight {
+    fn sample_li(&self, origin: &Point3, sample: &mut LightSample) -> bool {
+        let to_light = self.position - *origin;
+        let distance_squared = to_light.squared_length();
+        if distance_squared == 0.0 {
+            return false;
+        }
+
+        sample.distance = distance_squared.sqrt();
+        sample.wi = to_light / sample.distance;
+
+        let falloff = self.falloff(&-sample.wi);
+        if falloff == 0.0 {
+            return false;
+        }
+
+        sample.li = self.intensity * falloff / distance_squared;
+        sample.normal = Vec3::zeros();
+        sample.pdf = 1.0;
+        sample.is_delta = true;
+        true
+    }
+
+    fn power(&self, _world_bbox: &Aabb) -> f64 {
+        2.0 * PI
+            * (1.0 - 0.5 * (self.cos_falloff_start + self.cos_total_width))
+            * self.intensity.luminance()
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        let theta_o = self.cos_falloff_start.acos();
+        let theta_e = self.cos_total_width.acos() - theta_o;
+        Some(LightBounds {
+            bbox: Aabb::from_endpoints(&self.position, &self.position),
+            phi: 4.0 * PI * self.intensity.luminance(),
+            cone: DirectionCone::new(&self.direction, self.cos_falloff_start),
+            cos_theta_e: theta_e.cos(),
+            two_sided: false,
+        })
+    }
+
+    fn sample_le(&self, sample: &mut EmissionSample) -> bool {
+        // uniform over the cone of total width
+        let cos_theta = 1.0 - rtweekend::random_double() * (1.0 - self.cos_total_width);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * rtweekend::random_double();
+        let mut uvw = Onb::new();
+        uvw.build_from_w(&self.direction);
+        let direction = uvw.local_with_vec3(&Vec3::new(
+            phi.cos() * sin_theta,
+            phi.sin() * sin_theta,
+            cos_theta,
+        ));
+
+        sample.origin = self.position;
+        sample.direction = direction;
+        sample.normal = Vec3::zeros();
+        sample.le = self.intensity * self.falloff(&direction);
+        sample.pdf_pos = 1.0;
+        sample.pdf_dir = self.cone_pdf();
+        sample.is_delta = true;
+        true
+    }
+
+    fn pdf_le(&self, _p: &Point3, w: &Vec3, pdf_pos: &mut f64, pdf_dir: &mut f64) {
+        *pdf_pos = 0.0;
+        *pdf_dir = if *w * self.direction >= self.cos_total_width {
+            self.cone_pdf()
+        } else {
+            0.0
+        };
+    }
+}
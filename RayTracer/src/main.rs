@@ -1,28 +1,126 @@
 mod aabb;
+mod aov;
+mod aperture;
 mod camera;
+mod camera_path;
 mod color;
+mod color_space;
+mod denoise;
+mod direction_cone;
+mod film;
+mod filter;
 mod hittable;
+mod integrator;
 mod interval;
+mod lens_system;
+mod light;
 mod material;
+mod mipmap;
 mod onb;
 mod pdf;
+mod physical_camera;
+mod projection;
 mod ray;
 mod rtw_image;
 mod rtweekend;
+mod shader_graph;
+mod spectrum;
+mod stereo;
 mod texture;
+mod texture_cache;
 mod vec3;
 
 use crate::{
-    camera::Camera,
+    aperture::Aperture,
+    camera::{Camera, CameraParams},
     color::Color,
-    hittable::{Hittable, HittableList, Quad, RotateY, Translate},
-    material::{DiffuseLight, Lambertian, Material},
+    color_space::{ColorEncoding, WorkingSpace},
+    hittable::{
+        BvhNode, ConstantMedium, Hittable, HittableList, Quad, RotateY, Sphere, Subsurface,
+        Translate, Triangle,
+    },
+    lens_system::LensSystem,
+    light::{AreaLight, DirectionalLight, Light, LightList, PointLight, SpotLight},
+    material::{
+        AlphaMasked, BaseMaterial, Conductor, Dielectric, DiffuseLight, Dispersion, Lambertian,
+        Material, Metal, NormalMapped, ShaderMaterial,
+    },
+    mipmap::{TextureFilter, WrapMode},
+    physical_camera::PhysicalCamera,
+    projection::Projection,
+    texture::{
+        BrickTexture, CheckerTexture, ColorRamp, FlipbookTexture, Gradient, GradientTexture,
+        ImageTexture, MappedTexture, MixTexture, MultiplyTexture, NoiseTexture, RemapTexture,
+        ScrollingTexture, SolidColor, Texture, TextureMapping, UvTransform, VoxelTexture,
+        WoodTexture, WorleyFeature, WorleyTexture,
+    },
     vec3::{Point3, Vec3},
 };
-use material::BaseMaterial;
-use std::sync::Arc;
+use clap::ArgMatches;
+use std::{f64::consts::PI, process, sync::Arc};
+
+/// Builds a scene and renders it as the command line asks.
+type Scene = fn(&ArgMatches);
+
+/// Every scene `--scene` can pick, by name.
+const SCENES: &[(&str, Scene)] = &[
+    ("cornell-box", cornell_box),
+    ("marble", marble),
+    ("bump-map", bump_map),
+    ("lights", lights),
+    ("physical-camera", physical_camera),
+    ("realistic-camera", realistic_camera),
+    ("textures", textures),
+    ("shaders", shaders),
+    ("alpha-mask", alpha_mask),
+    ("panorama", panorama),
+    ("dispersion", dispersion),
+    ("texture-filtering", texture_filtering),
+    ("texture-mappings", texture_mappings),
+    ("animated-textures", animated_textures),
+    ("final-scene", final_scene),
+];
 
 fn main() {
+    let matches = camera::command().get_matches();
+    // textures are loaded while the scene is built
+    for path in matches
+        .get_many::<String>("texture-path")
+        .into_iter()
+        .flatten()
+    {
+        texture_cache::add_search_path(path);
+    }
+    if let Some(megabytes) = matches.get_one::<usize>("texture-budget") {
+        texture_cache::set_memory_budget(Some(megabytes << 20));
+    }
+    if let Some(filter) = matches.get_one::<String>("texture-filter") {
+        texture_cache::set_filter_override(Some(match filter.as_str() {
+            "nearest" => TextureFilter::Nearest,
+            "bilinear" => TextureFilter::Bilinear,
+            "ewa" => TextureFilter::Ewa,
+            _ => TextureFilter::Trilinear,
+        }));
+    }
+    // scene colors are given in the working space
+    color_space::set_working_space(
+        match matches.get_one::<String>("working-space").unwrap().as_str() {
+            "acescg" => WorkingSpace::AcesCg,
+            _ => WorkingSpace::Rec709,
+        },
+    );
+    let name = matches.get_one::<String>("scene").unwrap();
+    match SCENES.iter().find(|(scene, _)| scene == name) {
+        Some((_, render)) => render(&matches),
+        None => {
+            let names: Vec<&str> = SCENES.iter().map(|(scene, _)| *scene).collect();
+            eprintln!("Unknown scene '{}', try {}", name, names.join(", "));
+            process::exit(1);
+        }
+    }
+}
+
+fn cornell_box(matches: &ArgMatches) {
     let mut world = HittableList::default();
 
     let red = Arc::new(Lambertian::from_color(&Color::new(0.65, 0.05, 0.05))) as Arc<dyn Material>;
@@ -30,8 +128,7 @@ fn main() {
         Arc::new(Lambertian::from_color(&Color::new(0.73, 0.73, 0.73))) as Arc<dyn Material>;
     let green =
         Arc::new(Lambertian::from_color(&Color::new(0.12, 0.45, 0.15))) as Arc<dyn Material>;
-    let light =
-        Arc::new(DiffuseLight::from_color(&Color::new(15.0, 15.0, 15.0))) as Arc<dyn Material>;
+    let light = Arc::new(DiffuseLight::from_color(&Color::new(15.0, 15.0, 15.0)));
 
     world.add(
         &(Arc::new(Quad::new(
@@ -49,14 +146,13 @@ fn main() {
             &red,
         )) as Arc<dyn Hittable>),
     );
-    world.add(
-        &(Arc::new(Quad::new(
-            &Point3::new(343.0, 554.0, 332.0),
-            &Vec3::new(-130.0, 0.0, 0.0),
-            &Vec3::new(0.0, 0.0, -105.0),
-            &light,
-        )) as Arc<dyn Hittable>),
-    );
+    let light_quad = Arc::new(Quad::new(
+        &Point3::new(343.0, 554.0, 332.0),
+        &Vec3::new(-130.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, -105.0),
+        &(light.clone() as Arc<dyn Material>),
+    )) as Arc<dyn Hittable>;
+    world.add(&light_quad);
     world.add(
         &(Arc::new(Quad::new(
             &Point3::new(0.0, 0.0, 0.0),
@@ -93,29 +189,1136 @@ fn main() {
     box2 = Arc::new(RotateY::new(&box2, -18.0));
     box2 = Arc::new(Translate::new(&box2, &Vec3::new(130.0, 0.0, 65.0)));
     world.add(&box2);
+    let world = HittableList::new(
+        &(Arc::new(BvhNode::from_hittable_list(&mut world)) as Arc<dyn Hittable>),
+    );
+
+    let mut lights = LightList::default();
+    lights.add(&(Arc::new(AreaLight::new(&light_quad, &light)) as Arc<dyn Light>));
+
+    let cam = Camera::new(
+        &CameraParams {
+            aspect_ratio: 1.0,
+            image_width: 600,
+            samples_per_pixel: 300,
+            max_depth: 50,
+            background: Color::zeros(),
+            lookfrom: Point3::new(278.0, 278.0, -800.0),
+            lookat: Point3::new(278.0, 278.0, 0.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+        },
+        40.0,
+        0.0,
+        10.0,
+    );
+    cam.render(&world, &lights, matches);
+}
+
+/// Marble and wax spheres, translucent by subsurface scattering, under a
+/// square light.
+fn marble(matches: &ArgMatches) {
+    let mut world = HittableList::default();
+
+    let pertext: Arc<dyn Texture> = Arc::new(NoiseTexture::new(4.0));
+    world.add(
+        &(Arc::new(Sphere::new(
+            &Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            &(Arc::new(Lambertian::from_color(&Color::new(0.5, 0.5, 0.5))) as Arc<dyn Material>),
+        )) as Arc<dyn Hittable>),
+    );
+
+    // the material of a subsurface boundary is never seen
+    let boundary_mat = Arc::new(Lambertian::from_color(&Color::zeros())) as Arc<dyn Material>;
+    let marble_ball =
+        Arc::new(Sphere::new(&Point3::new(0.0, 2.0, 0.0), 2.0, &boundary_mat)) as Arc<dyn Hittable>;
+    world.add(&(Arc::new(Subsurface::new(&marble_ball, 0.15, &pertext, 1.5)) as Arc<dyn Hittable>));
+    let wax_ball =
+        Arc::new(Sphere::new(&Point3::new(0.0, 1.0, 3.5), 1.0, &boundary_mat)) as Arc<dyn Hittable>;
+    world.add(
+        &(Arc::new(Subsurface::new_with_color(
+            &wax_ball,
+            0.3,
+            &Color::new(0.9, 0.6, 0.3),
+            1.4,
+        )) as Arc<dyn Hittable>),
+    );
+
+    let light = Arc::new(DiffuseLight::from_color(&Color::new(4.0, 4.0, 4.0)));
+    let light_quad = Arc::new(Quad::new(
+        &Point3::new(3.0, 1.0, -2.0),
+        &Vec3::new(2.0, 0.0, 0.0),
+        &Vec3::new(0.0, 2.0, 0.0),
+        &(light.clone() as Arc<dyn Material>),
+    )) as Arc<dyn Hittable>;
+    world.add(&light_quad);
+
+    let mut lights = LightList::default();
+    lights.add(&(Arc::new(AreaLight::new(&light_quad, &light)) as Arc<dyn Light>));
+
+    let cam = Camera::new(
+        &CameraParams {
+            aspect_ratio: 16.0 / 9.0,
+            image_width: 400,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            background: Color::new(0.1, 0.1, 0.12),
+            lookfrom: Point3::new(26.0, 3.0, 6.0),
+            lookat: Point3::new(0.0, 2.0, 0.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+        },
+        20.0,
+        0.0,
+        10.0,
+    );
+    cam.render(&world, &lights, matches);
+}
+
+/// A hammered metal sphere and a rippled plaster one, their detail only in
+/// the shading normals.
+fn bump_map(matches: &ArgMatches) {
+    let mut world = HittableList::default();
+
+    world.add(
+        &(Arc::new(Sphere::new(
+            &Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            &(Arc::new(Lambertian::from_color(&Color::new(0.5, 0.5, 0.5))) as Arc<dyn Material>),
+        )) as Arc<dyn Hittable>),
+    );
 
-    // let mut lights = HittableList::default();
-    // let m = Arc::new(BaseMaterial::new()) as Arc<dyn Material>;
-    // let lights = Arc::new(Quad::new(
-    //     &Point3::new(343.0, 554.0, 332.0),
-    //     &Vec3::new(-130.0, 0.0, 0.0),
-    //     &Vec3::new(0.0, 0.0, -105.0),
-    //     &m,
-    // )) as Arc<dyn Hittable>;
+    let dents: Arc<dyn Texture> = Arc::new(WorleyTexture::new(4.0, WorleyFeature::F1));
+    let metal = Arc::new(Metal::new(&Color::new(0.8, 0.6, 0.4), 0.05)) as Arc<dyn Material>;
+    let hammered = Arc::new(NormalMapped::new_bump_map(&metal, &dents, 0.03)) as Arc<dyn Material>;
+    world.add(
+        &(Arc::new(Sphere::new(&Point3::new(0.0, 2.0, 0.0), 2.0, &hammered)) as Arc<dyn Hittable>),
+    );
+
+    let ripples: Arc<dyn Texture> = Arc::new(NoiseTexture::new(4.0));
+    let plaster =
+        Arc::new(Lambertian::from_color(&Color::new(0.8, 0.8, 0.75))) as Arc<dyn Material>;
+    let rippled =
+        Arc::new(NormalMapped::new_bump_map(&plaster, &ripples, 0.02)) as Arc<dyn Material>;
+    world.add(
+        &(Arc::new(Sphere::new(&Point3::new(0.0, 1.0, 3.5), 1.0, &rippled)) as Arc<dyn Hittable>),
+    );
+
+    let rivet: Arc<dyn Texture> = Arc::new(ImageTexture::new_with_sampling(
+        "normals/rivets.png",
+        ColorEncoding::Raw,
+        TextureFilter::Trilinear,
+        WrapMode::Repeat,
+    ));
+    let rivets: Arc<dyn Texture> = Arc::new(MappedTexture::from_transform(
+        &rivet,
+        &UvTransform::new((16.0, 8.0), 0.0, (0.0, 0.0)),
+    ));
+    let steel = Arc::new(Metal::new(&Color::new(0.6, 0.6, 0.65), 0.3)) as Arc<dyn Material>;
+    let riveted = Arc::new(NormalMapped::new_normal_map(&steel, &rivets, 1.0)) as Arc<dyn Material>;
+    world.add(
+        &(Arc::new(Sphere::new(&Point3::new(0.0, 1.0, -3.5), 1.0, &riveted)) as Arc<dyn Hittable>),
+    );
+
+    // a stone wall whose displaced cells cast their own shadows
+    let cells: Arc<dyn Texture> = Arc::new(WorleyTexture::new(3.0, WorleyFeature::F1));
+    let stone = Arc::new(Lambertian::from_color(&Color::new(0.6, 0.55, 0.5))) as Arc<dyn Material>;
+    world.add(
+        &(hittable::get_displaced_quad(
+            &Point3::new(-4.0, 0.0, -7.0),
+            &Vec3::new(0.0, 6.0, 0.0),
+            &Vec3::new(0.0, 0.0, 14.0),
+            96,
+            &cells,
+            0.4,
+            &stone,
+        ) as Arc<dyn Hittable>),
+    );
+
+    let light = Arc::new(DiffuseLight::from_color(&Color::new(4.0, 4.0, 4.0)));
+    let light_quad = Arc::new(Quad::new(
+        &Point3::new(3.0, 1.0, -2.0),
+        &Vec3::new(2.0, 0.0, 0.0),
+        &Vec3::new(0.0, 2.0, 0.0),
+        &(light.clone() as Arc<dyn Material>),
+    )) as Arc<dyn Hittable>;
+    world.add(&light_quad);
+
+    let mut lights = LightList::default();
+    lights.add(&(Arc::new(AreaLight::new(&light_quad, &light)) as Arc<dyn Light>));
 
     let cam = Camera::new(
+        &CameraParams {
+            aspect_ratio: 16.0 / 9.0,
+            image_width: 400,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            background: Color::new(0.3, 0.35, 0.4),
+            lookfrom: Point3::new(26.0, 3.0, 6.0),
+            lookat: Point3::new(0.0, 2.0, 0.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+        },
+        20.0,
+        0.0,
+        10.0,
+    );
+    cam.render(&world, &lights, matches);
+}
+
+/// Spheres lit by a warm point light, a cool spotlight, a dim sun and a two
+/// sided panel to their side given its power in watts.
+fn lights(matches: &ArgMatches) {
+    let mut world = HittableList::default();
+
+    world.add(
+        &(Arc::new(Sphere::new(
+            &Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            &(Arc::new(Lambertian::from_color(&Color::new(0.5, 0.5, 0.5))) as Arc<dyn Material>),
+        )) as Arc<dyn Hittable>),
+    );
+    for (x, albedo) in [
+        (-2.5, Color::new(0.8, 0.3, 0.3)),
+        (0.0, Color::new(0.8, 0.8, 0.8)),
+        (2.5, Color::new(0.3, 0.3, 0.8)),
+    ] {
+        let mat = Arc::new(Lambertian::from_color(&albedo)) as Arc<dyn Material>;
+        world.add(
+            &(Arc::new(Sphere::new(&Point3::new(x, 1.0, 0.0), 1.0, &mat)) as Arc<dyn Hittable>),
+        );
+    }
+
+    let glow: Arc<dyn Texture> = Arc::new(SolidColor::new(&Color::new(1.0, 0.7, 0.9)));
+    let panel_light = Arc::new(DiffuseLight::from_power(&glow, 8.0, 3.0, true));
+    let panel = Arc::new(Quad::new(
+        &Point3::new(-5.5, 0.5, -1.5),
+        &Vec3::new(0.0, 0.0, 3.0),
+        &Vec3::new(0.0, 1.0, 0.0),
+        &(panel_light.clone() as Arc<dyn Material>),
+    )) as Arc<dyn Hittable>;
+    world.add(&panel);
+
+    let mut lights = LightList::default();
+    lights.add(&(Arc::new(AreaLight::new(&panel, &panel_light)) as Arc<dyn Light>));
+    lights.add(
+        &(Arc::new(PointLight::new(
+            &Point3::new(-4.0, 4.0, 3.0),
+            &Color::new(40.0, 30.0, 20.0),
+        )) as Arc<dyn Light>),
+    );
+    lights.add(
+        &(Arc::new(SpotLight::new(
+            &Point3::new(3.0, 6.0, 2.0),
+            &Point3::new(2.5, 0.0, 0.0),
+            &Color::new(40.0, 50.0, 80.0),
+            25.0,
+            15.0,
+        )) as Arc<dyn Light>),
+    );
+    lights.add(
+        &(Arc::new(DirectionalLight::new(
+            &Vec3::new(1.0, -2.0, -1.0),
+            &Color::new(0.3, 0.3, 0.35),
+        )) as Arc<dyn Light>),
+    );
+
+    let cam = Camera::new(
+        &CameraParams {
+            aspect_ratio: 16.0 / 9.0,
+            image_width: 400,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            background: Color::zeros(),
+            lookfrom: Point3::new(0.0, 4.0, 12.0),
+            lookat: Point3::new(0.0, 1.0, 0.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+        },
+        30.0,
+        0.0,
+        10.0,
+    );
+    cam.render(&world, &lights, matches);
+}
+
+/// A row of spheres receding from the camera, lit by a window, for looking at
+/// depth of field.
+fn receding_spheres() -> (HittableList, LightList) {
+    let mut world = HittableList::default();
+
+    world.add(
+        &(Arc::new(Sphere::new(
+            &Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            &(Arc::new(Lambertian::from_color(&Color::new(0.5, 0.5, 0.5))) as Arc<dyn Material>),
+        )) as Arc<dyn Hittable>),
+    );
+    let metal = Arc::new(Metal::new(&Color::new(0.9, 0.9, 0.9), 0.0)) as Arc<dyn Material>;
+    for i in 0..6 {
+        let albedo = Color::new(0.8, 0.2 + 0.12 * i as f64, 0.2);
+        let mat = Arc::new(Lambertian::from_color(&albedo)) as Arc<dyn Material>;
+        let z = -3.0 * i as f64;
+        world.add(
+            &(Arc::new(Sphere::new(&Point3::new(-0.6, 0.5, z), 0.5, &mat)) as Arc<dyn Hittable>),
+        );
+        // small mirror balls whose highlights blur into bokeh
+        world.add(
+            &(Arc::new(Sphere::new(&Point3::new(0.6, 0.25, z + 1.0), 0.25, &metal))
+                as Arc<dyn Hittable>),
+        );
+    }
+
+    let light = Arc::new(DiffuseLight::from_color(&Color::new(2.0, 2.0, 2.0)));
+    let light_quad = Arc::new(Quad::new(
+        &Point3::new(-4.0, 3.0, -12.0),
+        &Vec3::new(8.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, 12.0),
+        &(light.clone() as Arc<dyn Material>),
+    )) as Arc<dyn Hittable>;
+    world.add(&light_quad);
+
+    let mut lights = LightList::default();
+    lights.add(&(Arc::new(AreaLight::new(&light_quad, &light)) as Arc<dyn Light>));
+    (world, lights)
+}
+
+fn receding_params() -> CameraParams {
+    CameraParams {
+        aspect_ratio: 3.0 / 2.0,
+        image_width: 400,
+        samples_per_pixel: 100,
+        max_depth: 50,
+        background: Color::new(0.05, 0.05, 0.08),
+        lookfrom: Point3::new(1.5, 1.0, 4.0),
+        lookat: Point3::new(0.0, 0.5, -6.0),
+        vup: Vec3::new(0.0, 1.0, 0.0),
+    }
+}
+
+/// The receding spheres through an 85mm lens at f/1.8 with a star shaped
+/// stop, exposed for the window light.
+fn physical_camera(matches: &ArgMatches) {
+    let (world, lights) = receding_spheres();
+    let physical = PhysicalCamera {
+        focal_length: 85.0,
+        f_number: 1.8,
+        focus_dist: 7.0,
+        shutter_time: 1.0 / 4.0,
+        iso: 400.0,
+        aperture: Aperture::from_image("apertures/star.png"),
+        ..PhysicalCamera::default()
+    };
+    let cam = Camera::new_physical(&receding_params(), &physical);
+    cam.render(&world, &lights, matches);
+}
+
+/// The receding spheres through a 50mm double Gauss lens wide open, with its
+/// aberrations and vignetting.
+fn realistic_camera(matches: &ArgMatches) {
+    let (world, lights) = receding_spheres();
+    let params = receding_params();
+    let lens = Arc::new(LensSystem::new(
+        &LensSystem::load("lenses/dgauss.50mm.dat"),
+        17.1,
+        36.0,
+        params.aspect_ratio,
+        7.0,
         1.0,
-        600,
-        300,
-        50,
+    ));
+    let cam = Camera::new_realistic(&params, &lens);
+    cam.render(&world, &lights, matches);
+}
+
+/// Procedural textures on a checkered floor: bricks, wood, a color ramp over
+/// a gradient, and marble tinted by multiplying and mixing.
+fn textures(matches: &ArgMatches) {
+    let mut world = HittableList::default();
+
+    let checker: Arc<dyn Texture> = Arc::new(CheckerTexture::from_colors(
+        1.0,
+        &Color::new(0.2, 0.3, 0.1),
+        &Color::new(0.9, 0.9, 0.9),
+    ));
+    world.add(
+        &(Arc::new(Sphere::new(
+            &Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            &(Arc::new(Lambertian::new(&checker)) as Arc<dyn Material>),
+        )) as Arc<dyn Hittable>),
+    );
+
+    let bricks: Arc<dyn Texture> = Arc::new(BrickTexture::from_colors(
+        0.125,
+        0.0625,
+        0.01,
+        &Color::new(0.6, 0.2, 0.1),
+        &Color::new(0.8, 0.8, 0.75),
+    ));
+    // repeated around the sphere, which is four times wider than tall in uv
+    let bricks: Arc<dyn Texture> = Arc::new(MappedTexture::from_transform(
+        &bricks,
+        &UvTransform::new((2.0, 1.0), 0.0, (0.0, 0.0)),
+    ));
+    let wood: Arc<dyn Texture> = Arc::new(WoodTexture::new(
+        8.0,
+        0.5,
+        &Color::new(0.8, 0.6, 0.35),
+        &Color::new(0.45, 0.25, 0.1),
+    ));
+    let gradient: Arc<dyn Texture> = Arc::new(GradientTexture::new(&Gradient::V));
+    let ramp: Arc<dyn Texture> = Arc::new(ColorRamp::new(
+        &gradient,
+        &[
+            (0.2, Color::new(0.1, 0.1, 0.6)),
+            (0.5, Color::new(0.9, 0.9, 0.2)),
+            (0.8, Color::new(0.8, 0.1, 0.1)),
+        ],
+    ));
+    let noise: Arc<dyn Texture> = Arc::new(NoiseTexture::new(4.0));
+    let tint: Arc<dyn Texture> = Arc::new(SolidColor::new(&Color::new(0.4, 0.8, 0.6)));
+    let tinted: Arc<dyn Texture> = Arc::new(MultiplyTexture::new(&noise, &tint));
+    let veined: Arc<dyn Texture> = Arc::new(MixTexture::new(&tinted, &wood, &gradient));
+
+    for (x, tex) in [
+        (-3.3, bricks),
+        (-1.1, wood.clone()),
+        (1.1, ramp),
+        (3.3, veined),
+    ] {
+        let mat = Arc::new(Lambertian::new(&tex)) as Arc<dyn Material>;
+        world.add(
+            &(Arc::new(Sphere::new(&Point3::new(x, 1.0, 0.0), 1.0, &mat)) as Arc<dyn Hittable>),
+        );
+    }
+
+    // a smaller row in front
+    let around: Arc<dyn Texture> = Arc::new(GradientTexture::new(&Gradient::U));
+    let hues: Arc<dyn Texture> = Arc::new(ColorRamp::new(
+        &around,
+        &[
+            (0.0, Color::new(0.8, 0.1, 0.1)),
+            (0.33, Color::new(0.1, 0.7, 0.1)),
+            (0.67, Color::new(0.1, 0.1, 0.8)),
+            (1.0, Color::new(0.8, 0.1, 0.1)),
+        ],
+    ));
+    let upward: Arc<dyn Texture> = Arc::new(GradientTexture::new(&Gradient::Linear {
+        origin: Point3::new(0.0, 0.2, 0.0),
+        direction: Vec3::new(0.0, 0.8, 0.0),
+    }));
+    let faded: Arc<dyn Texture> = Arc::new(MixTexture::new(&wood, &tint, &upward));
+    let glow: Arc<dyn Texture> = Arc::new(GradientTexture::new(&Gradient::Radial {
+        center: Point3::new(2.0, 0.9, 3.0),
+        radius: 0.7,
+    }));
+    // kept off black so the unlit side still shows
+    let glow: Arc<dyn Texture> = Arc::new(RemapTexture::new(&glow, 0.0, 1.0, 0.1, 0.9));
+
+    for (x, tex) in [(-2.2, hues), (0.0, faded), (2.2, glow)] {
+        let mat = Arc::new(Lambertian::new(&tex)) as Arc<dyn Material>;
+        world.add(
+            &(Arc::new(Sphere::new(&Point3::new(x, 0.6, 2.5), 0.6, &mat)) as Arc<dyn Hittable>),
+        );
+    }
+
+    let cam = Camera::new(
+        &CameraParams {
+            aspect_ratio: 16.0 / 9.0,
+            image_width: 400,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            background: Color::new(0.7, 0.8, 1.0),
+            lookfrom: Point3::new(0.0, 3.0, 10.0),
+            lookat: Point3::new(0.0, 1.0, 0.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+        },
+        35.0,
+        0.0,
+        10.0,
+    );
+    cam.render(&world, &LightList::default(), matches);
+}
+
+/// Car paint from a shader graph between gold and silver.
+fn shaders(matches: &ArgMatches) {
+    let mut world = HittableList::default();
+
+    world.add(
+        &(Arc::new(Sphere::new(
+            &Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            &(Arc::new(Lambertian::from_color(&Color::new(0.5, 0.5, 0.5))) as Arc<dyn Material>),
+        )) as Arc<dyn Hittable>),
+    );
+    let paint = Arc::new(ShaderMaterial::load("shaders/car_paint.shader")) as Arc<dyn Material>;
+    world.add(
+        &(Arc::new(Sphere::new(&Point3::new(0.0, 1.2, 0.0), 1.2, &paint)) as Arc<dyn Hittable>),
+    );
+    let gold = Arc::new(Conductor::load(
+        "spectra/au.eta.spd",
+        "spectra/au.k.spd",
+        0.1,
+    )) as Arc<dyn Material>;
+    world.add(
+        &(Arc::new(Sphere::new(&Point3::new(-2.6, 0.8, 0.0), 0.8, &gold)) as Arc<dyn Hittable>),
+    );
+    let silver = Arc::new(Conductor::load(
+        "spectra/ag.eta.spd",
+        "spectra/ag.k.spd",
+        0.1,
+    )) as Arc<dyn Material>;
+    world.add(
+        &(Arc::new(Sphere::new(&Point3::new(2.6, 0.8, 0.0), 0.8, &silver)) as Arc<dyn Hittable>),
+    );
+
+    let light = Arc::new(DiffuseLight::from_color(&Color::new(6.0, 6.0, 6.0)));
+    let light_quad = Arc::new(Quad::new(
+        &Point3::new(-3.0, 6.0, -1.0),
+        &Vec3::new(6.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, 4.0),
+        &(light.clone() as Arc<dyn Material>),
+    )) as Arc<dyn Hittable>;
+    world.add(&light_quad);
+
+    let mut lights = LightList::default();
+    lights.add(&(Arc::new(AreaLight::new(&light_quad, &light)) as Arc<dyn Light>));
+
+    let cam = Camera::new(
+        &CameraParams {
+            aspect_ratio: 16.0 / 9.0,
+            image_width: 400,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            background: Color::new(0.3, 0.35, 0.4),
+            lookfrom: Point3::new(0.0, 3.0, 10.0),
+            lookat: Point3::new(0.0, 1.0, 0.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+        },
+        30.0,
+        0.0,
+        10.0,
+    );
+    cam.render(&world, &lights, matches);
+}
+
+/// A sphere behind a lattice cut out of a quad by the mortar of a brick
+/// texture, its shadow falling through the holes, and leaves cut out of quads
+/// by the alpha channel of an image.
+fn alpha_mask(matches: &ArgMatches) {
+    let mut world = HittableList::default();
+
+    world.add(
+        &(Arc::new(Sphere::new(
+            &Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            &(Arc::new(Lambertian::from_color(&Color::new(0.5, 0.5, 0.5))) as Arc<dyn Material>),
+        )) as Arc<dyn Hittable>),
+    );
+    let red = Arc::new(Lambertian::from_color(&Color::new(0.8, 0.2, 0.2))) as Arc<dyn Material>;
+    world.add(
+        &(Arc::new(Sphere::new(&Point3::new(0.0, 1.0, -1.5), 1.0, &red)) as Arc<dyn Hittable>),
+    );
+
+    // open bricks in solid mortar
+    let lattice: Arc<dyn Texture> = Arc::new(BrickTexture::from_colors(
+        0.2,
+        0.1,
+        0.03,
         &Color::zeros(),
+        &Color::new(1.0, 1.0, 1.0),
+    ));
+    let wood = Arc::new(Lambertian::from_color(&Color::new(0.6, 0.45, 0.3))) as Arc<dyn Material>;
+    let screen = Arc::new(AlphaMasked::new(&wood, &lattice)) as Arc<dyn Material>;
+    world.add(
+        &(Arc::new(Quad::new(
+            &Point3::new(-2.0, 0.0, 0.0),
+            &Vec3::new(4.0, 0.0, 0.0),
+            &Vec3::new(0.0, 3.0, 0.0),
+            &screen,
+        )) as Arc<dyn Hittable>),
+    );
+
+    // leaves cut out by the alpha of their image
+    let leaf: Arc<dyn Texture> = Arc::new(ImageTexture::new("masks/leaf.png"));
+    let green = Arc::new(Lambertian::new(&leaf)) as Arc<dyn Material>;
+    let green = Arc::new(AlphaMasked::from_image(&green, "masks/leaf.png")) as Arc<dyn Material>;
+    for (corner, along, across) in [
+        (
+            Point3::new(1.6, 2.2, 1.2),
+            Vec3::new(0.6, 0.5, 0.2),
+            Vec3::new(0.6, -0.2, 0.5),
+        ),
+        (
+            Point3::new(2.4, 1.5, 1.8),
+            Vec3::new(0.7, 0.0, -0.4),
+            Vec3::new(0.0, 0.8, 0.0),
+        ),
+        (
+            Point3::new(-2.8, 2.4, 1.0),
+            Vec3::new(0.8, 0.0, 0.0),
+            Vec3::new(0.0, 0.3, 0.8),
+        ),
+    ] {
+        world.add(&(Arc::new(Quad::new(&corner, &along, &across, &green)) as Arc<dyn Hittable>));
+    }
+
+    let light = Arc::new(DiffuseLight::from_color(&Color::new(10.0, 10.0, 10.0)));
+    let light_quad = Arc::new(Quad::new(
+        &Point3::new(-1.0, 5.0, 3.0),
+        &Vec3::new(2.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, 2.0),
+        &(light.clone() as Arc<dyn Material>),
+    )) as Arc<dyn Hittable>;
+    world.add(&light_quad);
+
+    let mut lights = LightList::default();
+    lights.add(&(Arc::new(AreaLight::new(&light_quad, &light)) as Arc<dyn Light>));
+
+    let cam = Camera::new(
+        &CameraParams {
+            aspect_ratio: 16.0 / 9.0,
+            image_width: 400,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            background: Color::new(0.2, 0.25, 0.3),
+            lookfrom: Point3::new(2.0, 2.0, 8.0),
+            lookat: Point3::new(0.0, 1.2, 0.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+        },
+        30.0,
+        0.0,
+        10.0,
+    );
+    cam.render(&world, &lights, matches);
+}
+
+/// A ring of spheres all around the camera, seen at once in a 360°
+/// latitude-longitude panorama.
+fn panorama(matches: &ArgMatches) {
+    let mut world = HittableList::default();
+
+    world.add(
+        &(Arc::new(Sphere::new(
+            &Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            &(Arc::new(Lambertian::from_color(&Color::new(0.5, 0.5, 0.5))) as Arc<dyn Material>),
+        )) as Arc<dyn Hittable>),
+    );
+    for i in 0..8 {
+        let angle = i as f64 * PI / 4.0;
+        let albedo = Color::new(
+            0.5 + 0.4 * angle.cos(),
+            0.5 + 0.4 * (angle + 2.0).cos(),
+            0.5 + 0.4 * (angle + 4.0).cos(),
+        );
+        let mat = Arc::new(Lambertian::from_color(&albedo)) as Arc<dyn Material>;
+        let center = Point3::new(5.0 * angle.sin(), 1.0, 5.0 * angle.cos());
+        world.add(&(Arc::new(Sphere::new(&center, 1.0, &mat)) as Arc<dyn Hittable>));
+    }
+
+    let mut lights = LightList::default();
+    lights.add(
+        &(Arc::new(DirectionalLight::new(
+            &Vec3::new(1.0, -2.0, 0.5),
+            &Color::new(2.0, 1.9, 1.7),
+        )) as Arc<dyn Light>),
+    );
+
+    let mut cam = Camera::new(
+        &CameraParams {
+            aspect_ratio: 2.0,
+            image_width: 400,
+            samples_per_pixel: 64,
+            max_depth: 50,
+            background: Color::new(0.5, 0.7, 1.0),
+            lookfrom: Point3::new(0.0, 1.5, 0.0),
+            lookat: Point3::new(0.0, 1.5, 1.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+        },
+        90.0,
+        0.0,
+        10.0,
+    );
+    cam.set_projection(&Projection::Equirectangular);
+    cam.render(&world, &lights, matches);
+}
+
+/// A glass prism lying along the x axis, its triangular cross section having
+/// `base` and `apex` (in the yz plane) as corners, `length` long.
+fn prism(
+    base: [(f64, f64); 2],
+    apex: (f64, f64),
+    length: f64,
+    mat: &Arc<dyn Material>,
+) -> HittableList {
+    let mut sides = HittableList::default();
+    let x0 = -length / 2.0;
+    let corner = |x: f64, (y, z): (f64, f64)| Point3::new(x, y, z);
+    let along = Vec3::new(length, 0.0, 0.0);
+    // every face has its normal facing out of the glass
+    for (from, to) in [(base[0], base[1]), (base[1], apex), (apex, base[0])] {
+        let edge = corner(0.0, to) - corner(0.0, from);
+        sides.add(
+            &(Arc::new(Quad::new(&corner(x0, from), &edge, &along, mat)) as Arc<dyn Hittable>),
+        );
+    }
+    sides.add(
+        &(Arc::new(Triangle::new(
+            &corner(x0, base[0]),
+            &corner(x0, apex),
+            &corner(x0, base[1]),
+            mat,
+        )) as Arc<dyn Hittable>),
+    );
+    sides.add(
+        &(Arc::new(Triangle::new(
+            &corner(-x0, base[0]),
+            &corner(-x0, base[1]),
+            &corner(-x0, apex),
+            mat,
+        )) as Arc<dyn Hittable>),
+    );
+    sides
+}
+
+/// A slit of white light seen through a BK7 prism, spread into its colors,
+/// and through a ball of dense flint glass. Only spectral paths disperse, so
+/// this wants `--spectral`.
+fn dispersion(matches: &ArgMatches) {
+    let mut world = HittableList::default();
+
+    let floor = Arc::new(Lambertian::from_color(&Color::new(0.4, 0.4, 0.4))) as Arc<dyn Material>;
+    world.add(
+        &(Arc::new(Sphere::new(&Point3::new(0.0, -1001.0, 0.0), 1000.0, &floor))
+            as Arc<dyn Hittable>),
+    );
+    let bk7 = Arc::new(Dielectric::bk7()) as Arc<dyn Material>;
+    world.add(
+        &(Arc::new(prism([(0.2, 0.6), (0.2, -0.6)], (1.24, 0.0), 3.0, &bk7)) as Arc<dyn Hittable>),
+    );
+    let flint = Arc::new(Dielectric::new_dispersive(&Dispersion::Cauchy {
+        a: 1.728,
+        b: 0.01342,
+    })) as Arc<dyn Material>;
+    world.add(
+        &(Arc::new(Sphere::new(&Point3::new(2.4, 0.6, 0.0), 0.5, &flint)) as Arc<dyn Hittable>),
+    );
+
+    let light = Arc::new(DiffuseLight::from_color(&Color::new(8.0, 8.0, 8.0)));
+    let slit = Arc::new(Quad::new(
+        &Point3::new(-4.0, -0.94, -2.0),
+        &Vec3::new(8.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.06, 0.0),
+        &(light.clone() as Arc<dyn Material>),
+    )) as Arc<dyn Hittable>;
+    world.add(&slit);
+    let mut lights = LightList::default();
+    lights.add(&(Arc::new(AreaLight::new(&slit, &light)) as Arc<dyn Light>));
+
+    let cam = Camera::new(
+        &CameraParams {
+            aspect_ratio: 16.0 / 9.0,
+            image_width: 400,
+            samples_per_pixel: 64,
+            max_depth: 20,
+            background: Color::new(0.02, 0.02, 0.03),
+            lookfrom: Point3::new(0.0, 0.9, 5.0),
+            lookat: Point3::new(0.0, 0.4, 0.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+        },
+        40.0,
+        0.0,
+        10.0,
+    );
+    cam.render(&world, &lights, matches);
+}
+
+/// The earth map tiled over a floor running off to the horizon, elliptically
+/// filtered so it stays sharp across the view while it shrinks along it,
+/// between a wall of mirrored tiles and a framed poster. With few samples per
+/// pixel, `--texture-filter` shows what the other filters make of it.
+fn texture_filtering(matches: &ArgMatches) {
+    let mut world = HittableList::default();
+    let earth = |filter: TextureFilter, wrap: WrapMode, transform: &UvTransform| {
+        let image: Arc<dyn Texture> = Arc::new(ImageTexture::new_with_sampling(
+            "earthmap.jpg",
+            ColorEncoding::Srgb,
+            filter,
+            wrap,
+        ));
+        let tex: Arc<dyn Texture> = Arc::new(MappedTexture::from_transform(&image, transform));
+        Arc::new(Lambertian::new(&tex)) as Arc<dyn Material>
+    };
+
+    let floor = earth(
+        TextureFilter::Ewa,
+        WrapMode::Repeat,
+        &UvTransform::new((4.0, 40.0), 0.0, (0.0, 0.0)),
+    );
+    world.add(
+        &(Arc::new(Quad::new(
+            &Point3::new(-10.0, 0.0, 5.0),
+            &Vec3::new(20.0, 0.0, 0.0),
+            &Vec3::new(0.0, 0.0, -200.0),
+            &floor,
+        )) as Arc<dyn Hittable>),
+    );
+    let tiles = earth(
+        TextureFilter::Trilinear,
+        WrapMode::Mirror,
+        &UvTransform::new((2.0, 3.0), 0.0, (0.0, 0.0)),
+    );
+    world.add(
+        &(Arc::new(Quad::new(
+            &Point3::new(-4.0, 0.0, -2.0),
+            &Vec3::new(0.0, 0.0, -12.0),
+            &Vec3::new(0.0, 3.0, 0.0),
+            &tiles,
+        )) as Arc<dyn Hittable>),
+    );
+    // a dark frame around the image
+    let poster = earth(
+        TextureFilter::Trilinear,
+        WrapMode::Border(Color::new(0.05, 0.05, 0.05)),
+        &UvTransform::new((1.2, 1.4), 0.0, (-0.1, -0.2)),
+    );
+    world.add(
+        &(Arc::new(Quad::new(
+            &Point3::new(1.5, 0.5, -3.0),
+            &Vec3::new(3.0, 0.0, -1.0),
+            &Vec3::new(0.0, 1.6, 0.0),
+            &poster,
+        )) as Arc<dyn Hittable>),
+    );
+
+    let cam = Camera::new(
+        &CameraParams {
+            aspect_ratio: 16.0 / 9.0,
+            image_width: 400,
+            samples_per_pixel: 16,
+            max_depth: 50,
+            background: Color::new(0.9, 0.9, 0.9),
+            lookfrom: Point3::new(0.0, 1.2, 4.0),
+            lookat: Point3::new(0.0, 0.6, -10.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+        },
+        50.0,
+        0.0,
+        10.0,
+    );
+    cam.render(&world, &LightList::default(), matches);
+}
+
+fn texture_mappings(matches: &ArgMatches) {
+    let mut world = HittableList::default();
+    let earth: Arc<dyn Texture> = Arc::new(ImageTexture::new_with_sampling(
+        "earthmap.jpg",
+        ColorEncoding::Srgb,
+        TextureFilter::Trilinear,
+        WrapMode::Repeat,
+    ));
+    let mapped = |mapping: TextureMapping, transform: &UvTransform| {
+        let tex: Arc<dyn Texture> = Arc::new(MappedTexture::new(&earth, &mapping, transform));
+        Arc::new(Lambertian::new(&tex)) as Arc<dyn Material>
+    };
+
+    // the floor repeats every four units
+    let floor = mapped(
+        TextureMapping::Planar {
+            origin: Point3::zeros(),
+            s_axis: Vec3::new(0.25, 0.0, 0.0),
+            t_axis: Vec3::new(0.0, 0.0, 0.25),
+        },
+        &UvTransform::default(),
+    );
+    world.add(
+        &(Arc::new(Quad::new(
+            &Point3::new(-20.0, 0.0, 20.0),
+            &Vec3::new(40.0, 0.0, 0.0),
+            &Vec3::new(0.0, 0.0, -40.0),
+            &floor,
+        )) as Arc<dyn Hittable>),
+    );
+
+    // a cube wrapped like a globe
+    let globe = mapped(
+        TextureMapping::Spherical {
+            center: Point3::new(-2.4, 0.8, 0.0),
+        },
+        &UvTransform::default(),
+    );
+    world.add(
+        &(hittable::get_box(
+            &Point3::new(-3.2, 0.0, -0.8),
+            &Point3::new(-1.6, 1.6, 0.8),
+            &globe,
+        ) as Arc<dyn Hittable>),
+    );
+
+    // a sphere wrapped like a label, the image spanning its height
+    let label = mapped(
+        TextureMapping::Cylindrical {
+            center: Point3::new(0.0, 0.0, 0.0),
+        },
+        &UvTransform::new((1.0, 0.5), 0.0, (0.0, 0.0)),
+    );
+    world.add(
+        &(Arc::new(Sphere::new(&Point3::new(0.0, 1.0, 0.0), 1.0, &label)) as Arc<dyn Hittable>),
+    );
+
+    // a turned box without UVs of its own
+    let projected = mapped(
+        TextureMapping::Triplanar { sharpness: 4.0 },
+        &UvTransform::new((0.5, 0.5), 0.0, (0.0, 0.0)),
+    );
+    let cube = hittable::get_box(&Point3::zeros(), &Point3::new(1.5, 1.5, 1.5), &projected);
+    let cube: Arc<dyn Hittable> = Arc::new(RotateY::new(&(cube as Arc<dyn Hittable>), 30.0));
+    world.add(&(Arc::new(Translate::new(&cube, &Vec3::new(1.8, 0.0, -0.6))) as Arc<dyn Hittable>));
+
+    let cam = Camera::new(
+        &CameraParams {
+            aspect_ratio: 16.0 / 9.0,
+            image_width: 400,
+            samples_per_pixel: 32,
+            max_depth: 50,
+            background: Color::new(0.7, 0.8, 1.0),
+            lookfrom: Point3::new(0.0, 3.0, 7.0),
+            lookat: Point3::new(0.0, 0.8, 0.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+        },
+        45.0,
+        0.0,
+        10.0,
+    );
+    cam.render(&world, &LightList::default(), matches);
+}
+
+/// Textures changing with the time of the rays: a flowing floor, a turning
+/// globe and a clock played from an image sequence, one frame per unit of
+/// time, beside a box and a ball colored from voxels. Still images blur one
+/// frame; `--camera-path paths/still.path` renders the eight frames.
+fn animated_textures(matches: &ArgMatches) {
+    let mut world = HittableList::default();
+
+    let noise: Arc<dyn Texture> = Arc::new(NoiseTexture::new(2.0));
+    let flowing: Arc<dyn Texture> = Arc::new(ScrollingTexture::new(
+        &noise,
+        &Vec3::new(0.3, 0.0, 0.0),
+        (0.0, 0.0),
+    ));
+    let floor = Arc::new(Lambertian::new(&flowing)) as Arc<dyn Material>;
+    world.add(
+        &(Arc::new(Quad::new(
+            &Point3::new(-20.0, 0.0, 20.0),
+            &Vec3::new(40.0, 0.0, 0.0),
+            &Vec3::new(0.0, 0.0, -40.0),
+            &floor,
+        )) as Arc<dyn Hittable>),
+    );
+
+    // a turn every twenty frames
+    let earth: Arc<dyn Texture> = Arc::new(ImageTexture::new_with_sampling(
+        "earthmap.jpg",
+        ColorEncoding::Srgb,
+        TextureFilter::Trilinear,
+        WrapMode::Repeat,
+    ));
+    let turning: Arc<dyn Texture> =
+        Arc::new(ScrollingTexture::new(&earth, &Vec3::zeros(), (-0.05, 0.0)));
+    let globe = Arc::new(Lambertian::new(&turning)) as Arc<dyn Material>;
+    world.add(
+        &(Arc::new(Sphere::new(&Point3::new(-3.0, 1.0, 0.0), 1.0, &globe)) as Arc<dyn Hittable>),
+    );
+
+    let clock: Arc<dyn Texture> = Arc::new(FlipbookTexture::from_sequence(
+        "flipbook/clock.####.png",
+        1,
+        8,
+        1.0,
+    ));
+    let face = Arc::new(Lambertian::new(&clock)) as Arc<dyn Material>;
+    world.add(
+        &(Arc::new(Quad::new(
+            &Point3::new(-1.6, 0.1, 0.0),
+            &Vec3::new(1.8, 0.0, 0.0),
+            &Vec3::new(0.0, 1.8, 0.0),
+            &face,
+        )) as Arc<dyn Hittable>),
+    );
+
+    let (min, max) = (Point3::new(0.6, 0.0, -0.6), Point3::new(1.8, 1.2, 0.6));
+    let color_cube: Arc<dyn Texture> =
+        Arc::new(VoxelTexture::load("voxels/color_cube.txt", &min, &max));
+    let cube = Arc::new(Lambertian::new(&color_cube)) as Arc<dyn Material>;
+    world.add(&(hittable::get_box(&min, &max, &cube) as Arc<dyn Hittable>));
+
+    // agate-like bands, shells around a point off the center of the ball
+    let center = Point3::new(3.2, 0.8, 0.0);
+    let core = center + Vec3::new(0.4, 0.6, 0.8);
+    let shells: Arc<dyn Texture> = Arc::new(VoxelTexture::from_fn(
+        [32, 32, 32],
+        &(center - Vec3::new(0.8, 0.8, 0.8)),
+        &(center + Vec3::new(0.8, 0.8, 0.8)),
+        |p: &Point3| {
+            let t = 0.5 + 0.5 * (20.0 * (*p - core).length()).sin();
+            Color::new(0.9, 0.5, 0.2) * t + Color::new(0.2, 0.1, 0.05) * (1.0 - t)
+        },
+    ));
+    let onion = Arc::new(Lambertian::new(&shells)) as Arc<dyn Material>;
+    world.add(&(Arc::new(Sphere::new(&center, 0.8, &onion)) as Arc<dyn Hittable>));
+
+    let cam = Camera::new(
+        &CameraParams {
+            aspect_ratio: 16.0 / 9.0,
+            image_width: 400,
+            samples_per_pixel: 32,
+            max_depth: 50,
+            background: Color::new(0.7, 0.8, 1.0),
+            lookfrom: Point3::new(0.0, 2.0, 8.0),
+            lookat: Point3::new(0.0, 0.8, 0.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+        },
+        35.0,
+        0.0,
+        10.0,
+    );
+    cam.render(&world, &LightList::default(), matches);
+}
+
+/// The last scene of Ray Tracing: The Next Week: a floor of boxes, a moving
+/// sphere, glass holding blue smoke, a cluster of spheres and thin fog over
+/// everything.
+fn final_scene(matches: &ArgMatches) {
+    let mut boxes1 = HittableList::default();
+    let ground =
+        Arc::new(Lambertian::from_color(&Color::new(0.48, 0.83, 0.53))) as Arc<dyn Material>;
+    let boxes_per_side = 20;
+    for i in 0..boxes_per_side {
+        for j in 0..boxes_per_side {
+            let w = 100.0;
+            let x0 = -1000.0 + i as f64 * w;
+            let z0 = -1000.0 + j as f64 * w;
+            let y1 = rtweekend::random_double_in_range(1.0, 101.0);
+            boxes1.add(
+                &(hittable::get_box(
+                    &Point3::new(x0, 0.0, z0),
+                    &Point3::new(x0 + w, y1, z0 + w),
+                    &ground,
+                ) as Arc<dyn Hittable>),
+            );
+        }
+    }
+
+    let mut world = HittableList::default();
+    world.add(&(Arc::new(BvhNode::from_hittable_list(&mut boxes1)) as Arc<dyn Hittable>));
+
+    let light = Arc::new(DiffuseLight::from_color(&Color::new(7.0, 7.0, 7.0)));
+    let light_quad = Arc::new(Quad::new(
+        &Point3::new(123.0, 554.0, 147.0),
+        &Vec3::new(300.0, 0.0, 0.0),
+        &Vec3::new(0.0, 0.0, 265.0),
+        &(light.clone() as Arc<dyn Material>),
+    )) as Arc<dyn Hittable>;
+    world.add(&light_quad);
+
+    let center1 = Point3::new(400.0, 400.0, 200.0);
+    let center2 = center1 + Vec3::new(30.0, 0.0, 0.0);
+    let sphere_material =
+        Arc::new(Lambertian::from_color(&Color::new(0.7, 0.3, 0.1))) as Arc<dyn Material>;
+    world.add(
+        &(Arc::new(Sphere::new_moving(
+            &center1,
+            &center2,
+            50.0,
+            &sphere_material,
+        )) as Arc<dyn Hittable>),
+    );
+
+    let glass = Arc::new(Dielectric::new(1.5)) as Arc<dyn Material>;
+    world.add(
+        &(Arc::new(Sphere::new(&Point3::new(260.0, 150.0, 45.0), 50.0, &glass))
+            as Arc<dyn Hittable>),
+    );
+    world.add(
+        &(Arc::new(Sphere::new(
+            &Point3::new(0.0, 150.0, 145.0),
+            50.0,
+            &(Arc::new(Metal::new(&Color::new(0.8, 0.8, 0.9), 1.0)) as Arc<dyn Material>),
+        )) as Arc<dyn Hittable>),
+    );
+
+    let boundary =
+        Arc::new(Sphere::new(&Point3::new(360.0, 150.0, 145.0), 70.0, &glass)) as Arc<dyn Hittable>;
+    world.add(&boundary);
+    world.add(
+        &(Arc::new(ConstantMedium::new_with_color(
+            &boundary,
+            0.2,
+            &Color::new(0.2, 0.4, 0.9),
+        )) as Arc<dyn Hittable>),
+    );
+    // only bounds the fog, so it never shades anything
+    let outline = Arc::new(BaseMaterial::new()) as Arc<dyn Material>;
+    let boundary = Arc::new(Sphere::new(&Point3::zeros(), 5000.0, &outline)) as Arc<dyn Hittable>;
+    world.add(
+        &(Arc::new(ConstantMedium::new_with_color(
+            &boundary,
+            0.0001,
+            &Color::new(1.0, 1.0, 1.0),
+        )) as Arc<dyn Hittable>),
+    );
+
+    let earth: Arc<dyn Texture> = Arc::new(ImageTexture::new("earthmap.jpg"));
+    world.add(
+        &(Arc::new(Sphere::new(
+            &Point3::new(400.0, 200.0, 400.0),
+            100.0,
+            &(Arc::new(Lambertian::new(&earth)) as Arc<dyn Material>),
+        )) as Arc<dyn Hittable>),
+    );
+    let pertext: Arc<dyn Texture> = Arc::new(NoiseTexture::new(0.2));
+    world.add(
+        &(Arc::new(Sphere::new(
+            &Point3::new(220.0, 280.0, 300.0),
+            80.0,
+            &(Arc::new(Lambertian::new(&pertext)) as Arc<dyn Material>),
+        )) as Arc<dyn Hittable>),
+    );
+
+    let mut boxes2 = HittableList::default();
+    let white =
+        Arc::new(Lambertian::from_color(&Color::new(0.73, 0.73, 0.73))) as Arc<dyn Material>;
+    for _ in 0..1000 {
+        boxes2.add(
+            &(Arc::new(Sphere::new(
+                &Point3::random_in_range(0.0, 165.0),
+                10.0,
+                &white,
+            )) as Arc<dyn Hittable>),
+        );
+    }
+    let cluster = Arc::new(BvhNode::from_hittable_list(&mut boxes2)) as Arc<dyn Hittable>;
+    let cluster = Arc::new(RotateY::new(&cluster, 15.0)) as Arc<dyn Hittable>;
+    world.add(
+        &(Arc::new(Translate::new(&cluster, &Vec3::new(-100.0, 270.0, 395.0)))
+            as Arc<dyn Hittable>),
+    );
+
+    let mut lights = LightList::default();
+    lights.add(&(Arc::new(AreaLight::new(&light_quad, &light)) as Arc<dyn Light>));
+
+    let cam = Camera::new(
+        &CameraParams {
+            aspect_ratio: 1.0,
+            image_width: 400,
+            samples_per_pixel: 64,
+            max_depth: 40,
+            background: Color::zeros(),
+            lookfrom: Point3::new(478.0, 278.0, -600.0),
+            lookat: Point3::new(278.0, 278.0, 0.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+        },
         40.0,
-        &Point3::new(278.0, 278.0, -800.0),
-        &Point3::new(278.0, 278.0, 0.0),
-        &Vec3::new(0.0, 1.0, 0.0),
         0.0,
         10.0,
     );
-    cam.render(&world);
-    // cam.render(&world, &lights);
+    cam.render(&world, &lights, matches);
 }
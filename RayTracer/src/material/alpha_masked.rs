@@ -0,0 +1,88 @@
+use super::Material;
+use crate::{
+    color::Color,
+    color_space::ColorEncoding,
+    hittable::HitRecord,
+    ray::Ray,
+    spectrum::{SampledSpectrum, SampledWavelengths},
+    texture::{ImageTexture, Texture},
+    vec3::Point3,
+};
+use std::sync::Arc;
+
+/// `mat` cut out by an opacity mask, for leaves, fences and the like. Rays
+/// pass through where the luminance of `mask` is 0, and through the fraction
+/// of hits it leaves open in between.
+pub struct AlphaMasked {
+    mat: Arc<dyn Material>,
+    mask: Arc<dyn Texture>,
+}
+
+impl AlphaMasked {
+    pub fn new(mat: &Arc<dyn Material>, mask: &Arc<dyn Texture>) -> Self {
+        Self {
+            mat: mat.clone(),
+            mask: mask.clone(),
+        }
+    }
+
+    /// Masked by the alpha channel of an image.
+    pub fn from_image(mat: &Arc<dyn Material>, filename: &str) -> Self {
+        let mask: Arc<dyn Texture> = Arc::new(ImageTexture::new_with_encoding(
+            filename,
+            ColorEncoding::Alpha,
+        ));
+        Self::new(mat, &mask)
+    }
+}
+
+impl Material for AlphaMasked {
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: &Point3) -> Color {
+        self.mat.emitted(r_in, rec, u, v, p)
+    }
+
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        self.mat.scatter(r_in, rec, attenuation, scattered)
+    }
+
+    fn scatter_spectral(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        lambda: &mut SampledWavelengths,
+        attenuation: &mut SampledSpectrum,
+        scattered: &mut Ray,
+    ) -> bool {
+        self.mat
+            .scatter_spectral(r_in, rec, lambda, attenuation, scattered)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.mat.scattering_pdf(r_in, rec, scattered)
+    }
+
+    fn bsdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        self.mat.bsdf(r_in, rec, scattered)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.mat.albedo(rec)
+    }
+
+    fn is_specular(&self) -> bool {
+        self.mat.is_specular()
+    }
+
+    /// The mask at the time of the hit, looked up without filtering since
+    /// hits are tested before their ray differentials are known.
+    fn alpha(&self, rec: &HitRecord) -> f64 {
+        let alpha = self.mask.value_at(rec).luminance();
+        alpha.clamp(0.0, 1.0) * self.mat.alpha(rec)
+    }
+}
@@ -0,0 +1,142 @@
+use super::Material;
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    ray::Ray,
+    spectrum::{PiecewiseLinearSpectrum, SampledSpectrum, SampledWavelengths},
+    vec3::Vec3,
+};
+use std::sync::Arc;
+
+/// Wavelengths standing in for the red, green and blue channels on RGB paths.
+const RGB_WAVELENGTHS: [f64; 3] = [630.0, 532.0, 465.0];
+
+/// A metal colored by the Fresnel reflectance of its measured complex index
+/// of refraction `eta` + i `k` rather than by an albedo.
+pub struct Conductor {
+    eta: Arc<PiecewiseLinearSpectrum>,
+    k: Arc<PiecewiseLinearSpectrum>,
+    fuzz: f64,
+}
+
+impl Conductor {
+    pub fn new(
+        eta: &Arc<PiecewiseLinearSpectrum>,
+        k: &Arc<PiecewiseLinearSpectrum>,
+        fuzz: f64,
+    ) -> Self {
+        Self {
+            eta: eta.clone(),
+            k: k.clone(),
+            fuzz: fuzz.min(1.0),
+        }
+    }
+
+    /// Loads `eta` and `k` from spectrum files in the assets directory. Gold,
+    /// copper, silver and aluminium are there as `spectra/au.eta.spd` and
+    /// `spectra/au.k.spd` and so on.
+    pub fn load(eta_filename: &str, k_filename: &str, fuzz: f64) -> Self {
+        Self::new(
+            &Arc::new(PiecewiseLinearSpectrum::load(eta_filename)),
+            &Arc::new(PiecewiseLinearSpectrum::load(k_filename)),
+            fuzz,
+        )
+    }
+
+    /// Reflects `r_in` about the normal, perturbed by the fuzz, and returns the
+    /// cosine at the surface.
+    fn reflect(&self, r_in: &Ray, rec: &HitRecord, scattered: &mut Ray) -> f64 {
+        let reflected =
+            r_in.direction().reflect(&rec.normal).unit() + Vec3::random_unit_vector() * self.fuzz;
+        *scattered = Ray::new_with_time(&rec.p, &reflected, r_in.time());
+        (-r_in.direction().unit() * rec.normal).clamp(0.0, 1.0)
+    }
+
+    fn reflectance(&self, cos_theta: f64, lambda: f64) -> f64 {
+        fresnel_conductor(
+            cos_theta,
+            self.eta.evaluate(lambda),
+            self.k.evaluate(lambda),
+        )
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        let cos_theta = self.reflect(r_in, rec, scattered);
+        let [r, g, b] = RGB_WAVELENGTHS.map(|lambda| self.reflectance(cos_theta, lambda));
+        *attenuation = Color::new(r, g, b);
+        *scattered.direction() * rec.normal > 0.0
+    }
+
+    fn scatter_spectral(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        lambda: &mut SampledWavelengths,
+        attenuation: &mut SampledSpectrum,
+        scattered: &mut Ray,
+    ) -> bool {
+        let cos_theta = self.reflect(r_in, rec, scattered);
+        let (eta, k) = (self.eta.sample(lambda), self.k.sample(lambda));
+        *attenuation = SampledSpectrum::from_fn(|i| fresnel_conductor(cos_theta, eta[i], k[i]));
+        *scattered.direction() * rec.normal > 0.0
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        let [r, g, b] = RGB_WAVELENGTHS.map(|lambda| self.reflectance(1.0, lambda));
+        Color::new(r, g, b)
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+}
+
+/// Fresnel reflectance of unpolarized light arriving from air at a conductor
+/// (pbrt-v3).
+fn fresnel_conductor(cos_theta: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos_theta * cos_theta;
+    let sin2 = 1.0 - cos2;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_theta * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    0.5 * (rp + rs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shipped_metals_are_colored() {
+        let gold = Conductor::load("spectra/au.eta.spd", "spectra/au.k.spd", 0.0);
+        let copper = Conductor::load("spectra/cu.eta.spd", "spectra/cu.k.spd", 0.0);
+        let silver = Conductor::load("spectra/ag.eta.spd", "spectra/ag.k.spd", 0.0);
+        let rec = HitRecord::default();
+
+        let gold = gold.albedo(&rec);
+        assert!(gold.x > gold.y && gold.y > gold.z);
+        let copper = copper.albedo(&rec);
+        assert!(copper.x > copper.y && copper.x > copper.z);
+        let silver = silver.albedo(&rec);
+        assert!(silver.z > 0.85 && (silver.x - silver.z).abs() < 0.1);
+    }
+}
@@ -1,43 +1,136 @@
 use super::Material;
-use crate::{color::Color, hittable::HitRecord, ray::Ray, rtweekend};
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    ray::Ray,
+    rtweekend,
+    spectrum::{SampledSpectrum, SampledWavelengths},
+};
+
+/// How the refractive index of a glass changes with the wavelength.
+#[derive(Clone, Copy)]
+pub enum Dispersion {
+    /// n = a + b / λ², with λ in micrometers
+    Cauchy { a: f64, b: f64 },
+    /// n² = 1 + Σ b_i λ² / (λ² - c_i), with λ in micrometers
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    /// The refractive index at `lambda` nanometers.
+    pub fn index(&self, lambda: f64) -> f64 {
+        let lambda2 = (lambda / 1000.0).powi(2);
+        match self {
+            Self::Cauchy { a, b } => a + b / lambda2,
+            Self::Sellmeier { b, c } => {
+                let sum: f64 = (0..3).map(|i| b[i] * lambda2 / (lambda2 - c[i])).sum();
+                (1.0 + sum).sqrt()
+            }
+        }
+    }
+}
+
+/// Wavelength of the sodium d line, where glasses are usually specified.
+const D_LINE: f64 = 587.6;
 
 pub struct Dielectric {
     refraction_index: f64,
+    /// spreads light into its colors on spectral paths
+    dispersion: Option<Dispersion>,
 }
 
 impl Dielectric {
     pub fn new(refraction_index: f64) -> Self {
-        Self { refraction_index }
+        Self {
+            refraction_index,
+            dispersion: None,
+        }
     }
-}
 
-impl Material for Dielectric {
-    fn scatter(
+    /// A dispersive glass, with its index at the d line on RGB paths.
+    pub fn new_dispersive(dispersion: &Dispersion) -> Self {
+        Self {
+            refraction_index: dispersion.index(D_LINE),
+            dispersion: Some(*dispersion),
+        }
+    }
+
+    /// Schott N-BK7, the common crown glass of lenses and prisms.
+    pub fn bk7() -> Self {
+        Self::new_dispersive(&Dispersion::Sellmeier {
+            b: [1.03961212, 0.231792344, 1.01046945],
+            c: [0.00600069867, 0.0200179144, 103.560653],
+        })
+    }
+
+    /// Reflects or refracts `r_in` at an interface with `refraction_index`.
+    fn scatter_with_index(
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        attenuation: &mut Color,
+        refraction_index: f64,
         scattered: &mut Ray,
-    ) -> bool {
-        *attenuation = Color::ones();
+    ) {
         let ri = if rec.front_face {
-            1.0 / self.refraction_index
+            1.0 / refraction_index
         } else {
-            self.refraction_index
+            refraction_index
         };
 
         let unit_direction = r_in.direction().unit();
         let cos_theta = (-unit_direction * rec.normal).min(1.0);
         let sin_theta = (1.0 - cos_theta.powi(2)).sqrt();
 
-        let direction;
-        if ri * sin_theta > 1.0 || reflectance(cos_theta, ri) > rtweekend::random_double() {
-            direction = unit_direction.reflect(&rec.normal);
-        } else {
-            direction = unit_direction.refract(&rec.normal, ri);
-        }
+        let direction =
+            if ri * sin_theta > 1.0 || reflectance(cos_theta, ri) > rtweekend::random_double() {
+                unit_direction.reflect(&rec.normal)
+            } else {
+                unit_direction.refract(&rec.normal, ri)
+            };
 
         *scattered = Ray::new_with_time(&rec.p, &direction, r_in.time());
+    }
+}
+
+impl Material for Dielectric {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        *attenuation = Color::ones();
+        self.scatter_with_index(r_in, rec, self.refraction_index, scattered);
+        true
+    }
+
+    fn scatter_spectral(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        lambda: &mut SampledWavelengths,
+        attenuation: &mut SampledSpectrum,
+        scattered: &mut Ray,
+    ) -> bool {
+        *attenuation = SampledSpectrum::ones();
+        let refraction_index = match &self.dispersion {
+            Some(dispersion) => {
+                // the other wavelengths would bend differently
+                lambda.terminate_secondary();
+                dispersion.index(lambda.hero())
+            }
+            None => self.refraction_index,
+        };
+        self.scatter_with_index(r_in, rec, refraction_index, scattered);
+        true
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::ones()
+    }
+
+    fn is_specular(&self) -> bool {
         true
     }
 }
@@ -46,3 +139,43 @@ fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
     let r0 = ((1.0 - refraction_index) / (1.0 + refraction_index)).powi(2);
     r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wavelengths of the hydrogen F and C lines, bounding the Abbe number.
+    const F_LINE: f64 = 486.1;
+    const C_LINE: f64 = 656.3;
+
+    #[test]
+    fn test_bk7_index() {
+        let bk7 = Dielectric::bk7();
+        let dispersion = bk7.dispersion.unwrap();
+        // as listed by Schott
+        assert!((bk7.refraction_index - 1.5168).abs() < 1e-4);
+        assert!((dispersion.index(D_LINE) - 1.5168).abs() < 1e-4);
+        let abbe = (dispersion.index(D_LINE) - 1.0)
+            / (dispersion.index(F_LINE) - dispersion.index(C_LINE));
+        assert!((abbe - 64.17).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_index_falls_with_wavelength() {
+        let glasses = [
+            Dielectric::bk7().dispersion.unwrap(),
+            Dispersion::Cauchy {
+                a: 1.728,
+                b: 0.01342,
+            },
+        ];
+        for dispersion in glasses {
+            let indices: Vec<f64> = (400..=700)
+                .step_by(10)
+                .map(|lambda| dispersion.index(lambda as f64))
+                .collect();
+            assert!(indices.windows(2).all(|pair| pair[1] < pair[0]));
+            assert!(indices[0] - indices[indices.len() - 1] > 0.01);
+        }
+    }
+}
@@ -6,32 +6,130 @@ use crate::{
     texture::{SolidColor, Texture},
     vec3::Point3,
 };
-use std::sync::Arc;
+use std::{f64::consts::PI, sync::Arc};
 
 pub struct DiffuseLight {
     tex: Arc<dyn Texture>,
+    scale: f64,
+    two_sided: bool,
 }
 
 impl DiffuseLight {
-    // pub fn new(tex: &Arc<dyn Texture>) -> Self {
-    //     Self {
-    //         tex: tex.clone(),
-    //     }
-    // }
+    pub fn new(tex: &Arc<dyn Texture>) -> Self {
+        Self::new_with_scale(tex, 1.0, false)
+    }
 
     pub fn from_color(emit: &Color) -> Self {
+        Self::new(&(Arc::new(SolidColor::new(emit)) as Arc<dyn Texture>))
+    }
+
+    pub fn new_with_scale(tex: &Arc<dyn Texture>, scale: f64, two_sided: bool) -> Self {
         Self {
-            tex: Arc::new(SolidColor::new(emit)),
+            tex: tex.clone(),
+            scale,
+            two_sided,
         }
     }
+
+    /// Scales `tex` so that a surface of `area` square meters emits `power` watts in total.
+    pub fn from_power(tex: &Arc<dyn Texture>, power: f64, area: f64, two_sided: bool) -> Self {
+        let unit_power = Self::new_with_scale(tex, 1.0, two_sided).power(area);
+        let scale = if unit_power > 0.0 {
+            power / unit_power
+        } else {
+            0.0
+        };
+        Self::new_with_scale(tex, scale, two_sided)
+    }
+
+    pub fn is_two_sided(&self) -> bool {
+        self.two_sided
+    }
+
+    /// Total emitted power of a surface of `area` covered with this light.
+    pub fn power(&self, area: f64) -> f64 {
+        let sides = if self.two_sided { 2.0 } else { 1.0 };
+        PI * area * sides * self.scale * average_luminance(&self.tex)
+    }
 }
 
 impl Material for DiffuseLight {
     fn emitted(&self, _r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: &Point3) -> Color {
-        if rec.front_face {
-            self.tex.value(u, v, p)
+        if rec.front_face || self.two_sided {
+            self.tex.value(u, v, p) * self.scale
         } else {
             Color::zeros()
         }
     }
 }
+
+fn average_luminance(tex: &Arc<dyn Texture>) -> f64 {
+    const N: u32 = 16;
+
+    let mut accum = 0.0;
+    for j in 0..N {
+        for i in 0..N {
+            let u = (i as f64 + 0.5) / N as f64;
+            let v = (j as f64 + 0.5) / N as f64;
+            accum += tex.value(u, v, &Point3::zeros()).luminance();
+        }
+    }
+
+    accum / (N * N) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Emission rising from black to white along u.
+    struct Ramp;
+
+    impl Texture for Ramp {
+        fn value(&self, u: f64, _v: f64, _p: &Point3) -> Color {
+            Color::new(u, u, u)
+        }
+    }
+
+    /// Power leaving a 2 by 1.5 surface covered with `light`, integrating the
+    /// luminance of the emitted radiance over the surface and the cosine
+    /// weighted directions on both sides.
+    fn integrated_power(light: &DiffuseLight) -> f64 {
+        const N: u32 = 64;
+        let area = 2.0 * 1.5;
+        let (d_theta, d_phi) = (PI / 2.0 / N as f64, 2.0 * PI / N as f64);
+        let projected_solid_angle: f64 = (0..N)
+            .map(|i| {
+                let theta = (i as f64 + 0.5) * d_theta;
+                theta.cos() * theta.sin() * d_theta * d_phi * N as f64
+            })
+            .sum();
+
+        let r = Ray::default();
+        let mut power = 0.0;
+        for front_face in [true, false] {
+            let rec = HitRecord {
+                front_face,
+                ..Default::default()
+            };
+            for j in 0..N {
+                for i in 0..N {
+                    let (u, v) = ((i as f64 + 0.5) / N as f64, (j as f64 + 0.5) / N as f64);
+                    let radiance = light.emitted(&r, &rec, u, v, &Point3::zeros()).luminance();
+                    power += radiance * projected_solid_angle * area / (N * N) as f64;
+                }
+            }
+        }
+        power
+    }
+
+    #[test]
+    fn test_emitted_power_matches_from_power() {
+        let ramp = Arc::new(Ramp) as Arc<dyn Texture>;
+        for two_sided in [false, true] {
+            let light = DiffuseLight::from_power(&ramp, 100.0, 3.0, two_sided);
+            assert!((light.power(3.0) - 100.0).abs() < 1e-9);
+            assert!((integrated_power(&light) - 100.0).abs() < 0.1);
+        }
+    }
+}
@@ -13,9 +13,9 @@ pub struct Isotropic {
 }
 
 impl Isotropic {
-    // pub fn new(tex: &Arc<dyn Texture>) -> Self {
-    //     Self { tex: tex.clone() }
-    // }
+    pub fn new(tex: &Arc<dyn Texture>) -> Self {
+        Self { tex: tex.clone() }
+    }
 
     pub fn from_color(albedo: &Color) -> Self {
         Self {
@@ -32,7 +32,7 @@ impl Material for Isotropic {
         attenuation: &mut Color,
         scattered: &mut Ray,
     ) -> bool {
-        *attenuation = self.tex.value(rec.u, rec.v, &rec.p);
+        *attenuation = self.tex.value_at(rec);
         *scattered = Ray::new_with_time(&rec.p, &Vec3::random_unit_vector(), r_in.time());
         true
     }
@@ -40,4 +40,16 @@ impl Material for Isotropic {
     fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
         1.0 / (4.0 * PI)
     }
+
+    fn bsdf(&self, _r_in: &Ray, rec: &HitRecord, _scattered: &Ray) -> Color {
+        self.tex.value_at(rec) / (4.0 * PI)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.tex.value_at(rec)
+    }
+
+    fn is_volumetric(&self) -> bool {
+        true
+    }
 }
@@ -37,7 +37,7 @@ impl Material for Lambertian {
         uvw.build_from_w(&rec.normal);
         let scatter_direction = uvw.local_with_vec3(&Vec3::random_cosine_direction());
 
-        *attenuation = self.tex.value(rec.u, rec.v, &rec.p);
+        *attenuation = self.tex.value_at(rec);
         *scattered = Ray::new_with_time(&rec.p, &scatter_direction.unit(), r_in.time());
         true
     }
@@ -45,6 +45,18 @@ impl Material for Lambertian {
     fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
         let mut uvw = Onb::new();
         uvw.build_from_w(&rec.normal);
-        uvw.w() * *scattered.direction() / PI
+        (uvw.w() * scattered.direction().unit() / PI).max(0.0)
+    }
+
+    fn bsdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        if rec.normal * *scattered.direction() > 0.0 {
+            self.tex.value_at(rec) / PI
+        } else {
+            Color::zeros()
+        }
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.tex.value_at(rec)
     }
 }
@@ -29,4 +29,12 @@ impl Material for Metal {
         *attenuation = self.albedo;
         *scattered.direction() * rec.normal > 0.0
     }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.albedo
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
 }
@@ -1,18 +1,32 @@
+mod alpha_masked;
 mod base_material;
+mod conductor;
 mod dielectric;
 mod diffuse_light;
 mod isotropic;
 mod lambertian;
 mod metal;
+mod normal_mapped;
+mod shader_material;
 
+pub use alpha_masked::AlphaMasked;
 pub use base_material::BaseMaterial;
-pub use dielectric::Dielectric;
+pub use conductor::Conductor;
+pub use dielectric::{Dielectric, Dispersion};
 pub use diffuse_light::DiffuseLight;
 pub use isotropic::Isotropic;
 pub use lambertian::Lambertian;
 pub use metal::Metal;
+pub use normal_mapped::NormalMapped;
+pub use shader_material::ShaderMaterial;
 
-use crate::{color::Color, hittable::HitRecord, ray::Ray, vec3::Point3};
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    ray::Ray,
+    spectrum::{SampledSpectrum, SampledWavelengths},
+    vec3::Point3,
+};
 
 #[allow(unused_variables)]
 pub trait Material: Send + Sync {
@@ -30,7 +44,55 @@ pub trait Material: Send + Sync {
         false
     }
 
+    /// `scatter` for a path traced at the wavelengths `lambda`. Materials that
+    /// depend on the wavelength override it, and may keep only the hero
+    /// wavelength; the RGB attenuation of the others is upsampled.
+    fn scatter_spectral(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        lambda: &mut SampledWavelengths,
+        attenuation: &mut SampledSpectrum,
+        scattered: &mut Ray,
+    ) -> bool {
+        let mut rgb = Color::zeros();
+        if !self.scatter(r_in, rec, &mut rgb, scattered) {
+            return false;
+        }
+        *attenuation = SampledSpectrum::from_rgb(&rgb, lambda);
+        true
+    }
+
     fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
         0.0
     }
+
+    /// The BSDF (or phase function) for light leaving along `scattered`, without
+    /// the cosine term. Zero for specular materials, which can't be connected to.
+    fn bsdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        Color::zeros()
+    }
+
+    /// The surface color at `rec`, for auxiliary outputs.
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        Color::zeros()
+    }
+
+    /// Whether `scatter` picks a single mirror-like direction instead of sampling
+    /// `scattering_pdf`.
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    /// Whether this is a phase function, scattering over the whole sphere of
+    /// directions rather than the hemisphere around the normal.
+    fn is_volumetric(&self) -> bool {
+        false
+    }
+
+    /// The opacity of the surface at `rec`, in [0, 1]. Hits are kept with this
+    /// probability, so rays pass through where it is 0.
+    fn alpha(&self, rec: &HitRecord) -> f64 {
+        1.0
+    }
 }
@@ -0,0 +1,222 @@
+use super::Material;
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    ray::Ray,
+    spectrum::{SampledSpectrum, SampledWavelengths},
+    texture::Texture,
+    vec3::Point3,
+};
+use std::sync::Arc;
+
+/// How the shading normal is perturbed.
+enum Perturbation {
+    /// a tangent-space normal map, RGB in [0, 1] encoding xyz in [-1, 1], with
+    /// its tilt scaled by `strength`
+    NormalMap {
+        map: Arc<dyn Texture>,
+        strength: f64,
+    },
+    /// the luminance of `height`, times `scale`, as an offset along the normal
+    Bump {
+        height: Arc<dyn Texture>,
+        scale: f64,
+    },
+}
+
+/// Another material shaded with a normal tilted by a texture, adding surface
+/// detail without geometry.
+pub struct NormalMapped {
+    mat: Arc<dyn Material>,
+    perturbation: Perturbation,
+}
+
+impl NormalMapped {
+    pub fn new_normal_map(mat: &Arc<dyn Material>, map: &Arc<dyn Texture>, strength: f64) -> Self {
+        Self {
+            mat: mat.clone(),
+            perturbation: Perturbation::NormalMap {
+                map: map.clone(),
+                strength,
+            },
+        }
+    }
+
+    pub fn new_bump_map(mat: &Arc<dyn Material>, height: &Arc<dyn Texture>, scale: f64) -> Self {
+        Self {
+            mat: mat.clone(),
+            perturbation: Perturbation::Bump {
+                height: height.clone(),
+                scale,
+            },
+        }
+    }
+
+    /// `rec` with the perturbed normal and the tangent frame around it.
+    fn shade(&self, rec: &HitRecord) -> HitRecord {
+        let n = rec.normal;
+        let shading_normal = match &self.perturbation {
+            Perturbation::NormalMap { map, strength } => {
+                let c = map.value_at(rec) * 2.0 - Color::new(1.0, 1.0, 1.0);
+                rec.tangent * (c.x * strength) + rec.bitangent * (c.y * strength) + n * c.z
+            }
+            Perturbation::Bump { height, scale } => {
+                // finite differences over about the pixel footprint (pbrt)
+                let mut du = 0.5 * (rec.dudx.abs() + rec.dudy.abs());
+                if du == 0.0 {
+                    du = 0.0005;
+                }
+                let mut dv = 0.5 * (rec.dvdx.abs() + rec.dvdy.abs());
+                if dv == 0.0 {
+                    dv = 0.0005;
+                }
+
+                let displace = |du: f64, dv: f64| {
+                    let mut shifted = rec.clone();
+                    shifted.p = rec.p + rec.dpdu * du + rec.dpdv * dv;
+                    shifted.u = rec.u + du;
+                    shifted.v = rec.v + dv;
+                    height.value_at(&shifted).luminance() * scale
+                };
+                let d = displace(0.0, 0.0);
+                let dpdu = rec.dpdu + n * ((displace(du, 0.0) - d) / du);
+                let dpdv = rec.dpdv + n * ((displace(0.0, dv) - d) / dv);
+                let bumped = dpdu.cross(&dpdv);
+                if bumped * n < 0.0 {
+                    -bumped
+                } else {
+                    bumped
+                }
+            }
+        };
+        if shading_normal.squared_length() == 0.0 {
+            return rec.clone();
+        }
+
+        let mut shaded = rec.clone();
+        shaded.normal = shading_normal.unit();
+        shaded.set_tangent_frame();
+        shaded
+    }
+}
+
+impl Material for NormalMapped {
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: &Point3) -> Color {
+        self.mat.emitted(r_in, rec, u, v, p)
+    }
+
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        self.mat
+            .scatter(r_in, &self.shade(rec), attenuation, scattered)
+    }
+
+    fn scatter_spectral(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        lambda: &mut SampledWavelengths,
+        attenuation: &mut SampledSpectrum,
+        scattered: &mut Ray,
+    ) -> bool {
+        self.mat
+            .scatter_spectral(r_in, &self.shade(rec), lambda, attenuation, scattered)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.mat.scattering_pdf(r_in, &self.shade(rec), scattered)
+    }
+
+    fn bsdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        self.mat.bsdf(r_in, &self.shade(rec), scattered)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.mat.albedo(rec)
+    }
+
+    fn is_specular(&self) -> bool {
+        self.mat.is_specular()
+    }
+
+    fn alpha(&self, rec: &HitRecord) -> f64 {
+        self.mat.alpha(rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hittable::{Hittable, Quad},
+        interval::Interval,
+        material::Lambertian,
+        texture::SolidColor,
+        vec3::Vec3,
+    };
+
+    /// Heights rising along u.
+    struct Slope;
+
+    impl Texture for Slope {
+        fn value(&self, u: f64, _v: f64, _p: &Point3) -> Color {
+            Color::new(u, u, u)
+        }
+    }
+
+    /// A hit on a 2 by 2 quad facing +z, with its tangent frame along x and y.
+    fn hit(mat: &Arc<dyn Material>) -> HitRecord {
+        let quad = Quad::new(
+            &Point3::new(-1.0, -1.0, 0.0),
+            &Vec3::new(2.0, 0.0, 0.0),
+            &Vec3::new(0.0, 2.0, 0.0),
+            mat,
+        );
+        let r = Ray::new(&Point3::new(0.2, 0.3, 1.0), &Vec3::new(0.0, 0.0, -1.0));
+        let mut rec = HitRecord::default();
+        assert!(quad.hit(&r, &Interval::new(0.001, f64::INFINITY), &mut rec));
+        rec.prepare_shading(&r);
+        rec
+    }
+
+    #[test]
+    fn test_normal_map_tilts_along_the_tangent_frame() {
+        let base =
+            Arc::new(Lambertian::from_color(&Color::new(0.5, 0.5, 0.5))) as Arc<dyn Material>;
+        // tangent-space (0.5, -0.5, 1)
+        let map: Arc<dyn Texture> = Arc::new(SolidColor::new(&Color::new(0.75, 0.25, 1.0)));
+        let rec = hit(&base);
+
+        let mapped = NormalMapped::new_normal_map(&base, &map, 1.0);
+        let expected = (rec.tangent * 0.5 - rec.bitangent * 0.5 + rec.normal).unit();
+        let shaded = mapped.shade(&rec);
+        assert!((shaded.normal - expected).length() < 1e-6);
+        assert!((shaded.tangent * shaded.normal).abs() < 1e-9);
+
+        // with no strength the map only keeps the normal
+        let flat = NormalMapped::new_normal_map(&base, &map, 0.0);
+        assert!((flat.shade(&rec).normal - rec.normal).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_bump_tilts_normal_down_the_slope() {
+        let base =
+            Arc::new(Lambertian::from_color(&Color::new(0.5, 0.5, 0.5))) as Arc<dyn Material>;
+        let scale = 0.5;
+        let bumped =
+            NormalMapped::new_bump_map(&base, &(Arc::new(Slope) as Arc<dyn Texture>), scale);
+        let rec = hit(&base);
+
+        // the height rises by `scale` over the 2 units along x
+        let expected = Vec3::new(-scale / 2.0, 0.0, 1.0).unit();
+        let shaded = bumped.shade(&rec);
+        assert!((shaded.normal - expected).length() < 1e-6);
+        assert!((shaded.tangent * shaded.normal).abs() < 1e-9);
+        assert!((shaded.bitangent * shaded.normal).abs() < 1e-9);
+    }
+}
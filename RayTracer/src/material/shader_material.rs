@@ -0,0 +1,344 @@
+use super::{Dielectric, Material};
+use crate::{
+    color::Color,
+    color_space::ColorEncoding,
+    hittable::HitRecord,
+    onb::Onb,
+    ray::Ray,
+    shader_graph::{gray, BsdfParams, MathOp, Node, NodeId, ShaderGraph, ShaderOutput, MAX_NODES},
+    texture::{FractalNoise, ImageTexture, NoiseKind, WorleyFeature, WorleyTexture},
+    vec3::{Point3, Vec3},
+};
+use std::{collections::HashMap, f64::consts::PI, fs};
+
+/// How a `ShaderMaterial` scatters light, with its color as the albedo.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum ShaderBsdf {
+    #[default]
+    Diffuse,
+    /// fuzzed by the roughness
+    Metal,
+    Glass {
+        ior: f64,
+    },
+}
+
+/// A material whose parameters come from a shader graph at every hit.
+pub struct ShaderMaterial {
+    graph: ShaderGraph,
+    bsdf: ShaderBsdf,
+}
+
+impl ShaderMaterial {
+    pub fn new(graph: ShaderGraph, bsdf: ShaderBsdf) -> Self {
+        Self { graph, bsdf }
+    }
+
+    /// Loads a shader from the assets directory. Every line is a node,
+    /// `name = kind inputs...`, an output, `color|roughness|emission input`,
+    /// or the BSDF, `bsdf diffuse|metal|glass [ior]`, with `#` starting a
+    /// comment. Inputs are node names or numbers. Images are sRGB unless
+    /// followed by `linear` or `raw`. The node kinds are `constant`, `image`,
+    /// `noise`, `worley`, `checker`, `uv`, `position`, `normal`, `time`,
+    /// `add`, `subtract`, `multiply`, `divide`, `power`, `minimum`, `maximum`,
+    /// `mix`, `fresnel`, `layer_weight` and `ramp`.
+    pub fn load(filename: &str) -> Self {
+        let path = String::from("assets/") + filename;
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => {
+                eprintln!("ERROR: Could not load shader file '{}'.", path);
+                // the cyan of missing images
+                let mut graph = ShaderGraph::new();
+                let cyan = graph.add(Node::Constant(Color::new(0.0, 1.0, 1.0)));
+                graph.set_output(ShaderOutput::Color, cyan);
+                return Self::new(graph, ShaderBsdf::Diffuse);
+            }
+        };
+
+        let mut parser = Parser::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
+            if let Err(message) = parser.parse_line(&words) {
+                eprintln!("ERROR: {} line {}: {}", path, index + 1, message);
+            }
+        }
+        Self::new(parser.graph, parser.bsdf)
+    }
+
+    /// The graph at `rec` seen from `wo`.
+    fn params(&self, rec: &HitRecord, wo: &Vec3) -> BsdfParams {
+        self.graph.evaluate(rec, wo)
+    }
+}
+
+impl Material for ShaderMaterial {
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord, _u: f64, _v: f64, _p: &Point3) -> Color {
+        if !self.graph.has_emission() || !rec.front_face {
+            return Color::zeros();
+        }
+        self.params(rec, &-r_in.direction().unit()).emission
+    }
+
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+    ) -> bool {
+        let params = self.params(rec, &-r_in.direction().unit());
+        match self.bsdf {
+            ShaderBsdf::Diffuse => {
+                let mut uvw = Onb::new();
+                uvw.build_from_w(&rec.normal);
+                let scatter_direction = uvw.local_with_vec3(&Vec3::random_cosine_direction());
+                *scattered = Ray::new_with_time(&rec.p, &scatter_direction.unit(), r_in.time());
+                *attenuation = params.color;
+                true
+            }
+            ShaderBsdf::Metal => {
+                let reflected = r_in.direction().reflect(&rec.normal).unit()
+                    + Vec3::random_unit_vector() * params.roughness;
+                *scattered = Ray::new_with_time(&rec.p, &reflected, r_in.time());
+                *attenuation = params.color;
+                *scattered.direction() * rec.normal > 0.0
+            }
+            ShaderBsdf::Glass { ior } => {
+                let scatters = Dielectric::new(ior).scatter(r_in, rec, attenuation, scattered);
+                *attenuation = attenuation.elemul(&params.color);
+                scatters
+            }
+        }
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        match self.bsdf {
+            ShaderBsdf::Diffuse => (rec.normal * scattered.direction().unit() / PI).max(0.0),
+            _ => 0.0,
+        }
+    }
+
+    fn bsdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        if self.bsdf != ShaderBsdf::Diffuse || rec.normal * *scattered.direction() <= 0.0 {
+            return Color::zeros();
+        }
+        self.params(rec, &-r_in.direction().unit()).color / PI
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.params(rec, &rec.normal).color
+    }
+
+    fn is_specular(&self) -> bool {
+        self.bsdf != ShaderBsdf::Diffuse
+    }
+}
+
+/// Builds a graph line by line, naming the nodes.
+#[derive(Default)]
+struct Parser {
+    graph: ShaderGraph,
+    bsdf: ShaderBsdf,
+    names: HashMap<String, NodeId>,
+}
+
+impl Parser {
+    fn parse_line(&mut self, words: &[&str]) -> Result<(), String> {
+        match words {
+            ["bsdf", "diffuse"] => self.bsdf = ShaderBsdf::Diffuse,
+            ["bsdf", "metal"] => self.bsdf = ShaderBsdf::Metal,
+            ["bsdf", "glass", ior] => self.bsdf = ShaderBsdf::Glass { ior: number(ior)? },
+            [output @ ("color" | "roughness" | "emission"), input] => {
+                let input = self.input(input)?;
+                let output = match *output {
+                    "color" => ShaderOutput::Color,
+                    "roughness" => ShaderOutput::Roughness,
+                    _ => ShaderOutput::Emission,
+                };
+                self.graph.set_output(output, input);
+            }
+            [name, "=", kind, args @ ..] => {
+                let node = self.node(kind, args)?;
+                let id = self.add(node)?;
+                self.names.insert(name.to_string(), id);
+            }
+            _ => return Err(format!("unexpected '{}'", words.join(" "))),
+        }
+        Ok(())
+    }
+
+    fn node(&mut self, kind: &str, args: &[&str]) -> Result<Node, String> {
+        let op = match kind {
+            "add" => Some(MathOp::Add),
+            "subtract" => Some(MathOp::Subtract),
+            "multiply" => Some(MathOp::Multiply),
+            "divide" => Some(MathOp::Divide),
+            "power" => Some(MathOp::Power),
+            "minimum" => Some(MathOp::Minimum),
+            "maximum" => Some(MathOp::Maximum),
+            _ => None,
+        };
+        if let Some(op) = op {
+            let [a, b] = args else {
+                return Err(format!("'{}' takes two inputs", kind));
+            };
+            return Ok(Node::Math {
+                op,
+                a: self.input(a)?,
+                b: self.input(b)?,
+            });
+        }
+
+        match (kind, args) {
+            ("constant", [value]) => Ok(Node::Constant(gray(number(value)?))),
+            ("constant", [r, g, b]) => Ok(Node::Constant(color([r, g, b])?)),
+            ("image", [filename]) => Ok(Node::Image(ImageTexture::new(filename))),
+            ("image", [filename, encoding]) => {
+                let encoding = match *encoding {
+                    "srgb" => ColorEncoding::Srgb,
+                    "linear" => ColorEncoding::Linear,
+                    "raw" => ColorEncoding::Raw,
+                    _ => return Err(format!("unknown color encoding '{}'", encoding)),
+                };
+                Ok(Node::Image(ImageTexture::new_with_encoding(
+                    filename, encoding,
+                )))
+            }
+            ("noise", [kind, scale, octaves]) => {
+                let kind = match *kind {
+                    "fbm" => NoiseKind::Fbm,
+                    "turbulence" => NoiseKind::Turbulence,
+                    "ridged" => NoiseKind::Ridged,
+                    _ => return Err(format!("unknown noise '{}'", kind)),
+                };
+                let noise = FractalNoise::new(kind, number(scale)?, number(octaves)? as u32);
+                Ok(Node::Noise(Box::new(noise)))
+            }
+            ("worley", [feature, scale]) => {
+                let feature = match *feature {
+                    "f1" => WorleyFeature::F1,
+                    "f2-f1" => WorleyFeature::F2MinusF1,
+                    "cell" => WorleyFeature::CellId,
+                    _ => return Err(format!("unknown worley feature '{}'", feature)),
+                };
+                Ok(Node::Worley(WorleyTexture::new(number(scale)?, feature)))
+            }
+            ("checker", [scale, r1, g1, b1, r2, g2, b2]) => Ok(Node::Checker {
+                scale: number(scale)?,
+                even: color([r1, g1, b1])?,
+                odd: color([r2, g2, b2])?,
+            }),
+            ("uv", []) => Ok(Node::Uv),
+            ("position", []) => Ok(Node::Position),
+            ("normal", []) => Ok(Node::Normal),
+            ("time", []) => Ok(Node::Time),
+            ("mix", [a, b, factor]) => Ok(Node::Mix {
+                a: self.input(a)?,
+                b: self.input(b)?,
+                factor: self.input(factor)?,
+            }),
+            ("fresnel", [ior]) => Ok(Node::Fresnel { ior: number(ior)? }),
+            ("layer_weight", [blend]) => Ok(Node::LayerWeight {
+                blend: number(blend)?,
+            }),
+            ("ramp", [input, stops @ ..]) if !stops.is_empty() && stops.len() % 4 == 0 => {
+                let stops = stops
+                    .chunks(4)
+                    .map(|stop| Ok((number(stop[0])?, color([stop[1], stop[2], stop[3]])?)))
+                    .collect::<Result<_, String>>()?;
+                Ok(Node::ColorRamp {
+                    input: self.input(input)?,
+                    stops,
+                })
+            }
+            _ => Err(format!("bad node '{} {}'", kind, args.join(" "))),
+        }
+    }
+
+    /// A named node, or a new constant for a number.
+    fn input(&mut self, word: &str) -> Result<NodeId, String> {
+        if let Ok(value) = word.parse::<f64>() {
+            return self.add(Node::Constant(gray(value)));
+        }
+        self.names
+            .get(word)
+            .copied()
+            .ok_or_else(|| format!("unknown node '{}'", word))
+    }
+
+    fn add(&mut self, node: Node) -> Result<NodeId, String> {
+        if self.graph.len() >= MAX_NODES {
+            return Err(format!("more than {} nodes", MAX_NODES));
+        }
+        Ok(self.graph.add(node))
+    }
+}
+
+fn number(word: &str) -> Result<f64, String> {
+    word.parse()
+        .map_err(|_| format!("expected a number, got '{}'", word))
+}
+
+fn color(words: [&str; 3]) -> Result<Color, String> {
+    Ok(Color::new(
+        number(words[0])?,
+        number(words[1])?,
+        number(words[2])?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(lines: &[&str]) -> Result<Parser, String> {
+        let mut parser = Parser::default();
+        for line in lines {
+            parser.parse_line(&line.split_whitespace().collect::<Vec<_>>())?;
+        }
+        Ok(parser)
+    }
+
+    #[test]
+    fn test_parser_reports_errors() {
+        assert_eq!(parse(&["a = add x 1"]).err().unwrap(), "unknown node 'x'");
+        assert_eq!(
+            parse(&["a = add 1"]).err().unwrap(),
+            "'add' takes two inputs"
+        );
+        assert_eq!(
+            parse(&["n = noise blue 4 2"]).err().unwrap(),
+            "unknown noise 'blue'"
+        );
+        assert!(parse(&["bsdf glass thick"]).is_err());
+        assert!(parse(&["color"]).is_err());
+    }
+
+    #[test]
+    fn test_parsed_graph_evaluates() {
+        let parser = parse(&[
+            "base = constant 0.2 0.4 0.6",
+            "tint = multiply base 0.5",
+            "color tint",
+            "roughness 2",
+            "bsdf metal",
+        ])
+        .unwrap();
+        assert!(parser.bsdf == ShaderBsdf::Metal);
+
+        let material = ShaderMaterial::new(parser.graph, parser.bsdf);
+        let rec = HitRecord {
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            ..Default::default()
+        };
+        let params = material.params(&rec, &rec.normal);
+        assert_eq!(params.color, Color::new(0.1, 0.2, 0.3));
+        assert_eq!(params.roughness, 1.0);
+        assert_eq!(material.albedo(&rec), params.color);
+    }
+}
@@ -0,0 +1,342 @@
+use crate::{color::Color, texture_cache::TiledImage, vec3::Vec3};
+use std::sync::Arc;
+
+/// Longest an EWA ellipse may get relative to its width, bounding the texels
+/// a lookup reads at grazing angles.
+const MAX_ANISOTROPY: f64 = 8.0;
+const EWA_WEIGHT_LUT_SIZE: usize = 128;
+
+/// How an image texture is sampled over the footprint of a pixel.
+#[derive(Clone, Copy, PartialEq)]
+pub enum TextureFilter {
+    /// the texel under the lookup point, ignoring the footprint
+    Nearest,
+    /// the four nearest texels, ignoring the footprint
+    Bilinear,
+    /// bilinear lookups in the two mip levels whose texels are closest to the
+    /// footprint size
+    Trilinear,
+    /// elliptically weighted average over the footprint (Heckbert 1989),
+    /// sharp along the short axis of anisotropic footprints
+    Ewa,
+}
+
+/// What lookups outside of [0, 1]^2 see.
+#[derive(Clone, Copy, PartialEq)]
+pub enum WrapMode {
+    /// tiles the image
+    Repeat,
+    /// tiles the image, flipping every other tile so the seams match
+    Mirror,
+    /// extends the edge texels
+    Clamp,
+    /// a constant color around the image
+    Border(Color),
+}
+
+/// An image and its successive halvings down to a single texel, shared by
+/// every `MipMap` sampling it.
+#[derive(Default)]
+pub struct MipPyramid {
+    /// from the full image to 1x1, none when the image is missing
+    levels: Vec<TiledImage>,
+}
+
+impl MipPyramid {
+    /// `levels` from the full image on, each half the size of the one
+    /// before, continued down to 1x1.
+    pub fn new(mut levels: Vec<TiledImage>) -> Self {
+        while let Some(level) = levels.last().filter(|l| l.width() > 1 || l.height() > 1) {
+            let next = level.downsample();
+            levels.push(next);
+        }
+        Self { levels }
+    }
+}
+
+/// Filtered lookups into a mip pyramid, whose cost doesn't grow with the
+/// footprint (pbrt-v3).
+pub struct MipMap {
+    pyramid: Arc<MipPyramid>,
+    filter: TextureFilter,
+    wrap: WrapMode,
+    ewa_weights: Vec<f64>,
+}
+
+impl MipMap {
+    pub fn new(pyramid: &Arc<MipPyramid>, filter: TextureFilter, wrap: WrapMode) -> Self {
+        // Gaussian falloff, reaching 0 at the edge of the ellipse
+        let alpha = 2.0;
+        let ewa_weights = (0..EWA_WEIGHT_LUT_SIZE)
+            .map(|i| {
+                let r2 = i as f64 / (EWA_WEIGHT_LUT_SIZE - 1) as f64;
+                (-alpha * r2).exp() - (-alpha).exp()
+            })
+            .collect();
+
+        Self {
+            pyramid: pyramid.clone(),
+            filter,
+            wrap,
+            ewa_weights,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pyramid.levels.is_empty()
+    }
+
+    /// The filtered value around (`s`, `t`), with [0, 1]^2 covering the image
+    /// from its top left corner, and `dst0` and `dst1` the change of (s, t) to the neighbouring
+    /// pixels, spanning the footprint.
+    pub fn lookup(&self, s: f64, t: f64, dst0: &Vec3, dst1: &Vec3) -> Color {
+        match self.filter {
+            TextureFilter::Nearest => {
+                let level = &self.pyramid.levels[0];
+                self.texel(
+                    0,
+                    (s * level.width() as f64).floor() as i64,
+                    (t * level.height() as f64).floor() as i64,
+                )
+            }
+            TextureFilter::Bilinear => self.bilerp(0, s, t),
+            TextureFilter::Trilinear => {
+                let width = 2.0
+                    * dst0
+                        .x
+                        .abs()
+                        .max(dst0.y.abs())
+                        .max(dst1.x.abs())
+                        .max(dst1.y.abs());
+                self.trilinear(s, t, width)
+            }
+            TextureFilter::Ewa => self.ewa(s, t, dst0, dst1),
+        }
+    }
+
+    /// Texel (x, y) of `level`, wrapped into the image.
+    fn texel(&self, level: usize, x: i64, y: i64) -> Color {
+        let level = &self.pyramid.levels[level];
+        let (width, height) = (level.width() as i64, level.height() as i64);
+        let (x, y) = match self.wrap {
+            WrapMode::Repeat => (x.rem_euclid(width), y.rem_euclid(height)),
+            WrapMode::Mirror => (mirror(x, width), mirror(y, height)),
+            WrapMode::Clamp => (x.clamp(0, width - 1), y.clamp(0, height - 1)),
+            WrapMode::Border(color) => {
+                if x < 0 || y < 0 || x >= width || y >= height {
+                    return color;
+                }
+                (x, y)
+            }
+        };
+        level.texel(x as usize, y as usize)
+    }
+
+    fn bilerp(&self, level: usize, s: f64, t: f64) -> Color {
+        let level_image = &self.pyramid.levels[level];
+        let (width, height) = (level_image.width(), level_image.height());
+        let x = s * width as f64 - 0.5;
+        let y = t * height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (dx, dy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        self.texel(level, x0, y0) * ((1.0 - dx) * (1.0 - dy))
+            + self.texel(level, x0 + 1, y0) * (dx * (1.0 - dy))
+            + self.texel(level, x0, y0 + 1) * ((1.0 - dx) * dy)
+            + self.texel(level, x0 + 1, y0 + 1) * (dx * dy)
+    }
+
+    /// Blends the two levels whose texel spacing brackets `width`.
+    fn trilinear(&self, s: f64, t: f64, width: f64) -> Color {
+        let top = self.pyramid.levels.len() - 1;
+        let level = top as f64 + width.max(1e-8).log2();
+        if level <= 0.0 {
+            return self.bilerp(0, s, t);
+        }
+        if level >= top as f64 {
+            return self.texel(top, 0, 0);
+        }
+
+        let below = level.floor() as usize;
+        let delta = level - below as f64;
+        self.bilerp(below, s, t) * (1.0 - delta) + self.bilerp(below + 1, s, t) * delta
+    }
+
+    fn ewa(&self, s: f64, t: f64, dst0: &Vec3, dst1: &Vec3) -> Color {
+        let (mut major, mut minor) = (*dst0, *dst1);
+        if major.squared_length() < minor.squared_length() {
+            (major, minor) = (minor, major);
+        }
+        let major_length = major.length();
+        let mut minor_length = minor.length();
+
+        // widen overly eccentric ellipses, blurring them along the short axis
+        if minor_length * MAX_ANISOTROPY < major_length && minor_length > 0.0 {
+            let scale = major_length / (minor_length * MAX_ANISOTROPY);
+            minor *= scale;
+            minor_length *= scale;
+        }
+        if minor_length == 0.0 {
+            return self.bilerp(0, s, t);
+        }
+
+        // the level where the short axis spans a few texels
+        let top = self.pyramid.levels.len() - 1;
+        let level = (top as f64 + minor_length.log2()).max(0.0);
+        let below = level.floor() as usize;
+        let delta = level - below as f64;
+        self.ewa_level(below, s, t, &major, &minor) * (1.0 - delta)
+            + self.ewa_level(below + 1, s, t, &major, &minor) * delta
+    }
+
+    fn ewa_level(&self, level: usize, s: f64, t: f64, dst0: &Vec3, dst1: &Vec3) -> Color {
+        if level >= self.pyramid.levels.len() {
+            return self.texel(self.pyramid.levels.len() - 1, 0, 0);
+        }
+
+        // to texel units, centered on texels
+        let (width, height) = (
+            self.pyramid.levels[level].width() as f64,
+            self.pyramid.levels[level].height() as f64,
+        );
+        let (s, t) = (s * width - 0.5, t * height - 0.5);
+        let dst0 = Vec3::new(dst0.x * width, dst0.y * height, 0.0);
+        let dst1 = Vec3::new(dst1.x * width, dst1.y * height, 0.0);
+
+        // implicit ellipse A s^2 + B s t + C t^2 = 1 with axes dst0 and dst1,
+        // grown by a texel so it always covers one
+        let mut a = dst0.y * dst0.y + dst1.y * dst1.y + 1.0;
+        let mut b = -2.0 * (dst0.x * dst0.y + dst1.x * dst1.y);
+        let mut c = dst0.x * dst0.x + dst1.x * dst1.x + 1.0;
+        let inv_f = 1.0 / (a * c - b * b * 0.25);
+        a *= inv_f;
+        b *= inv_f;
+        c *= inv_f;
+
+        // bounding box of the ellipse
+        let det = -b * b + 4.0 * a * c;
+        let inv_det = 1.0 / det;
+        let u_sqrt = (det * c).sqrt();
+        let v_sqrt = (a * det).sqrt();
+        let s0 = (s - 2.0 * inv_det * u_sqrt).ceil() as i64;
+        let s1 = (s + 2.0 * inv_det * u_sqrt).floor() as i64;
+        let t0 = (t - 2.0 * inv_det * v_sqrt).ceil() as i64;
+        let t1 = (t + 2.0 * inv_det * v_sqrt).floor() as i64;
+
+        let mut sum = Color::zeros();
+        let mut weight_sum = 0.0;
+        for it in t0..=t1 {
+            let tt = it as f64 - t;
+            for is in s0..=s1 {
+                let ss = is as f64 - s;
+                let r2 = a * ss * ss + b * ss * tt + c * tt * tt;
+                if r2 < 1.0 {
+                    let index =
+                        ((r2 * EWA_WEIGHT_LUT_SIZE as f64) as usize).min(EWA_WEIGHT_LUT_SIZE - 1);
+                    let weight = self.ewa_weights[index];
+                    sum += self.texel(level, is, it) * weight;
+                    weight_sum += weight;
+                }
+            }
+        }
+
+        if weight_sum > 0.0 {
+            sum / weight_sum
+        } else {
+            self.bilerp(level, (s + 0.5) / width, (t + 0.5) / height)
+        }
+    }
+}
+
+/// `x` reflected back and forth into [0, `size`).
+fn mirror(x: i64, size: i64) -> i64 {
+    let x = x.rem_euclid(2 * size);
+    if x < size {
+        x
+    } else {
+        2 * size - 1 - x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 4x2 image whose red channel is the column and green the row.
+    fn mipmap(filter: TextureFilter, wrap: WrapMode) -> MipMap {
+        let image = TiledImage::from_fn(4, 2, |x, y| Color::new(x as f64, y as f64, 0.0));
+        MipMap::new(&Arc::new(MipPyramid::new(vec![image])), filter, wrap)
+    }
+
+    /// The nearest texel at texel coordinates (x, y), as seen through `wrap`.
+    fn texel_at(wrap: WrapMode, x: f64, y: f64) -> Color {
+        let zero = Vec3::zeros();
+        mipmap(TextureFilter::Nearest, wrap).lookup((x + 0.5) / 4.0, (y + 0.5) / 2.0, &zero, &zero)
+    }
+
+    #[test]
+    fn test_wrap_modes() {
+        let at = |wrap, x, y| {
+            let c = texel_at(wrap, x, y);
+            (c.x, c.y)
+        };
+        assert_eq!(at(WrapMode::Repeat, 5.0, -1.0), (1.0, 1.0));
+        assert_eq!(at(WrapMode::Repeat, -1.0, 2.0), (3.0, 0.0));
+        assert_eq!(at(WrapMode::Mirror, 4.0, -1.0), (3.0, 0.0));
+        assert_eq!(at(WrapMode::Mirror, -2.0, 3.0), (1.0, 0.0));
+        assert_eq!(at(WrapMode::Mirror, 9.0, 0.0), (1.0, 0.0));
+        assert_eq!(at(WrapMode::Clamp, 7.0, -3.0), (3.0, 0.0));
+        assert_eq!(at(WrapMode::Clamp, -7.0, 3.0), (0.0, 1.0));
+
+        let border = WrapMode::Border(Color::new(9.0, 9.0, 9.0));
+        assert_eq!(at(border, 4.0, 0.0), (9.0, 9.0));
+        assert_eq!(at(border, 3.0, 1.0), (3.0, 1.0));
+    }
+
+    #[test]
+    fn test_bilinear_blends_across_the_seam() {
+        let zero = Vec3::zeros();
+        // halfway between the last column and the first, wrapped around
+        let repeat =
+            mipmap(TextureFilter::Bilinear, WrapMode::Repeat).lookup(1.0, 0.25, &zero, &zero);
+        assert!((repeat.x - 1.5).abs() < 1e-12);
+        let clamp =
+            mipmap(TextureFilter::Bilinear, WrapMode::Clamp).lookup(1.0, 0.25, &zero, &zero);
+        assert!((clamp.x - 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_pyramid_ends_at_one_texel() {
+        let image = TiledImage::from_fn(5, 3, |_, _| Color::new(0.5, 0.5, 0.5));
+        let pyramid = MipPyramid::new(vec![image]);
+        let last = pyramid.levels.last().unwrap();
+        assert_eq!((last.width(), last.height()), (1, 1));
+        assert!((last.texel(0, 0) - Color::new(0.5, 0.5, 0.5)).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_ewa_stays_sharp_across_anisotropic_footprints() {
+        // white and black columns, one texel wide
+        let image = TiledImage::from_fn(64, 64, |x, _| Color::ones() * (x % 2) as f64);
+        let pyramid = Arc::new(MipPyramid::new(vec![image]));
+        let ewa = MipMap::new(&pyramid, TextureFilter::Ewa, WrapMode::Repeat);
+        let trilinear = MipMap::new(&pyramid, TextureFilter::Trilinear, WrapMode::Repeat);
+
+        // a third of a texel across the columns, eight texels along them
+        let across = Vec3::new(1.0 / 192.0, 0.0, 0.0);
+        let along = Vec3::new(0.0, 8.0 / 64.0, 0.0);
+        let (s, t) = (11.5 / 64.0, 0.5);
+        let sharp = ewa.lookup(s, t, &across, &along);
+        let blurred = trilinear.lookup(s, t, &across, &along);
+        assert!(sharp.x > 0.65, "{sharp:?}");
+        assert!((blurred.x - 0.5).abs() < 0.1, "{blurred:?}");
+
+        // isotropic footprints of several columns average them alike
+        let wide = Vec3::new(0.0, 8.0 / 64.0, 0.0);
+        let wide_across = Vec3::new(8.0 / 64.0, 0.0, 0.0);
+        let ewa_wide = ewa.lookup(s, t, &wide_across, &wide);
+        let trilinear_wide = trilinear.lookup(s, t, &wide_across, &wide);
+        assert!((ewa_wide.x - 0.5).abs() < 0.1 && (trilinear_wide.x - 0.5).abs() < 0.1);
+    }
+}
@@ -4,6 +4,7 @@ mod sphere_pdf;
 
 pub use cosine_pdf::CosinePdf;
 pub use hittable_pdf::HittablePdf;
+pub use sphere_pdf::SpherePdf;
 
 use crate::vec3::Vec3;
 
@@ -11,3 +12,14 @@ pub trait Pdf {
     fn value(&self, direction: &Vec3) -> f64;
     fn generate(&self) -> Vec3;
 }
+
+/// MIS weight of a sample drawn from `f_pdf` when `g_pdf` could have drawn it too.
+pub fn power_heuristic(f_pdf: f64, g_pdf: f64) -> f64 {
+    let f = f_pdf * f_pdf;
+    let g = g_pdf * g_pdf;
+    if f + g == 0.0 {
+        0.0
+    } else {
+        f / (f + g)
+    }
+}
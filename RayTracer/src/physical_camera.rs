@@ -0,0 +1,65 @@
+use crate::aperture::Aperture;
+
+/// Camera settings in photographic terms, turned into a field of view, a lens
+/// radius and an exposure by `Camera::new_physical`.
+#[derive(Clone)]
+pub struct PhysicalCamera {
+    /// in millimeters
+    pub focal_length: f64,
+    pub f_number: f64,
+    /// width of the sensor in millimeters, its height follows the aspect ratio
+    pub sensor_width: f64,
+    /// distance to the plane in focus, in scene units
+    pub focus_dist: f64,
+    /// in seconds
+    pub shutter_time: f64,
+    pub iso: f64,
+    /// exposure compensation in stops
+    pub exposure_bias: f64,
+    pub aperture: Aperture,
+    /// horizontal squeeze of an anamorphic lens, which makes bokeh oval
+    pub anamorphic_squeeze: f64,
+    /// how many scene units make a meter
+    pub units_per_meter: f64,
+}
+
+impl Default for PhysicalCamera {
+    /// A 50mm lens at f/2.8 on a full frame sensor, 1/60s at ISO 100.
+    fn default() -> Self {
+        Self {
+            focal_length: 50.0,
+            f_number: 2.8,
+            sensor_width: 36.0,
+            focus_dist: 10.0,
+            shutter_time: 1.0 / 60.0,
+            iso: 100.0,
+            exposure_bias: 0.0,
+            aperture: Aperture::Circle,
+            anamorphic_squeeze: 1.0,
+            units_per_meter: 1.0,
+        }
+    }
+}
+
+impl PhysicalCamera {
+    /// Vertical field of view in degrees.
+    pub fn vfov(&self, aspect_ratio: f64) -> f64 {
+        let sensor_height = self.sensor_width / aspect_ratio;
+        2.0 * (sensor_height / (2.0 * self.focal_length))
+            .atan()
+            .to_degrees()
+    }
+
+    /// Radius of the entrance pupil in scene units.
+    pub fn lens_radius(&self) -> f64 {
+        self.focal_length / 1000.0 / (2.0 * self.f_number) * self.units_per_meter
+    }
+
+    /// Factor applied to the scene radiance, from the exposure value at ISO 100
+    /// and the usual 1.2 calibration of a sensor's saturation level.
+    pub fn exposure(&self) -> f64 {
+        let ev100 = (self.f_number * self.f_number / self.shutter_time * 100.0 / self.iso).log2()
+            - self.exposure_bias;
+        1.0 / (1.2 * 2f64.powf(ev100))
+    }
+}
@@ -0,0 +1,173 @@
+use crate::vec3::Vec3;
+use std::f64::consts::PI;
+
+/// How film positions map to camera rays.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective,
+    /// parallel rays along the view direction over a view `height` scene
+    /// units tall, for technical drawings
+    Orthographic {
+        height: f64,
+    },
+    /// latitude-longitude 360° panorama, best with a 2:1 image
+    Equirectangular,
+    /// six 90° faces in a 3:2 image, right, left and up on the top row, down,
+    /// front and back on the bottom one
+    CubeMap,
+    /// equidistant (angular) fisheye with an image circle of `fov` degrees
+    /// inscribed in the image
+    Fisheye {
+        fov: f64,
+    },
+}
+
+impl Projection {
+    /// The direction through film position (`s`, `t`) in [0, 1]^2, from the
+    /// top left corner, as (right, up, forward) coordinates of the camera.
+    /// `None` where no ray leaves, outside the image circle of a fisheye.
+    ///
+    /// Only meaningful for the panoramic and fisheye projections, whose rays
+    /// all start at the camera center.
+    pub fn direction(&self, s: f64, t: f64, aspect_ratio: f64) -> Option<Vec3> {
+        match self {
+            Self::Equirectangular => {
+                let phi = (2.0 * s - 1.0) * PI;
+                let theta = t * PI;
+                Some(Vec3::new(
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                    theta.sin() * phi.cos(),
+                ))
+            }
+            Self::CubeMap => {
+                let column = ((s * 3.0) as u32).min(2);
+                let row = ((t * 2.0) as u32).min(1);
+                let a = 2.0 * (s * 3.0 - column as f64) - 1.0;
+                let b = 1.0 - 2.0 * (t * 2.0 - row as f64);
+
+                let (right, up, forward) = (
+                    Vec3::new(1.0, 0.0, 0.0),
+                    Vec3::new(0.0, 1.0, 0.0),
+                    Vec3::new(0.0, 0.0, 1.0),
+                );
+                // direction the face looks in, and its right and up axes
+                let (face, face_right, face_up) = match (row, column) {
+                    (0, 0) => (right, -forward, up),
+                    (0, 1) => (-right, forward, up),
+                    (0, _) => (up, right, -forward),
+                    (_, 0) => (-up, right, forward),
+                    (_, 1) => (forward, right, up),
+                    (_, _) => (-forward, -right, up),
+                };
+                Some(face + face_right * a + face_up * b)
+            }
+            Self::Fisheye { fov } => {
+                // normalized so the image circle touches the shorter image sides
+                let (mut x, mut y) = (2.0 * s - 1.0, 1.0 - 2.0 * t);
+                if aspect_ratio >= 1.0 {
+                    x *= aspect_ratio;
+                } else {
+                    y /= aspect_ratio;
+                }
+                let r = (x * x + y * y).sqrt();
+                if r > 1.0 {
+                    return None;
+                }
+
+                let theta = r * fov.to_radians() / 2.0;
+                let (sin_phi, cos_phi) = if r > 0.0 { (y / r, x / r) } else { (0.0, 1.0) };
+                Some(Vec3::new(
+                    theta.sin() * cos_phi,
+                    theta.sin() * sin_phi,
+                    theta.cos(),
+                ))
+            }
+            Self::Perspective | Self::Orthographic { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: &Vec3, b: &Vec3) {
+        assert!((*a - *b).length() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_equirectangular_center_and_poles() {
+        let p = Projection::Equirectangular;
+        assert_near(
+            &p.direction(0.5, 0.5, 2.0).unwrap(),
+            &Vec3::new(0.0, 0.0, 1.0),
+        );
+        assert_near(
+            &p.direction(0.75, 0.5, 2.0).unwrap(),
+            &Vec3::new(1.0, 0.0, 0.0),
+        );
+        assert_near(
+            &p.direction(0.3, 0.0, 2.0).unwrap(),
+            &Vec3::new(0.0, 1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn test_cube_map_faces() {
+        let p = Projection::CubeMap;
+        let mut faces = Vec::new();
+        for row in 0..2 {
+            for column in 0..3 {
+                let (s, t) = ((column as f64 + 0.5) / 3.0, (row as f64 + 0.5) / 2.0);
+                let face = p.direction(s, t, 1.5).unwrap();
+                // a and b run from -1 to 1 over the third and the half of the
+                // image a face takes up
+                let delta = 0.01;
+                let right = (p.direction(s + delta, t, 1.5).unwrap() - face) / (6.0 * delta);
+                let up = (p.direction(s, t + delta, 1.5).unwrap() - face) / (-4.0 * delta);
+
+                assert!((face.length() - 1.0).abs() < 1e-9);
+                assert!((right.length() - 1.0).abs() < 1e-9);
+                assert!((up.length() - 1.0).abs() < 1e-9);
+                assert!((face * right).abs() < 1e-9);
+                assert!((face * up).abs() < 1e-9);
+                assert!((right * up).abs() < 1e-9);
+                // seen from inside the cube, every face is right handed
+                assert_near(&right.cross(&up), &face);
+                faces.push(face);
+            }
+        }
+
+        for axis in [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ] {
+            assert!(faces.iter().any(|face| (*face - axis).length() < 1e-9));
+            assert!(faces.iter().any(|face| (*face + axis).length() < 1e-9));
+        }
+    }
+
+    #[test]
+    fn test_fisheye_image_circle() {
+        let p = Projection::Fisheye { fov: 180.0 };
+        assert_near(
+            &p.direction(0.5, 0.5, 1.0).unwrap(),
+            &Vec3::new(0.0, 0.0, 1.0),
+        );
+        // the edge of the circle looks sideways at 90°
+        assert_near(
+            &p.direction(1.0, 0.5, 1.0).unwrap(),
+            &Vec3::new(1.0, 0.0, 0.0),
+        );
+        assert_near(
+            &p.direction(0.5, 0.0, 1.0).unwrap(),
+            &Vec3::new(0.0, 1.0, 0.0),
+        );
+        assert!(p.direction(0.05, 0.05, 1.0).is_none());
+        // on a wide image the circle touches the top and bottom only
+        assert!(p.direction(0.9, 0.5, 2.0).is_none());
+        assert!(p.direction(0.7, 0.5, 2.0).is_some());
+    }
+}
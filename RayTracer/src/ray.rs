@@ -1,18 +1,32 @@
 use crate::vec3::{Point3, Vec3};
 
-#[derive(Default)]
+#[derive(Clone, Copy, Default)]
 pub struct Ray {
     orig: Point3,
     dir: Vec3,
     tm: f64,
+    differentials: Option<RayDifferentials>,
+    ignores_alpha: bool,
+}
+
+/// Rays through the next pixel to the right and the one below, which bound
+/// the area a camera ray covers for texture filtering.
+#[derive(Clone, Copy)]
+pub struct RayDifferentials {
+    pub rx_origin: Point3,
+    pub rx_direction: Vec3,
+    pub ry_origin: Point3,
+    pub ry_direction: Vec3,
 }
 
 impl Ray {
     pub fn new(origin: &Point3, direction: &Vec3) -> Self {
         Self {
-            orig: origin.clone(),
-            dir: direction.clone(),
+            orig: *origin,
+            dir: *direction,
             tm: 0.0,
+            differentials: None,
+            ignores_alpha: false,
         }
     }
 
@@ -21,9 +35,34 @@ impl Ray {
             orig: *origin,
             dir: *direction,
             tm: time,
+            differentials: None,
+            ignores_alpha: false,
+        }
+    }
+
+    /// The ray from `origin` along `direction` at the same time and alike in
+    /// seeing through cutouts, for intersecting in another object space.
+    pub fn transformed(&self, origin: &Point3, direction: &Vec3) -> Self {
+        Self {
+            orig: *origin,
+            dir: *direction,
+            tm: self.tm,
+            differentials: None,
+            ignores_alpha: self.ignores_alpha,
         }
     }
 
+    /// The ray hitting surfaces whole, without their alpha test, for queries
+    /// about the shapes themselves like light sampling and its pdf.
+    pub fn ignoring_alpha(mut self) -> Self {
+        self.ignores_alpha = true;
+        self
+    }
+
+    pub fn ignores_alpha(&self) -> bool {
+        self.ignores_alpha
+    }
+
     pub fn origin(&self) -> &Point3 {
         &self.orig
     }
@@ -36,6 +75,15 @@ impl Ray {
         self.tm
     }
 
+    /// Only camera rays have differentials, scattered rays don't.
+    pub fn differentials(&self) -> Option<&RayDifferentials> {
+        self.differentials.as_ref()
+    }
+
+    pub fn set_differentials(&mut self, differentials: &RayDifferentials) {
+        self.differentials = Some(*differentials);
+    }
+
     pub fn at(&self, t: f64) -> Point3 {
         self.orig + self.dir * t
     }
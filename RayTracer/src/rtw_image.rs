@@ -1,22 +1,31 @@
+use crate::texture_cache;
 use image::{DynamicImage, GenericImageView};
+use std::path::Path;
 
 pub struct RtwImage {
     data: Option<DynamicImage>,
 }
 
 impl RtwImage {
+    /// Opens `image_filename` from the first texture search path holding it.
     pub fn open(image_filename: &str) -> Self {
-        let path = String::from("assets/") + image_filename;
-        let data;
-        match image::open(&path) {
-            Ok(img) => {
-                data = Some(img);
+        match texture_cache::resolve(image_filename) {
+            Some(path) => Self::open_path(&path),
+            None => {
+                eprintln!("ERROR: Could not load image file '{}'.", image_filename);
+                Self { data: None }
             }
+        }
+    }
+
+    pub fn open_path(path: &Path) -> Self {
+        let data = match image::open(path) {
+            Ok(img) => Some(img),
             Err(_) => {
-                eprintln!("ERROR: Could not load image file '{}'.", path);
-                data = None;
+                eprintln!("ERROR: Could not load image file '{}'.", path.display());
+                None
             }
-        }
+        };
 
         Self { data }
     }
@@ -49,6 +58,19 @@ impl RtwImage {
             None => MAGENTA,
         }
     }
+
+    /// The opacity of pixel (`x`, `y`), 255 for images without alpha.
+    pub fn alpha(&self, mut x: u32, mut y: u32) -> u8 {
+        match &self.data {
+            Some(img) => {
+                x = clamp(x, 0, img.width());
+                y = clamp(y, 0, img.height());
+
+                img.get_pixel(x, y)[3]
+            }
+            None => 255,
+        }
+    }
 }
 
 fn clamp(x: u32, low: u32, high: u32) -> u32 {
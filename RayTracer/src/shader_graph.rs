@@ -0,0 +1,228 @@
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    texture::{ColorRamp, FractalNoise, ImageTexture, Texture, WorleyTexture},
+    vec3::Vec3,
+};
+
+/// Most nodes a graph may have, so that evaluating one needs no allocation.
+pub const MAX_NODES: usize = 64;
+
+/// Index of a node within its `ShaderGraph`.
+pub type NodeId = usize;
+
+/// Componentwise operations of a `Node::Math`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum MathOp {
+    Add,
+    Subtract,
+    Multiply,
+    /// zero where the divisor is
+    Divide,
+    Power,
+    Minimum,
+    Maximum,
+}
+
+/// A step of a shader, producing a color from the hit and the nodes before
+/// it. Inputs used as scalars are reduced to their luminance. The lookups are
+/// held in the node itself, so evaluating a graph makes no virtual calls.
+pub enum Node {
+    Constant(Color),
+    /// an image looked up over the pixel footprint
+    Image(ImageTexture),
+    /// boxed, as its permutation tables would make every node large
+    Noise(Box<FractalNoise>),
+    Worley(WorleyTexture),
+    /// 3D checker of cubes with side `scale`
+    Checker {
+        scale: f64,
+        even: Color,
+        odd: Color,
+    },
+    /// (u, v, 0)
+    Uv,
+    Position,
+    Normal,
+    /// the time of the ray, for animating a shader
+    Time,
+    Math {
+        op: MathOp,
+        a: NodeId,
+        b: NodeId,
+    },
+    /// `a` blended into `b` by `factor`
+    Mix {
+        a: NodeId,
+        b: NodeId,
+        factor: NodeId,
+    },
+    /// Schlick's reflectance of a dielectric of index `ior` toward the viewer
+    Fresnel {
+        ior: f64,
+    },
+    /// 0 where the surface faces the viewer, rising to 1 at grazing angles,
+    /// the rise pushed toward the edges by `blend` below 0.5 and inward above
+    LayerWeight {
+        blend: f64,
+    },
+    /// the luminance of `input` colored by interpolating between stops, held
+    /// beyond the ends
+    ColorRamp {
+        input: NodeId,
+        stops: Vec<(f64, Color)>,
+    },
+}
+
+/// Which parameter of the BSDF a node drives.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ShaderOutput {
+    Color,
+    Roughness,
+    Emission,
+}
+
+/// What a shader evaluates to at a hit.
+#[derive(Clone, Copy)]
+pub struct BsdfParams {
+    pub color: Color,
+    pub roughness: f64,
+    pub emission: Color,
+}
+
+/// A material description as a DAG of nodes, kept in the order they were
+/// added so that every input comes before the node that reads it.
+#[derive(Default)]
+pub struct ShaderGraph {
+    nodes: Vec<Node>,
+    color: Option<NodeId>,
+    roughness: Option<NodeId>,
+    emission: Option<NodeId>,
+}
+
+impl ShaderGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `node` and returns its id. Its inputs must already be in the
+    /// graph, which keeps it acyclic.
+    pub fn add(&mut self, mut node: Node) -> NodeId {
+        let id = self.nodes.len();
+        assert!(
+            id < MAX_NODES,
+            "shader graphs hold at most {} nodes",
+            MAX_NODES
+        );
+        let inputs = match &node {
+            Node::Math { a, b, .. } => vec![*a, *b],
+            Node::Mix { a, b, factor } => vec![*a, *b, *factor],
+            Node::ColorRamp { input, .. } => vec![*input],
+            _ => Vec::new(),
+        };
+        assert!(
+            inputs.iter().all(|&input| input < id),
+            "shader node inputs must be added before the node"
+        );
+
+        if let Node::ColorRamp { stops, .. } = &mut node {
+            stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        }
+        self.nodes.push(node);
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn set_output(&mut self, output: ShaderOutput, node: NodeId) {
+        assert!(node < self.nodes.len(), "shader output of a missing node");
+        match output {
+            ShaderOutput::Color => self.color = Some(node),
+            ShaderOutput::Roughness => self.roughness = Some(node),
+            ShaderOutput::Emission => self.emission = Some(node),
+        }
+    }
+
+    pub fn has_emission(&self) -> bool {
+        self.emission.is_some()
+    }
+
+    /// Runs the nodes at `rec`, seen from the unit direction `wo` pointing
+    /// away from the surface. Unconnected outputs are a light gray, smooth
+    /// and dark.
+    pub fn evaluate(&self, rec: &HitRecord, wo: &Vec3) -> BsdfParams {
+        let mut values = [Color::zeros(); MAX_NODES];
+        for (id, node) in self.nodes.iter().enumerate() {
+            values[id] = match node {
+                Node::Constant(c) => *c,
+                Node::Image(image) => image.value_at(rec),
+                Node::Noise(noise) => gray(noise.evaluate(&rec.p)),
+                Node::Worley(worley) => gray(worley.evaluate(&rec.p)),
+                Node::Checker { scale, even, odd } => {
+                    let cell = |x: f64| (x / scale).floor() as i64;
+                    if (cell(rec.p.x) + cell(rec.p.y) + cell(rec.p.z)) % 2 == 0 {
+                        *even
+                    } else {
+                        *odd
+                    }
+                }
+                Node::Uv => Color::new(rec.u, rec.v, 0.0),
+                Node::Position => rec.p,
+                Node::Normal => rec.normal,
+                Node::Time => gray(rec.time),
+                Node::Math { op, a, b } => math(*op, &values[*a], &values[*b]),
+                Node::Mix { a, b, factor } => {
+                    let t = values[*factor].luminance().clamp(0.0, 1.0);
+                    values[*a] * (1.0 - t) + values[*b] * t
+                }
+                Node::Fresnel { ior } => {
+                    let cosine = (*wo * rec.normal).abs().min(1.0);
+                    let r0 = ((1.0 - ior) / (1.0 + ior)).powi(2);
+                    gray(r0 + (1.0 - r0) * (1.0 - cosine).powi(5))
+                }
+                Node::LayerWeight { blend } => {
+                    let facing = 1.0 - (*wo * rec.normal).abs().min(1.0);
+                    let blend = blend.clamp(0.0, 1.0 - 1e-5);
+                    let exponent = if blend < 0.5 {
+                        2.0 * blend
+                    } else {
+                        0.5 / (1.0 - blend)
+                    };
+                    gray(facing.powf(exponent))
+                }
+                Node::ColorRamp { input, stops } => {
+                    let x = values[*input].luminance();
+                    ColorRamp::interpolate(stops, x).unwrap_or(gray(x))
+                }
+            };
+        }
+
+        BsdfParams {
+            color: self.color.map_or(gray(0.8), |id| values[id]),
+            roughness: self
+                .roughness
+                .map_or(0.0, |id| values[id].luminance().clamp(0.0, 1.0)),
+            emission: self.emission.map_or(Color::zeros(), |id| values[id]),
+        }
+    }
+}
+
+/// The color with all three components `value`.
+pub fn gray(value: f64) -> Color {
+    Color::new(value, value, value)
+}
+
+fn math(op: MathOp, a: &Color, b: &Color) -> Color {
+    let apply = |f: fn(f64, f64) -> f64| Color::new(f(a.x, b.x), f(a.y, b.y), f(a.z, b.z));
+    match op {
+        MathOp::Add => *a + *b,
+        MathOp::Subtract => *a - *b,
+        MathOp::Multiply => a.elemul(b),
+        MathOp::Divide => apply(|a, b| if b == 0.0 { 0.0 } else { a / b }),
+        MathOp::Power => apply(|a, b| a.max(0.0).powf(b)),
+        MathOp::Minimum => apply(f64::min),
+        MathOp::Maximum => apply(f64::max),
+    }
+}
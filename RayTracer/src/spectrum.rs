@@ -0,0 +1,309 @@
+use crate::{color::Color, color_space, rtweekend};
+use std::{
+    fs,
+    ops::{Add, AddAssign, Div, Index, Mul, MulAssign},
+};
+
+pub const LAMBDA_MIN: f64 = 360.0;
+pub const LAMBDA_MAX: f64 = 830.0;
+/// Wavelengths carried by a path, the hero wavelength and evenly spaced
+/// companions.
+pub const N_SPECTRUM_SAMPLES: usize = 4;
+
+/// Integral of the y color matching function over the visible range, which
+/// makes a constant spectrum of 1 have a luminance of 1.
+const CIE_Y_INTEGRAL: f64 = 106.922;
+/// CIE XYZ to linear sRGB, white balanced so that the constant spectrum, the
+/// white of `SampledSpectrum::from_rgb`, maps to (1, 1, 1).
+const XYZ_TO_RGB: [[f64; 3]; 3] = [
+    [2.6997755, -1.2806627, -0.4153501],
+    [-1.0206034, 1.9753741, 0.0437570],
+    [0.0612613, -0.2246249, 1.1639653],
+];
+/// Centers and half widths of the smooth steps between the blue, green and
+/// red basis spectra of `from_rgb`, fitted so that RGB colors come back
+/// within a few percent.
+const BLUE_GREEN_EDGE: f64 = 488.0;
+const GREEN_RED_EDGE: f64 = 587.0;
+const EDGE_HALF_WIDTH: f64 = 35.0;
+
+/// The wavelengths a spectral path is traced at, in nanometers, with their
+/// sampling densities.
+#[derive(Clone, Copy)]
+pub struct SampledWavelengths {
+    lambda: [f64; N_SPECTRUM_SAMPLES],
+    pdf: [f64; N_SPECTRUM_SAMPLES],
+}
+
+impl SampledWavelengths {
+    /// Hero wavelength sampling (Wilkie et al. 2014): one wavelength drawn
+    /// where the eye is sensitive, and the others rotated from it through the
+    /// visible range.
+    pub fn sample_visible() -> Self {
+        let u = rtweekend::random_double();
+        let mut lambda = [0.0; N_SPECTRUM_SAMPLES];
+        let mut pdf = [0.0; N_SPECTRUM_SAMPLES];
+        for i in 0..N_SPECTRUM_SAMPLES {
+            let up = (u + i as f64 / N_SPECTRUM_SAMPLES as f64).fract();
+            lambda[i] = sample_visible_wavelength(up);
+            pdf[i] = visible_wavelength_pdf(lambda[i]);
+        }
+        Self { lambda, pdf }
+    }
+
+    pub fn hero(&self) -> f64 {
+        self.lambda[0]
+    }
+
+    /// Keeps only the hero wavelength, for paths whose direction depends on
+    /// it, like refraction through dispersive glass.
+    pub fn terminate_secondary(&mut self) {
+        if self.is_secondary_terminated() {
+            return;
+        }
+        for pdf in &mut self.pdf[1..] {
+            *pdf = 0.0;
+        }
+        self.pdf[0] /= N_SPECTRUM_SAMPLES as f64;
+    }
+
+    pub fn is_secondary_terminated(&self) -> bool {
+        self.pdf[1..].iter().all(|&pdf| pdf == 0.0)
+    }
+}
+
+impl Index<usize> for SampledWavelengths {
+    type Output = f64;
+
+    fn index(&self, index: usize) -> &f64 {
+        &self.lambda[index]
+    }
+}
+
+/// Values of a spectrum at the wavelengths of a path.
+#[derive(Clone, Copy, PartialEq)]
+pub struct SampledSpectrum {
+    values: [f64; N_SPECTRUM_SAMPLES],
+}
+
+impl SampledSpectrum {
+    pub fn new(value: f64) -> Self {
+        Self {
+            values: [value; N_SPECTRUM_SAMPLES],
+        }
+    }
+
+    pub fn zeros() -> Self {
+        Self::new(0.0)
+    }
+
+    pub fn ones() -> Self {
+        Self::new(1.0)
+    }
+
+    pub fn from_fn(f: impl Fn(usize) -> f64) -> Self {
+        Self {
+            values: std::array::from_fn(f),
+        }
+    }
+
+    /// Upsamples a working space reflectance or emission to a smooth
+    /// spectrum, the sum of three overlapping bands weighted by the Rec.709
+    /// channels. The bands add up to 1, so white stays flat and reflectances
+    /// stay within [0, 1].
+    pub fn from_rgb(rgb: &Color, lambda: &SampledWavelengths) -> Self {
+        let rgb = color_space::to_rec709(rgb);
+        Self::from_fn(|i| {
+            let red = smooth_step((lambda[i] - GREEN_RED_EDGE) / EDGE_HALF_WIDTH);
+            let blue = 1.0 - smooth_step((lambda[i] - BLUE_GREEN_EDGE) / EDGE_HALF_WIDTH);
+            rgb.x * red + rgb.y * (1.0 - red - blue) + rgb.z * blue
+        })
+    }
+
+    pub fn is_black(&self) -> bool {
+        self.values.iter().all(|&value| value == 0.0)
+    }
+
+    pub fn max_component(&self) -> f64 {
+        self.values.iter().copied().fold(f64::MIN, f64::max)
+    }
+
+    /// The working space estimate of a path carrying this spectrum at
+    /// `lambda`, through the CIE XYZ color matching functions.
+    pub fn to_rgb(self, lambda: &SampledWavelengths) -> Color {
+        let mut xyz = Color::zeros();
+        for i in 0..N_SPECTRUM_SAMPLES {
+            if lambda.pdf[i] != 0.0 {
+                xyz += cie_xyz(lambda[i]) * (self.values[i] / lambda.pdf[i]);
+            }
+        }
+        xyz = xyz / (N_SPECTRUM_SAMPLES as f64 * CIE_Y_INTEGRAL);
+
+        let [r, g, b] = XYZ_TO_RGB.map(|row| row[0] * xyz.x + row[1] * xyz.y + row[2] * xyz.z);
+        color_space::from_rec709(&Color::new(r, g, b))
+    }
+}
+
+impl Index<usize> for SampledSpectrum {
+    type Output = f64;
+
+    fn index(&self, index: usize) -> &f64 {
+        &self.values[index]
+    }
+}
+
+impl Add for SampledSpectrum {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::from_fn(|i| self.values[i] + rhs.values[i])
+    }
+}
+
+impl AddAssign for SampledSpectrum {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Mul for SampledSpectrum {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::from_fn(|i| self.values[i] * rhs.values[i])
+    }
+}
+
+impl Mul<f64> for SampledSpectrum {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self {
+        Self::from_fn(|i| self.values[i] * rhs)
+    }
+}
+
+impl MulAssign for SampledSpectrum {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl MulAssign<f64> for SampledSpectrum {
+    fn mul_assign(&mut self, rhs: f64) {
+        *self = *self * rhs;
+    }
+}
+
+impl Div<f64> for SampledSpectrum {
+    type Output = Self;
+
+    fn div(self, rhs: f64) -> Self {
+        Self::from_fn(|i| self.values[i] / rhs)
+    }
+}
+
+/// A spectrum given at increasing wavelengths, linear in between and held
+/// past the ends, like measured optical constants.
+pub struct PiecewiseLinearSpectrum {
+    lambdas: Vec<f64>,
+    values: Vec<f64>,
+}
+
+impl PiecewiseLinearSpectrum {
+    /// From wavelength and value pairs, sorted by wavelength.
+    pub fn new(samples: &[(f64, f64)]) -> Self {
+        let mut samples = samples.to_vec();
+        samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            lambdas: samples.iter().map(|sample| sample.0).collect(),
+            values: samples.iter().map(|sample| sample.1).collect(),
+        }
+    }
+
+    /// Loads a text file from the assets directory with a wavelength in
+    /// nanometers and a value on every line, `#` starting a comment.
+    pub fn load(filename: &str) -> Self {
+        let path = String::from("assets/") + filename;
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => {
+                eprintln!("ERROR: Could not load spectrum file '{}'.", path);
+                return Self::new(&[]);
+            }
+        };
+
+        let samples: Vec<_> = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap())
+            .filter_map(|line| {
+                let values: Vec<f64> = line
+                    .split_whitespace()
+                    .filter_map(|value| value.parse().ok())
+                    .collect();
+                match values[..] {
+                    [lambda, value] => Some((lambda, value)),
+                    _ => None,
+                }
+            })
+            .collect();
+        Self::new(&samples)
+    }
+
+    pub fn evaluate(&self, lambda: f64) -> f64 {
+        let n = self.lambdas.len();
+        if n == 0 {
+            return 0.0;
+        }
+        if lambda <= self.lambdas[0] {
+            return self.values[0];
+        }
+        if lambda >= self.lambdas[n - 1] {
+            return self.values[n - 1];
+        }
+
+        let k = self.lambdas.partition_point(|&l| l <= lambda) - 1;
+        let t = (lambda - self.lambdas[k]) / (self.lambdas[k + 1] - self.lambdas[k]);
+        self.values[k] + (self.values[k + 1] - self.values[k]) * t
+    }
+
+    pub fn sample(&self, lambda: &SampledWavelengths) -> SampledSpectrum {
+        SampledSpectrum::from_fn(|i| self.evaluate(lambda[i]))
+    }
+}
+
+/// The CIE 1931 color matching functions, from the multi-lobe fit of Wyman,
+/// Sloan and Shirley 2013.
+fn cie_xyz(lambda: f64) -> Color {
+    let g = |mu: f64, sigma_below: f64, sigma_above: f64| {
+        let sigma = if lambda < mu {
+            sigma_below
+        } else {
+            sigma_above
+        };
+        (-0.5 * ((lambda - mu) / sigma).powi(2)).exp()
+    };
+    Color::new(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+/// Samples wavelengths roughly in proportion to the eye's sensitivity
+/// (pbrt-v4).
+fn sample_visible_wavelength(u: f64) -> f64 {
+    538.0 - 138.888889 * (0.85691062 - 1.82750197 * u).atanh()
+}
+
+fn visible_wavelength_pdf(lambda: f64) -> f64 {
+    if !(LAMBDA_MIN..=LAMBDA_MAX).contains(&lambda) {
+        return 0.0;
+    }
+    0.0039398042 / (0.0072 * (lambda - 538.0)).cosh().powi(2)
+}
+
+/// 0 below -1, 1 above 1 and smooth in between.
+fn smooth_step(x: f64) -> f64 {
+    let t = ((x + 1.0) / 2.0).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
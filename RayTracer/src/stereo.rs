@@ -0,0 +1,61 @@
+/// How the views of both eyes are packed into one image.
+#[derive(Clone, Copy, PartialEq)]
+pub enum StereoLayout {
+    /// left eye on the left half
+    SideBySide,
+    /// left eye on the top half, the usual layout of stereo panoramas
+    OverUnder,
+}
+
+/// Two eyes side by side along the camera's `u` axis, rendered together.
+///
+/// Perspective cameras get parallel eyes with shifted image planes meeting at
+/// the convergence distance, which keeps vertical parallax out. Panoramic and
+/// fisheye projections become omni-directional stereo, with the eyes on a
+/// circle so that every direction is seen by a pair of eyes looking along it.
+#[derive(Clone, Copy)]
+pub struct StereoRig {
+    /// distance between the eyes in scene units
+    pub interocular: f64,
+    /// distance of the plane appearing at screen depth, infinite for parallel
+    /// views
+    pub convergence: f64,
+    pub layout: StereoLayout,
+}
+
+impl StereoRig {
+    pub fn new(interocular: f64, convergence: f64, layout: StereoLayout) -> Self {
+        Self {
+            interocular,
+            convergence,
+            layout,
+        }
+    }
+
+    /// Size of the packed image of two eyes with images of `width` by `height`.
+    pub fn image_size(&self, width: u32, height: u32) -> (u32, u32) {
+        match self.layout {
+            StereoLayout::SideBySide => (2 * width, height),
+            StereoLayout::OverUnder => (width, 2 * height),
+        }
+    }
+
+    /// Turns position (`x`, `y`) of the packed image into one within the
+    /// image of an eye of `width` by `height`, and the offset of that eye
+    /// from the camera center along `u`.
+    pub fn eye_position(&self, x: f64, y: f64, width: u32, height: u32) -> (f64, f64, f64) {
+        let half = self.interocular / 2.0;
+        match self.layout {
+            StereoLayout::SideBySide if x >= width as f64 => (x - width as f64, y, half),
+            StereoLayout::OverUnder if y >= height as f64 => (x, y - height as f64, half),
+            _ => (x, y, -half),
+        }
+    }
+
+    /// How far the image plane of an eye at `eye_offset` moves along `u` at
+    /// `focus_dist`, so both eyes see the same point at the convergence
+    /// distance.
+    pub fn image_shift(&self, eye_offset: f64, focus_dist: f64) -> f64 {
+        eye_offset * (1.0 - focus_dist / self.convergence)
+    }
+}
@@ -0,0 +1,77 @@
+use super::{solid_color::SolidColor, Texture};
+use crate::{color::Color, hittable::HitRecord, vec3::Point3};
+use std::sync::Arc;
+
+/// Running bond brickwork in texture space, every other row offset by half a
+/// brick, with `brick` and `mortar` looked up inside and between the bricks.
+pub struct BrickTexture {
+    /// size of a brick and its share of the mortar, in uv units
+    brick_width: f64,
+    brick_height: f64,
+    /// width of the joints, in uv units
+    mortar_width: f64,
+    brick: Arc<dyn Texture>,
+    mortar: Arc<dyn Texture>,
+}
+
+impl BrickTexture {
+    pub fn new(
+        brick_width: f64,
+        brick_height: f64,
+        mortar_width: f64,
+        brick: &Arc<dyn Texture>,
+        mortar: &Arc<dyn Texture>,
+    ) -> Self {
+        Self {
+            brick_width,
+            brick_height,
+            mortar_width,
+            brick: brick.clone(),
+            mortar: mortar.clone(),
+        }
+    }
+
+    pub fn from_colors(
+        brick_width: f64,
+        brick_height: f64,
+        mortar_width: f64,
+        brick: &Color,
+        mortar: &Color,
+    ) -> Self {
+        Self::new(
+            brick_width,
+            brick_height,
+            mortar_width,
+            &(Arc::new(SolidColor::new(brick)) as Arc<dyn Texture>),
+            &(Arc::new(SolidColor::new(mortar)) as Arc<dyn Texture>),
+        )
+    }
+
+    fn pick(&self, u: f64, v: f64) -> &Arc<dyn Texture> {
+        let row = (v / self.brick_height).floor();
+        let shift = if (row as i64).rem_euclid(2) == 0 {
+            0.0
+        } else {
+            0.5
+        };
+        let s = (u / self.brick_width + shift).rem_euclid(1.0) * self.brick_width;
+        let t = v.rem_euclid(self.brick_height);
+
+        // the joint at the low edges of each brick
+        if s < self.mortar_width || t < self.mortar_width {
+            &self.mortar
+        } else {
+            &self.brick
+        }
+    }
+}
+
+impl Texture for BrickTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.pick(u, v).value(u, v, p)
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        self.pick(rec.u, rec.v).value_at(rec)
+    }
+}
@@ -1,5 +1,5 @@
 use super::{solid_color::SolidColor, Texture};
-use crate::{color::Color, vec3::Point3};
+use crate::{color::Color, hittable::HitRecord, vec3::Point3};
 use std::sync::Arc;
 
 pub struct CheckerTexture {
@@ -24,18 +24,26 @@ impl CheckerTexture {
             odd: Arc::new(SolidColor::new(c2)),
         }
     }
-}
 
-impl Texture for CheckerTexture {
-    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+    fn pick(&self, p: &Point3) -> &Arc<dyn Texture> {
         let x_integer = (self.inv_scale * p.x).floor() as i64;
         let y_integer = (self.inv_scale * p.y).floor() as i64;
         let z_integer = (self.inv_scale * p.z).floor() as i64;
 
         if (x_integer + y_integer + z_integer) % 2 == 0 {
-            self.even.value(u, v, p)
+            &self.even
         } else {
-            self.odd.value(u, v, p)
+            &self.odd
         }
     }
 }
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.pick(p).value(u, v, p)
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        self.pick(&rec.p).value_at(rec)
+    }
+}
@@ -0,0 +1,94 @@
+use super::{ImageTexture, Texture};
+use crate::{color::Color, hittable::HitRecord, vec3::Point3};
+use std::sync::Arc;
+
+/// A sequence of frames played over the time of the rays, each shown for
+/// `frame_duration` from time 0 on and looping after the last, so that
+/// motion blurred renders blend the frames the shutter spans. Rendered
+/// animations take one unit of time per frame.
+pub struct FlipbookTexture {
+    frames: Vec<Arc<dyn Texture>>,
+    frame_duration: f64,
+}
+
+impl FlipbookTexture {
+    pub fn new(frames: &[Arc<dyn Texture>], frame_duration: f64) -> Self {
+        assert!(!frames.is_empty(), "a flipbook needs at least one frame");
+        assert!(
+            frame_duration > 0.0,
+            "a flipbook frame must last a positive time"
+        );
+        Self {
+            frames: frames.to_vec(),
+            frame_duration,
+        }
+    }
+
+    /// Loads frames `first` to `first + count - 1` of an image sequence whose
+    /// filenames are `pattern` with its run of `#` replaced by the zero padded
+    /// frame number, like `fire.####.png`.
+    pub fn from_sequence(pattern: &str, first: u32, count: u32, frame_duration: f64) -> Self {
+        let digits = pattern.chars().filter(|&c| c == '#').count();
+        let run = "#".repeat(digits);
+        assert!(
+            digits > 0 && pattern.contains(&run),
+            "image sequence pattern '{}' needs one run of '#' for the frame number",
+            pattern
+        );
+        let frames: Vec<Arc<dyn Texture>> = (first..first + count.max(1))
+            .map(|frame| {
+                let filename = pattern.replacen(&run, &format!("{:0digits$}", frame), 1);
+                Arc::new(ImageTexture::new(&filename)) as Arc<dyn Texture>
+            })
+            .collect();
+        Self::new(&frames, frame_duration)
+    }
+
+    fn frame(&self, time: f64) -> &Arc<dyn Texture> {
+        let index = (time / self.frame_duration).floor() as i64;
+        &self.frames[index.rem_euclid(self.frames.len() as i64) as usize]
+    }
+}
+
+impl Texture for FlipbookTexture {
+    /// The first frame.
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.frames[0].value(u, v, p)
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        self.frame(rec.time).value_at(rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::SolidColor;
+
+    #[test]
+    fn test_frames_follow_time() {
+        let frames: Vec<Arc<dyn Texture>> = (0..3)
+            .map(|i| Arc::new(SolidColor::new(&Color::new(i as f64, 0.0, 0.0))) as Arc<dyn Texture>)
+            .collect();
+        let flipbook = FlipbookTexture::new(&frames, 0.5);
+        let mut rec = HitRecord::default();
+        for (time, frame) in [(0.2, 0.0), (0.7, 1.0), (1.2, 2.0), (1.6, 0.0), (-0.2, 2.0)] {
+            rec.time = time;
+            assert_eq!(flipbook.value_at(&rec).x, frame);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_pattern_needs_frame_number() {
+        FlipbookTexture::from_sequence("fire.png", 1, 2, 1.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_frames_need_duration() {
+        let frame = Arc::new(SolidColor::new(&Color::zeros())) as Arc<dyn Texture>;
+        FlipbookTexture::new(&[frame], 0.0);
+    }
+}
@@ -0,0 +1,91 @@
+use super::{perlin::Perlin, Texture};
+use crate::{color::Color, vec3::Point3};
+
+/// How the octaves of a `FractalNoise` are combined.
+#[derive(Clone, Copy, PartialEq)]
+pub enum NoiseKind {
+    /// fractional Brownian motion, the plain sum, centered on 0.5
+    Fbm,
+    /// the sum of absolute values, with creases where the noise crosses zero
+    Turbulence,
+    /// inverted turbulence, squared, with each octave weighted by the last,
+    /// for sharp ridges like mountain ranges (Musgrave)
+    Ridged,
+}
+
+/// Perlin noise summed over octaves of rising frequency and falling
+/// amplitude, as a gray value in [0, 1].
+pub struct FractalNoise {
+    noise: Perlin,
+    kind: NoiseKind,
+    scale: f64,
+    octaves: u32,
+    /// frequency and amplitude ratios between successive octaves
+    lacunarity: f64,
+    gain: f64,
+}
+
+impl FractalNoise {
+    /// Octaves doubling in frequency and halving in amplitude.
+    pub fn new(kind: NoiseKind, scale: f64, octaves: u32) -> Self {
+        Self::new_with_falloff(kind, scale, octaves, 2.0, 0.5)
+    }
+
+    pub fn new_with_falloff(
+        kind: NoiseKind,
+        scale: f64,
+        octaves: u32,
+        lacunarity: f64,
+        gain: f64,
+    ) -> Self {
+        Self {
+            noise: Perlin::new(),
+            kind,
+            scale,
+            octaves: octaves.max(1),
+            lacunarity,
+            gain,
+        }
+    }
+
+    /// The noise at `p`, in [0, 1].
+    pub fn evaluate(&self, p: &Point3) -> f64 {
+        let mut p = *p * self.scale;
+        let mut amplitude = 1.0;
+        let mut total_amplitude = 0.0;
+        let mut sum = 0.0;
+        // previous ridge, which sharpens the next octave
+        let mut ridge_weight = 1.0;
+
+        for _ in 0..self.octaves {
+            let n = self.noise.noise(&p);
+            sum += amplitude
+                * match self.kind {
+                    NoiseKind::Fbm => n,
+                    NoiseKind::Turbulence => n.abs(),
+                    NoiseKind::Ridged => {
+                        let ridge = (1.0 - n.abs()).powi(2) * ridge_weight;
+                        ridge_weight = ridge.clamp(0.0, 1.0);
+                        ridge
+                    }
+                };
+            total_amplitude += amplitude;
+            amplitude *= self.gain;
+            p *= self.lacunarity;
+        }
+
+        let value = sum / total_amplitude;
+        match self.kind {
+            NoiseKind::Fbm => 0.5 + 0.5 * value,
+            NoiseKind::Turbulence | NoiseKind::Ridged => value,
+        }
+        .clamp(0.0, 1.0)
+    }
+}
+
+impl Texture for FractalNoise {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let value = self.evaluate(p);
+        Color::new(value, value, value)
+    }
+}
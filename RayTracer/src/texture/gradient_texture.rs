@@ -0,0 +1,46 @@
+use super::Texture;
+use crate::{
+    color::Color,
+    vec3::{Point3, Vec3},
+};
+
+/// What a `GradientTexture` ramps along.
+#[derive(Clone, Copy)]
+pub enum Gradient {
+    /// the u texture coordinate
+    U,
+    /// the v texture coordinate
+    V,
+    /// from 0 at `origin` to 1 at `origin + direction`
+    Linear { origin: Point3, direction: Vec3 },
+    /// from 1 at `center` to 0 at `radius` away
+    Radial { center: Point3, radius: f64 },
+}
+
+/// A gray ramp in [0, 1], meant to drive a `ColorRamp` or `MixTexture`.
+pub struct GradientTexture {
+    gradient: Gradient,
+}
+
+impl GradientTexture {
+    pub fn new(gradient: &Gradient) -> Self {
+        Self {
+            gradient: *gradient,
+        }
+    }
+}
+
+impl Texture for GradientTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let value = match self.gradient {
+            Gradient::U => u,
+            Gradient::V => v,
+            Gradient::Linear { origin, direction } => {
+                (*p - origin) * direction / direction.squared_length()
+            }
+            Gradient::Radial { center, radius } => 1.0 - (*p - center).length() / radius,
+        }
+        .clamp(0.0, 1.0);
+        Color::new(value, value, value)
+    }
+}
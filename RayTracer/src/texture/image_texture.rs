@@ -1,36 +1,76 @@
 use super::Texture;
-use crate::{color::Color, interval::Interval, rtw_image::RtwImage, vec3::Point3};
+use crate::{
+    color::Color,
+    color_space::ColorEncoding,
+    hittable::HitRecord,
+    mipmap::{MipMap, TextureFilter, WrapMode},
+    texture_cache,
+    vec3::{Point3, Vec3},
+};
 
 pub struct ImageTexture {
-    image: RtwImage,
+    mipmap: MipMap,
 }
 
 impl ImageTexture {
+    /// An sRGB color image.
     pub fn new(filename: &str) -> Self {
+        Self::new_with_encoding(filename, ColorEncoding::Srgb)
+    }
+
+    /// An image stored as `encoding`, like `ColorEncoding::Raw` for normal or
+    /// roughness maps.
+    pub fn new_with_encoding(filename: &str, encoding: ColorEncoding) -> Self {
+        Self::new_with_sampling(
+            filename,
+            encoding,
+            TextureFilter::Trilinear,
+            WrapMode::Clamp,
+        )
+    }
+
+    /// Samples the image through the texture cache, sharing its texels with
+    /// every other texture of the same file, with `filter` unless the command
+    /// line picked another.
+    pub fn new_with_sampling(
+        filename: &str,
+        encoding: ColorEncoding,
+        filter: TextureFilter,
+        wrap: WrapMode,
+    ) -> Self {
+        let pyramid = texture_cache::load(filename, encoding).unwrap_or_default();
+        let filter = texture_cache::filter_override().unwrap_or(filter);
         Self {
-            image: RtwImage::open(filename),
+            mipmap: MipMap::new(&pyramid, filter, wrap),
         }
     }
+
+    /// Looks up (`u`, `v`) with the given change of u and v to the
+    /// neighbouring pixels.
+    fn lookup(&self, u: f64, v: f64, duvdx: &Vec3, duvdy: &Vec3) -> Color {
+        if self.mipmap.is_empty() {
+            return Color::new(0.0, 1.0, 1.0);
+        }
+
+        // images are stored from the top row down
+        let (s, t) = (u, 1.0 - v);
+        let dst0 = Vec3::new(duvdx.x, -duvdx.y, 0.0);
+        let dst1 = Vec3::new(duvdy.x, -duvdy.y, 0.0);
+        self.mipmap.lookup(s, t, &dst0, &dst1)
+    }
 }
 
 impl Texture for ImageTexture {
-    fn value(&self, mut u: f64, mut v: f64, _p: &Point3) -> Color {
-        if self.image.height() == 0 {
-            Color::new(0.0, 1.0, 1.0)
-        } else {
-            u = Interval::new(0.0, 1.0).clamp(u);
-            v = 1.0 - Interval::new(0.0, 1.0).clamp(v);
-
-            let i = (u * self.image.width() as f64) as u32;
-            let j = (v * self.image.height() as f64) as u32;
-            let pixel = self.image.pixel_data(i, j);
-
-            let color_scale = 1.0 / 255.0;
-            Color::new(
-                color_scale * pixel[0] as f64,
-                color_scale * pixel[1] as f64,
-                color_scale * pixel[2] as f64,
-            )
-        }
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        self.lookup(u, v, &Vec3::zeros(), &Vec3::zeros())
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        self.lookup(
+            rec.u,
+            rec.v,
+            &Vec3::new(rec.dudx, rec.dvdx, 0.0),
+            &Vec3::new(rec.dudy, rec.dvdy, 0.0),
+        )
     }
 }
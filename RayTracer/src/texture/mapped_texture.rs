@@ -0,0 +1,210 @@
+use super::Texture;
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    vec3::{Point3, Vec3},
+};
+use std::{f64::consts::PI, sync::Arc};
+
+/// Where texture coordinates come from.
+#[derive(Clone, Copy)]
+pub enum TextureMapping {
+    /// the (u, v) of the primitive
+    Uv,
+    /// distances of the world position from `origin` along `s_axis` and
+    /// `t_axis`, whose lengths set how often the texture repeats
+    Planar {
+        origin: Point3,
+        s_axis: Vec3,
+        t_axis: Vec3,
+    },
+    /// longitude and latitude around `center`, like the UVs of a sphere
+    Spherical { center: Point3 },
+    /// angle around the vertical axis through `center`, and height above it
+    Cylindrical { center: Point3 },
+    /// planar projections along the x, y and z axes blended by how much the
+    /// normal faces each of them, raised to `sharpness`, for texturing
+    /// geometry without UVs
+    Triplanar { sharpness: f64 },
+}
+
+/// Scales, then rotates (counterclockwise, in degrees) and offsets texture
+/// coordinates.
+#[derive(Clone, Copy)]
+pub struct UvTransform {
+    pub scale: (f64, f64),
+    pub rotation: f64,
+    pub offset: (f64, f64),
+}
+
+impl Default for UvTransform {
+    fn default() -> Self {
+        Self {
+            scale: (1.0, 1.0),
+            rotation: 0.0,
+            offset: (0.0, 0.0),
+        }
+    }
+}
+
+impl UvTransform {
+    pub fn new(scale: (f64, f64), rotation: f64, offset: (f64, f64)) -> Self {
+        Self {
+            scale,
+            rotation,
+            offset,
+        }
+    }
+
+    /// Transforms a coordinate pair, and a change of it with `translate` false.
+    fn apply(&self, u: f64, v: f64, translate: bool) -> (f64, f64) {
+        let (u, v) = (u * self.scale.0, v * self.scale.1);
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let (u, v) = (cos * u - sin * v, sin * u + cos * v);
+        if translate {
+            (u + self.offset.0, v + self.offset.1)
+        } else {
+            (u, v)
+        }
+    }
+}
+
+/// Looks up another texture at texture coordinates of its own, so tiled
+/// floors and walls don't need custom primitives.
+pub struct MappedTexture {
+    tex: Arc<dyn Texture>,
+    mapping: TextureMapping,
+    transform: UvTransform,
+}
+
+impl MappedTexture {
+    pub fn new(tex: &Arc<dyn Texture>, mapping: &TextureMapping, transform: &UvTransform) -> Self {
+        Self {
+            tex: tex.clone(),
+            mapping: *mapping,
+            transform: *transform,
+        }
+    }
+
+    /// `tex` with its UVs scaled, rotated and offset.
+    pub fn from_transform(tex: &Arc<dyn Texture>, transform: &UvTransform) -> Self {
+        Self::new(tex, &TextureMapping::Uv, transform)
+    }
+
+    /// Looks up `tex` at the transformed (`u`, `v`), with the derivatives of
+    /// `rec` replaced by `duvdx` and `duvdy`.
+    fn lookup(
+        &self,
+        rec: &HitRecord,
+        u: f64,
+        v: f64,
+        duvdx: (f64, f64),
+        duvdy: (f64, f64),
+    ) -> Color {
+        let mut mapped = rec.clone();
+        (mapped.u, mapped.v) = self.transform.apply(u, v, true);
+        (mapped.dudx, mapped.dvdx) = self.transform.apply(duvdx.0, duvdx.1, false);
+        (mapped.dudy, mapped.dvdy) = self.transform.apply(duvdy.0, duvdy.1, false);
+        self.tex.value_at(&mapped)
+    }
+
+    /// Coordinates of the spherical and cylindrical mappings at `p`.
+    fn angular_uv(&self, p: &Point3) -> (f64, f64) {
+        match self.mapping {
+            TextureMapping::Spherical { center } => {
+                let d = (*p - center).unit();
+                let theta = (-d.y).clamp(-1.0, 1.0).acos();
+                let phi = (-d.z).atan2(d.x) + PI;
+                (phi / (2.0 * PI), theta / PI)
+            }
+            TextureMapping::Cylindrical { center } => {
+                let d = *p - center;
+                let phi = (-d.z).atan2(d.x) + PI;
+                (phi / (2.0 * PI), d.y)
+            }
+            _ => (0.0, 0.0),
+        }
+    }
+
+    /// Change of the angular coordinates along `dp`, by forward differences
+    /// (pbrt), across the seam where u wraps around the short way.
+    fn angular_derivative(&self, p: &Point3, uv: (f64, f64), dp: &Vec3) -> (f64, f64) {
+        const DELTA: f64 = 0.1;
+        let moved = self.angular_uv(&(*p + *dp * DELTA));
+        let mut du = (moved.0 - uv.0) / DELTA;
+        let dv = (moved.1 - uv.1) / DELTA;
+        if du > 0.5 / DELTA {
+            du -= 1.0 / DELTA;
+        } else if du < -0.5 / DELTA {
+            du += 1.0 / DELTA;
+        }
+        (du, dv)
+    }
+}
+
+impl Texture for MappedTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.value_at(&HitRecord {
+            p: *p,
+            u,
+            v,
+            ..Default::default()
+        })
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        match self.mapping {
+            TextureMapping::Uv => self.lookup(
+                rec,
+                rec.u,
+                rec.v,
+                (rec.dudx, rec.dvdx),
+                (rec.dudy, rec.dvdy),
+            ),
+            TextureMapping::Planar {
+                origin,
+                s_axis,
+                t_axis,
+            } => {
+                let d = rec.p - origin;
+                self.lookup(
+                    rec,
+                    d * s_axis,
+                    d * t_axis,
+                    (rec.dpdx * s_axis, rec.dpdx * t_axis),
+                    (rec.dpdy * s_axis, rec.dpdy * t_axis),
+                )
+            }
+            TextureMapping::Spherical { .. } | TextureMapping::Cylindrical { .. } => {
+                let (u, v) = self.angular_uv(&rec.p);
+                let duvdx = self.angular_derivative(&rec.p, (u, v), &rec.dpdx);
+                let duvdy = self.angular_derivative(&rec.p, (u, v), &rec.dpdy);
+                self.lookup(rec, u, v, duvdx, duvdy)
+            }
+            TextureMapping::Triplanar { sharpness } => {
+                let n = rec.normal;
+                let mut weights = [n.x, n.y, n.z].map(|c| c.abs().powf(sharpness));
+                let sum: f64 = weights.iter().sum();
+                if sum > 0.0 {
+                    weights = weights.map(|w| w / sum);
+                } else {
+                    weights = [1.0 / 3.0; 3];
+                }
+
+                // the two world axes each projection keeps
+                let (p, dx, dy) = (rec.p, rec.dpdx, rec.dpdy);
+                let projections = [
+                    ((p.z, p.y), (dx.z, dx.y), (dy.z, dy.y)),
+                    ((p.x, p.z), (dx.x, dx.z), (dy.x, dy.z)),
+                    ((p.x, p.y), (dx.x, dx.y), (dy.x, dy.y)),
+                ];
+                weights
+                    .iter()
+                    .zip(projections)
+                    .filter(|(w, _)| **w > 0.0)
+                    .map(|(w, (uv, duvdx, duvdy))| self.lookup(rec, uv.0, uv.1, duvdx, duvdy) * *w)
+                    .fold(Color::zeros(), |sum, c| sum + c)
+            }
+        }
+    }
+}
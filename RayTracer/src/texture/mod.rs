@@ -1,15 +1,42 @@
+mod brick_texture;
 mod checker_texture;
+mod flipbook_texture;
+mod fractal_noise;
+mod gradient_texture;
 mod image_texture;
+mod mapped_texture;
 mod noise_texture;
+mod operators;
+mod perlin;
+mod scrolling_texture;
 mod solid_color;
+mod voxel_texture;
+mod wood_texture;
+mod worley_texture;
 
+pub use brick_texture::BrickTexture;
 pub use checker_texture::CheckerTexture;
+pub use flipbook_texture::FlipbookTexture;
+pub use fractal_noise::{FractalNoise, NoiseKind};
+pub use gradient_texture::{Gradient, GradientTexture};
 pub use image_texture::ImageTexture;
+pub use mapped_texture::{MappedTexture, TextureMapping, UvTransform};
 pub use noise_texture::NoiseTexture;
+pub use operators::{ColorRamp, MixTexture, MultiplyTexture, RemapTexture};
+pub use scrolling_texture::ScrollingTexture;
 pub use solid_color::SolidColor;
+pub use voxel_texture::VoxelTexture;
+pub use wood_texture::WoodTexture;
+pub use worley_texture::{WorleyFeature, WorleyTexture};
 
-use crate::{color::Color, vec3::Point3};
+use crate::{color::Color, hittable::HitRecord, vec3::Point3};
 
 pub trait Texture: Send + Sync {
     fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+
+    /// The value at `rec`, filtered over the footprint its uv derivatives
+    /// span and at the time of its ray. Unfiltered and still by default.
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        self.value(rec.u, rec.v, &rec.p)
+    }
 }
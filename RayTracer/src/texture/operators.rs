@@ -0,0 +1,191 @@
+//! Textures computed from other textures, for building materials out of
+//! simple parts. Inputs used as scalars are reduced to their luminance.
+
+use super::Texture;
+use crate::{color::Color, hittable::HitRecord, vec3::Point3};
+use std::sync::Arc;
+
+/// Blends `a` into `b` by `factor`, 0 giving `a` and 1 giving `b`.
+pub struct MixTexture {
+    a: Arc<dyn Texture>,
+    b: Arc<dyn Texture>,
+    factor: Arc<dyn Texture>,
+}
+
+impl MixTexture {
+    pub fn new(a: &Arc<dyn Texture>, b: &Arc<dyn Texture>, factor: &Arc<dyn Texture>) -> Self {
+        Self {
+            a: a.clone(),
+            b: b.clone(),
+            factor: factor.clone(),
+        }
+    }
+
+    fn mix(&self, lookup: impl Fn(&Arc<dyn Texture>) -> Color) -> Color {
+        let t = lookup(&self.factor).luminance().clamp(0.0, 1.0);
+        if t == 0.0 {
+            return lookup(&self.a);
+        }
+        if t == 1.0 {
+            return lookup(&self.b);
+        }
+        lookup(&self.a) * (1.0 - t) + lookup(&self.b) * t
+    }
+}
+
+impl Texture for MixTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.mix(|tex| tex.value(u, v, p))
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        self.mix(|tex| tex.value_at(rec))
+    }
+}
+
+/// The componentwise product of two textures, like a color tinted by a mask.
+pub struct MultiplyTexture {
+    a: Arc<dyn Texture>,
+    b: Arc<dyn Texture>,
+}
+
+impl MultiplyTexture {
+    pub fn new(a: &Arc<dyn Texture>, b: &Arc<dyn Texture>) -> Self {
+        Self {
+            a: a.clone(),
+            b: b.clone(),
+        }
+    }
+}
+
+impl Texture for MultiplyTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.a.value(u, v, p).elemul(&self.b.value(u, v, p))
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        self.a.value_at(rec).elemul(&self.b.value_at(rec))
+    }
+}
+
+/// Maps each component of `input` linearly from [`from_min`, `from_max`] to
+/// [`to_min`, `to_max`], clamped to the target range.
+pub struct RemapTexture {
+    input: Arc<dyn Texture>,
+    from_min: f64,
+    from_max: f64,
+    to_min: f64,
+    to_max: f64,
+}
+
+impl RemapTexture {
+    pub fn new(
+        input: &Arc<dyn Texture>,
+        from_min: f64,
+        from_max: f64,
+        to_min: f64,
+        to_max: f64,
+    ) -> Self {
+        assert!(
+            from_min != from_max,
+            "a remap needs a source range of nonzero width"
+        );
+        Self {
+            input: input.clone(),
+            from_min,
+            from_max,
+            to_min,
+            to_max,
+        }
+    }
+
+    fn remap(&self, c: Color) -> Color {
+        let map = |x: f64| {
+            let t = ((x - self.from_min) / (self.from_max - self.from_min)).clamp(0.0, 1.0);
+            self.to_min + (self.to_max - self.to_min) * t
+        };
+        Color::new(map(c.x), map(c.y), map(c.z))
+    }
+}
+
+impl Texture for RemapTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.remap(self.input.value(u, v, p))
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        self.remap(self.input.value_at(rec))
+    }
+}
+
+/// Colors the luminance of `input` by interpolating between stops, and holds
+/// the end colors beyond the first and last stop.
+pub struct ColorRamp {
+    input: Arc<dyn Texture>,
+    /// positions and colors, sorted by position
+    stops: Vec<(f64, Color)>,
+}
+
+impl ColorRamp {
+    pub fn new(input: &Arc<dyn Texture>, stops: &[(f64, Color)]) -> Self {
+        let mut stops = stops.to_vec();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            input: input.clone(),
+            stops,
+        }
+    }
+
+    fn ramp(&self, c: Color) -> Color {
+        Self::interpolate(&self.stops, c.luminance()).unwrap_or(c)
+    }
+
+    /// The color of `stops`, sorted by position, at `x`, `None` without stops.
+    pub fn interpolate(stops: &[(f64, Color)], x: f64) -> Option<Color> {
+        let (first, last) = (stops.first()?, stops.last()?);
+        if x <= first.0 {
+            return Some(first.1);
+        }
+        if x >= last.0 {
+            return Some(last.1);
+        }
+
+        let k = stops.partition_point(|stop| stop.0 <= x) - 1;
+        let (x0, c0) = stops[k];
+        let (x1, c1) = stops[k + 1];
+        let t = (x - x0) / (x1 - x0);
+        Some(c0 * (1.0 - t) + c1 * t)
+    }
+}
+
+impl Texture for ColorRamp {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.ramp(self.input.value(u, v, p))
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        self.ramp(self.input.value_at(rec))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::SolidColor;
+
+    #[test]
+    fn test_remap_clamps_to_target() {
+        let input = Arc::new(SolidColor::new(&Color::new(-1.0, 0.25, 3.0))) as Arc<dyn Texture>;
+        // a reversed source range flips the ramp
+        let remap = RemapTexture::new(&input, 1.0, 0.0, 2.0, 4.0);
+        let c = remap.value(0.0, 0.0, &Point3::zeros());
+        assert_eq!(c, Color::new(4.0, 3.5, 2.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_remap_rejects_empty_range() {
+        let input = Arc::new(SolidColor::new(&Color::zeros())) as Arc<dyn Texture>;
+        RemapTexture::new(&input, 0.5, 0.5, 0.0, 1.0);
+    }
+}
@@ -0,0 +1,41 @@
+use super::Texture;
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    vec3::{Point3, Vec3},
+};
+use std::sync::Arc;
+
+/// `tex` moving with the time of the rays, its solid pattern carried along
+/// `velocity` and its image sliding by `uv_velocity` per unit of time, like
+/// drifting clouds or flowing water.
+pub struct ScrollingTexture {
+    tex: Arc<dyn Texture>,
+    velocity: Vec3,
+    uv_velocity: (f64, f64),
+}
+
+impl ScrollingTexture {
+    pub fn new(tex: &Arc<dyn Texture>, velocity: &Vec3, uv_velocity: (f64, f64)) -> Self {
+        Self {
+            tex: tex.clone(),
+            velocity: *velocity,
+            uv_velocity,
+        }
+    }
+}
+
+impl Texture for ScrollingTexture {
+    /// The texture at time 0.
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.tex.value(u, v, p)
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        let mut moved = rec.clone();
+        moved.p = rec.p - self.velocity * rec.time;
+        moved.u = rec.u - self.uv_velocity.0 * rec.time;
+        moved.v = rec.v - self.uv_velocity.1 * rec.time;
+        self.tex.value_at(&moved)
+    }
+}
@@ -0,0 +1,188 @@
+use super::Texture;
+use crate::{
+    color::Color,
+    texture_cache,
+    vec3::{Point3, Vec3},
+};
+use std::fs;
+
+/// Colors on a regular grid of voxels filling the box from `min` to `max`,
+/// a solid texture interpolated trilinearly between voxel centers and held
+/// at the outer voxels beyond them.
+pub struct VoxelTexture {
+    /// voxels along x, y and z
+    resolution: [usize; 3],
+    /// x varying fastest, then y, then z
+    voxels: Vec<Color>,
+    min: Point3,
+    max: Point3,
+}
+
+impl VoxelTexture {
+    pub fn new(resolution: [usize; 3], voxels: Vec<Color>, min: &Point3, max: &Point3) -> Self {
+        assert!(
+            resolution.iter().all(|&n| n > 0)
+                && voxels.len() == resolution[0] * resolution[1] * resolution[2],
+            "voxel data doesn't match its resolution"
+        );
+        Self {
+            resolution,
+            voxels,
+            min: *min,
+            max: *max,
+        }
+    }
+
+    /// Samples `f` at the voxel centers.
+    pub fn from_fn(
+        resolution: [usize; 3],
+        min: &Point3,
+        max: &Point3,
+        f: impl Fn(&Point3) -> Color,
+    ) -> Self {
+        let size = *max - *min;
+        let mut centers = Vec::with_capacity(resolution[0] * resolution[1] * resolution[2]);
+        for k in 0..resolution[2] {
+            for j in 0..resolution[1] {
+                for i in 0..resolution[0] {
+                    let t = Vec3::new(
+                        (i as f64 + 0.5) / resolution[0] as f64,
+                        (j as f64 + 0.5) / resolution[1] as f64,
+                        (k as f64 + 0.5) / resolution[2] as f64,
+                    );
+                    centers.push(*min + size.elemul(&t));
+                }
+            }
+        }
+        let voxels = centers.iter().map(f).collect();
+        Self::new(resolution, voxels, min, max)
+    }
+
+    /// Loads a grid found along the texture search paths: the resolution
+    /// `nx ny nz`, followed by a gray value or an RGB color per voxel, with
+    /// `#` starting a comment.
+    pub fn load(filename: &str, min: &Point3, max: &Point3) -> Self {
+        let text = texture_cache::resolve(filename).and_then(|path| fs::read_to_string(path).ok());
+        let values: Option<Vec<f64>> = text.map(|text| {
+            text.lines()
+                .map(|line| line.split('#').next().unwrap())
+                .flat_map(str::split_whitespace)
+                .filter_map(|word| word.parse().ok())
+                .collect()
+        });
+
+        if let Some(values) = values.filter(|values| values.len() >= 3) {
+            let resolution = [values[0], values[1], values[2]].map(|n| n.max(0.0) as usize);
+            let count = resolution[0] * resolution[1] * resolution[2];
+            let data = &values[3..];
+            let voxels = if data.len() == count {
+                Some(data.iter().map(|&x| Color::new(x, x, x)).collect())
+            } else if data.len() == 3 * count {
+                Some(
+                    data.chunks(3)
+                        .map(|c| Color::new(c[0], c[1], c[2]))
+                        .collect(),
+                )
+            } else {
+                None
+            };
+            if let Some(voxels) = voxels.filter(|_| count > 0) {
+                return Self::new(resolution, voxels, min, max);
+            }
+        }
+
+        eprintln!("ERROR: Could not load voxel file '{}'.", filename);
+        // the cyan of missing images
+        Self::new([1, 1, 1], vec![Color::new(0.0, 1.0, 1.0)], min, max)
+    }
+
+    fn voxel(&self, i: usize, j: usize, k: usize) -> Color {
+        let [nx, ny, _] = self.resolution;
+        self.voxels[(k * ny + j) * nx + i]
+    }
+}
+
+impl Texture for VoxelTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        // continuous voxel coordinates, with voxel centers at integers
+        let size = self.max - self.min;
+        let local = [
+            (p.x - self.min.x) / size.x,
+            (p.y - self.min.y) / size.y,
+            (p.z - self.min.z) / size.z,
+        ];
+        let mut lower = [0; 3];
+        let mut upper = [0; 3];
+        let mut frac = [0.0; 3];
+        for axis in 0..3 {
+            let n = self.resolution[axis];
+            let x = (local[axis] * n as f64 - 0.5).clamp(0.0, (n - 1) as f64);
+            let x = if x.is_finite() { x } else { 0.0 };
+            lower[axis] = x.floor() as usize;
+            upper[axis] = (lower[axis] + 1).min(n - 1);
+            frac[axis] = x - lower[axis] as f64;
+        }
+
+        let mut sum = Color::zeros();
+        for corner in 0..8 {
+            let pick = |axis: usize| (corner >> axis) & 1 == 1;
+            let weight = (0..3)
+                .map(|axis| {
+                    if pick(axis) {
+                        frac[axis]
+                    } else {
+                        1.0 - frac[axis]
+                    }
+                })
+                .product::<f64>();
+            if weight > 0.0 {
+                let index = |axis: usize| if pick(axis) { upper[axis] } else { lower[axis] };
+                sum += self.voxel(index(0), index(1), index(2)) * weight;
+            }
+        }
+        sum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp() -> VoxelTexture {
+        VoxelTexture::from_fn(
+            [4, 3, 2],
+            &Point3::zeros(),
+            &Point3::new(4.0, 3.0, 2.0),
+            |p| Color::new(p.x, p.y, p.z),
+        )
+    }
+
+    #[test]
+    fn test_interpolates_between_voxel_centers() {
+        let tex = ramp();
+        // a linear field comes back exactly between the centers
+        for p in [
+            Point3::new(0.5, 0.5, 0.5),
+            Point3::new(1.25, 2.0, 1.1),
+            Point3::new(3.5, 1.7, 1.5),
+        ] {
+            assert!((tex.value(0.0, 0.0, &p) - p).length() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_holds_the_outer_voxels() {
+        let tex = ramp();
+        let held = tex.value(0.0, 0.0, &Point3::new(-1.0, 10.0, 0.2));
+        assert!((held - Color::new(0.5, 2.5, 0.5)).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_missing_file_is_cyan() {
+        let tex = VoxelTexture::load("no such voxels.txt", &Point3::zeros(), &Point3::ones());
+        assert_eq!(
+            tex.value(0.0, 0.0, &Point3::zeros()),
+            Color::new(0.0, 1.0, 1.0)
+        );
+    }
+}
@@ -0,0 +1,38 @@
+use super::{perlin::Perlin, Texture};
+use crate::{color::Color, vec3::Point3};
+
+/// Growth rings around the y axis, their circles wobbled by turbulence.
+pub struct WoodTexture {
+    noise: Perlin,
+    /// rings per unit of distance from the axis
+    ring_frequency: f64,
+    /// how far, in rings, the turbulence shifts them
+    distortion: f64,
+    light: Color,
+    dark: Color,
+}
+
+impl WoodTexture {
+    pub fn new(ring_frequency: f64, distortion: f64, light: &Color, dark: &Color) -> Self {
+        Self {
+            noise: Perlin::new(),
+            ring_frequency,
+            distortion,
+            light: *light,
+            dark: *dark,
+        }
+    }
+}
+
+impl Texture for WoodTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let radius = (p.x * p.x + p.z * p.z).sqrt() * self.ring_frequency;
+        let turbulence = self.noise.turb(&(*p * self.ring_frequency), 4);
+        let ring = (radius + self.distortion * turbulence).fract();
+
+        // late wood, the dark band, is thinner than early wood
+        let t = (ring * 2.0 * std::f64::consts::PI).cos() * 0.5 + 0.5;
+        let t = t.powi(4);
+        self.light * (1.0 - t) + self.dark * t
+    }
+}
@@ -0,0 +1,93 @@
+use super::Texture;
+use crate::{color::Color, rtweekend, vec3::Point3};
+
+/// What a `WorleyTexture` reports about the feature points near a lookup.
+#[derive(Clone, Copy, PartialEq)]
+pub enum WorleyFeature {
+    /// distance to the nearest point, dark at the points and bright between
+    F1,
+    /// difference of the two nearest distances, dark along the cell borders
+    F2MinusF1,
+    /// a random gray per cell, like flagstones
+    CellId,
+}
+
+/// Cellular noise (Worley 1996) from one randomly placed feature point per
+/// unit cell, as a gray value in [0, 1].
+pub struct WorleyTexture {
+    scale: f64,
+    feature: WorleyFeature,
+    seed: u64,
+}
+
+impl WorleyTexture {
+    pub fn new(scale: f64, feature: WorleyFeature) -> Self {
+        Self {
+            scale,
+            feature,
+            seed: rtweekend::random_int_in_range(0, i32::MAX) as u64,
+        }
+    }
+
+    /// The feature at `p`, in [0, 1].
+    pub fn evaluate(&self, p: &Point3) -> f64 {
+        let p = *p * self.scale;
+        let cell = [p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64];
+
+        // the two nearest points, and the cell of the nearest, among the
+        // neighbouring cells
+        let (mut f1, mut f2) = (f64::INFINITY, f64::INFINITY);
+        let mut nearest_hash = 0;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let neighbour = [cell[0] + dx, cell[1] + dy, cell[2] + dz];
+                    let hash = self.hash(&neighbour);
+                    let feature = Point3::new(
+                        neighbour[0] as f64 + unit_float(hash),
+                        neighbour[1] as f64 + unit_float(hash >> 21),
+                        neighbour[2] as f64 + unit_float(hash >> 42),
+                    );
+                    let distance = (feature - p).length();
+                    if distance < f1 {
+                        (f1, f2) = (distance, f1);
+                        nearest_hash = hash;
+                    } else if distance < f2 {
+                        f2 = distance;
+                    }
+                }
+            }
+        }
+
+        match self.feature {
+            WorleyFeature::F1 => f1,
+            WorleyFeature::F2MinusF1 => f2 - f1,
+            WorleyFeature::CellId => unit_float(nearest_hash.rotate_left(17)),
+        }
+        .clamp(0.0, 1.0)
+    }
+
+    /// SplitMix64 of the cell coordinates.
+    fn hash(&self, cell: &[i64; 3]) -> u64 {
+        let mut x = self.seed;
+        for &c in cell {
+            x = (x ^ c as u64).wrapping_add(0x9e3779b97f4a7c15);
+            x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+            x ^= x >> 31;
+        }
+        x
+    }
+}
+
+impl Texture for WorleyTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let value = self.evaluate(p);
+        Color::new(value, value, value)
+    }
+}
+
+/// The low 21 bits of `bits` as a number in [0, 1).
+fn unit_float(bits: u64) -> f64 {
+    (bits & 0x1f_ffff) as f64 / (1u64 << 21) as f64
+}
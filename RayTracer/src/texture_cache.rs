@@ -0,0 +1,469 @@
+//! Images shared between the textures that use them, found along a list of
+//! search paths and kept as tiles of working space floats. With a memory
+//! budget, the full resolution level is converted into a temporary tile file
+//! instead, its tiles are read back on first use and the least recently used
+//! ones are dropped when the budget runs out.
+
+use crate::{
+    color::Color,
+    color_space::{self, ColorEncoding, WorkingSpace},
+    mipmap::{MipPyramid, TextureFilter},
+    rtw_image::RtwImage,
+};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, LazyLock, Mutex, RwLock, Weak,
+    },
+};
+
+/// Side of the square tiles images are stored in, in texels.
+const TILE_SIZE: usize = 32;
+const TILE_BYTES: usize = TILE_SIZE * TILE_SIZE * std::mem::size_of::<[f32; 3]>();
+
+static SEARCH_PATHS: LazyLock<RwLock<Vec<PathBuf>>> =
+    LazyLock::new(|| RwLock::new(vec![PathBuf::from("assets")]));
+/// Images decoded differently, or into another working space, are kept apart.
+type CacheKey = (PathBuf, ColorEncoding, WorkingSpace);
+
+static PYRAMIDS: LazyLock<Mutex<HashMap<CacheKey, Weak<MipPyramid>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Filter replacing the one image textures ask for, to compare filters.
+static FILTER_OVERRIDE: RwLock<Option<TextureFilter>> = RwLock::new(None);
+/// The pool of the images loaded through the cache.
+static TILE_POOL: LazyLock<Arc<TilePool>> = LazyLock::new(|| Arc::new(TilePool::new(0)));
+/// Numbers the tile files of this process.
+static TILE_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Lazily loaded tiles of any number of images sharing one memory budget.
+struct TilePool {
+    /// bytes the tiles may take up, 0 for no limit
+    budget: AtomicUsize,
+    resident_bytes: AtomicUsize,
+    /// counts tile loads, stamping tiles with how recently they were used
+    clock: AtomicU64,
+    images: Mutex<Vec<Weak<LazyTiles>>>,
+}
+
+/// Looks up images in `path` after the directories already searched.
+pub fn add_search_path(path: &str) {
+    SEARCH_PATHS.write().unwrap().push(PathBuf::from(path));
+}
+
+/// Samples image textures created from now on with `filter` rather than the
+/// one they ask for, `None` leaving it to them.
+pub fn set_filter_override(filter: Option<TextureFilter>) {
+    *FILTER_OVERRIDE.write().unwrap() = filter;
+}
+
+pub fn filter_override() -> Option<TextureFilter> {
+    *FILTER_OVERRIDE.read().unwrap()
+}
+
+/// The first existing file named `filename` along the search paths, or
+/// `filename` itself when it is absolute and exists.
+pub fn resolve(filename: &str) -> Option<PathBuf> {
+    let filename = Path::new(filename);
+    if filename.is_absolute() {
+        return filename.exists().then(|| filename.to_path_buf());
+    }
+    SEARCH_PATHS
+        .read()
+        .unwrap()
+        .iter()
+        .map(|dir| dir.join(filename))
+        .find(|path| path.exists())
+}
+
+/// Limits the memory of the full resolution level of images loaded from now
+/// on, which then get written to a temporary tile file and read back tile by
+/// tile as lookups reach them. The coarser levels, a third of the size
+/// together, stay in memory. `None` loads images whole.
+pub fn set_memory_budget(bytes: Option<usize>) {
+    TILE_POOL
+        .budget
+        .store(bytes.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// The mip pyramid of `filename` decoded as `encoding`, shared with every
+/// other texture reading the same file alike for as long as one of them is
+/// alive. `None` when the file can't be found or read.
+pub fn load(filename: &str, encoding: ColorEncoding) -> Option<Arc<MipPyramid>> {
+    let Some(path) = resolve(filename) else {
+        eprintln!("ERROR: Could not find image file '{}'.", filename);
+        return None;
+    };
+
+    let key = (path, encoding, color_space::working_space());
+    let mut pyramids = PYRAMIDS.lock().unwrap();
+    if let Some(pyramid) = pyramids.get(&key).and_then(Weak::upgrade) {
+        return Some(pyramid);
+    }
+
+    let image = RtwImage::open_path(&key.0);
+    if image.width() == 0 || image.height() == 0 {
+        return None;
+    }
+    let levels = if TILE_POOL.budget.load(Ordering::Relaxed) > 0 {
+        lazy_levels(&image, encoding)
+    } else {
+        vec![TiledImage::from_image(&image, encoding)]
+    };
+    let pyramid = Arc::new(MipPyramid::new(levels));
+
+    pyramids.retain(|_, pyramid| pyramid.strong_count() > 0);
+    pyramids.insert(key, Arc::downgrade(&pyramid));
+    Some(pyramid)
+}
+
+/// The full resolution level of `image` in a tile file and the level below
+/// in memory, both converted before the decoded image is dropped, or the
+/// full level in memory when the file can't be written.
+fn lazy_levels(image: &RtwImage, encoding: ColorEncoding) -> Vec<TiledImage> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let fetch = |x, y| source_texel(image, encoding, x, y);
+    match TiledImage::lazy(&TILE_POOL, width, height, fetch) {
+        Ok(finest) => vec![finest, TiledImage::downsampled(width, height, fetch)],
+        Err(err) => {
+            eprintln!("ERROR: Could not write a tile file, loading the image whole: {err}.");
+            vec![TiledImage::from_image(image, encoding)]
+        }
+    }
+}
+
+/// Float texels in square tiles, so that neighbouring lookups share
+/// cache lines in both directions.
+pub struct TiledImage {
+    width: usize,
+    height: usize,
+    storage: Storage,
+}
+
+enum Storage {
+    /// all tiles, one after another, each row-major
+    Resident(Vec<[f32; 3]>),
+    Lazy(Arc<LazyTiles>),
+}
+
+/// The texels of a tile, row by row.
+type Tile = Arc<[[f32; 3]]>;
+
+/// Tiles read on demand from a file holding them one after another.
+struct LazyTiles {
+    pool: Arc<TilePool>,
+    path: PathBuf,
+    file: Mutex<File>,
+    tiles: Vec<RwLock<Option<Tile>>>,
+    last_used: Vec<AtomicU64>,
+}
+
+impl TiledImage {
+    pub fn from_fn(width: usize, height: usize, texel: impl Fn(usize, usize) -> Color) -> Self {
+        let (tiles_x, tiles_y) = (width.div_ceil(TILE_SIZE), height.div_ceil(TILE_SIZE));
+        let mut texels = Vec::with_capacity(tiles_x * tiles_y * TILE_SIZE * TILE_SIZE);
+        for tile_y in 0..tiles_y {
+            for tile_x in 0..tiles_x {
+                texels.extend(tile_texels(tile_x, tile_y, width, height, &texel));
+            }
+        }
+
+        Self {
+            width,
+            height,
+            storage: Storage::Resident(texels),
+        }
+    }
+
+    fn from_image(image: &RtwImage, encoding: ColorEncoding) -> Self {
+        Self::from_fn(image.width() as usize, image.height() as usize, |x, y| {
+            source_texel(image, encoding, x, y)
+        })
+    }
+
+    /// Writes the tiles out to a temporary file, leaving them to be read back
+    /// into `pool` as lookups reach them.
+    fn lazy(
+        pool: &Arc<TilePool>,
+        width: usize,
+        height: usize,
+        texel: impl Fn(usize, usize) -> Color,
+    ) -> io::Result<Self> {
+        let (tiles_x, tiles_y) = (width.div_ceil(TILE_SIZE), height.div_ceil(TILE_SIZE));
+        let path = std::env::temp_dir().join(format!(
+            "ray_tracer_tiles_{}_{}.bin",
+            std::process::id(),
+            TILE_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let written = (|| {
+            let mut writer = BufWriter::new(File::create(&path)?);
+            for tile_y in 0..tiles_y {
+                for tile_x in 0..tiles_x {
+                    for texel in tile_texels(tile_x, tile_y, width, height, &texel) {
+                        for channel in texel {
+                            writer.write_all(&channel.to_le_bytes())?;
+                        }
+                    }
+                }
+            }
+            writer.flush()?;
+            File::open(&path)
+        })();
+        let file = match written {
+            Ok(file) => file,
+            Err(err) => {
+                let _ = fs::remove_file(&path);
+                return Err(err);
+            }
+        };
+
+        let tile_count = tiles_x * tiles_y;
+        let tiles = Arc::new(LazyTiles {
+            pool: pool.clone(),
+            path,
+            file: Mutex::new(file),
+            tiles: (0..tile_count).map(|_| RwLock::new(None)).collect(),
+            last_used: (0..tile_count).map(|_| AtomicU64::new(0)).collect(),
+        });
+        pool.images.lock().unwrap().push(Arc::downgrade(&tiles));
+
+        Ok(Self {
+            width,
+            height,
+            storage: Storage::Lazy(tiles),
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Texel (`x`, `y`), which must lie within the image.
+    pub fn texel(&self, x: usize, y: usize) -> Color {
+        let tiles_x = self.width.div_ceil(TILE_SIZE);
+        let tile = (y / TILE_SIZE) * tiles_x + x / TILE_SIZE;
+        let offset = (y % TILE_SIZE) * TILE_SIZE + x % TILE_SIZE;
+        let [r, g, b] = match &self.storage {
+            Storage::Resident(texels) => texels[tile * TILE_SIZE * TILE_SIZE + offset],
+            Storage::Lazy(tiles) => tiles.tile(tile)[offset],
+        };
+        Color::new(r as f64, g as f64, b as f64)
+    }
+
+    /// Half the size, rounded up, each texel the average of up to four.
+    pub fn downsample(&self) -> Self {
+        Self::downsampled(self.width, self.height, |x, y| self.texel(x, y))
+    }
+
+    /// Half of the `width` by `height` image with texels `texel`.
+    fn downsampled(width: usize, height: usize, texel: impl Fn(usize, usize) -> Color) -> Self {
+        Self::from_fn(width.div_ceil(2), height.div_ceil(2), |x, y| {
+            let mut sum = Color::zeros();
+            let mut count = 0.0;
+            for (sx, sy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let (px, py) = (2 * x + sx, 2 * y + sy);
+                if px < width && py < height {
+                    sum += texel(px, py);
+                    count += 1.0;
+                }
+            }
+            sum / count
+        })
+    }
+}
+
+impl LazyTiles {
+    fn tile(&self, index: usize) -> Tile {
+        let pool = &self.pool;
+        self.last_used[index].store(pool.clock.load(Ordering::Relaxed), Ordering::Relaxed);
+        if let Some(tile) = &*self.tiles[index].read().unwrap() {
+            return tile.clone();
+        }
+
+        let texels = self.read_tile(index);
+        let resident = {
+            let mut slot = self.tiles[index].write().unwrap();
+            // another thread may have loaded it meanwhile
+            if let Some(tile) = &*slot {
+                return tile.clone();
+            }
+            // counted before it is published, so an eviction taking it right
+            // after can't subtract it first
+            let resident = pool.resident_bytes.fetch_add(TILE_BYTES, Ordering::Relaxed);
+            *slot = Some(texels.clone());
+            resident + TILE_BYTES
+        };
+
+        self.last_used[index].store(
+            pool.clock.fetch_add(1, Ordering::Relaxed) + 1,
+            Ordering::Relaxed,
+        );
+        let budget = pool.budget.load(Ordering::Relaxed);
+        if resident > budget && budget > 0 {
+            pool.evict_tiles(budget - budget / 8);
+        }
+        texels
+    }
+
+    fn read_tile(&self, index: usize) -> Tile {
+        let mut bytes = vec![0; TILE_BYTES];
+        let read = {
+            let mut file = self.file.lock().unwrap();
+            file.seek(SeekFrom::Start((index * TILE_BYTES) as u64))
+                .and_then(|_| file.read_exact(&mut bytes))
+        };
+        if let Err(err) = read {
+            eprintln!("ERROR: Could not read tile {index} back: {err}.");
+            return vec![[0.0; 3]; TILE_SIZE * TILE_SIZE].into();
+        }
+
+        bytes
+            .chunks_exact(std::mem::size_of::<[f32; 3]>())
+            .map(|texel| {
+                let channel =
+                    |i: usize| f32::from_le_bytes(texel[4 * i..4 * i + 4].try_into().unwrap());
+                [channel(0), channel(1), channel(2)]
+            })
+            .collect()
+    }
+
+    fn resident_tiles(&self) -> usize {
+        self.tiles
+            .iter()
+            .filter(|tile| tile.read().unwrap().is_some())
+            .count()
+    }
+}
+
+impl Drop for LazyTiles {
+    fn drop(&mut self) {
+        self.pool
+            .resident_bytes
+            .fetch_sub(self.resident_tiles() * TILE_BYTES, Ordering::Relaxed);
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl TilePool {
+    fn new(budget: usize) -> Self {
+        Self {
+            budget: AtomicUsize::new(budget),
+            resident_bytes: AtomicUsize::new(0),
+            clock: AtomicU64::new(0),
+            images: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Drops the least recently used tiles of all images until at most
+    /// `target` bytes remain. Tiles still in use by lookups stay alive until
+    /// they finish.
+    fn evict_tiles(&self, target: usize) {
+        let mut images = self.images.lock().unwrap();
+        images.retain(|image| image.strong_count() > 0);
+        let images: Vec<_> = images.iter().filter_map(Weak::upgrade).collect();
+
+        let mut candidates: Vec<(u64, usize, usize)> = images
+            .iter()
+            .enumerate()
+            .flat_map(|(image_index, image)| {
+                image
+                    .tiles
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, tile)| tile.read().unwrap().is_some())
+                    .map(move |(tile_index, _)| {
+                        let stamp = image.last_used[tile_index].load(Ordering::Relaxed);
+                        (stamp, image_index, tile_index)
+                    })
+            })
+            .collect();
+        candidates.sort_unstable();
+
+        for (_, image_index, tile_index) in candidates {
+            if self.resident_bytes.load(Ordering::Relaxed) <= target {
+                break;
+            }
+            if images[image_index].tiles[tile_index]
+                .write()
+                .unwrap()
+                .take()
+                .is_some()
+            {
+                self.resident_bytes.fetch_sub(TILE_BYTES, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// The texels of tile (`tile_x`, `tile_y`) row by row, padded with black
+/// past the edges of the image.
+fn tile_texels(
+    tile_x: usize,
+    tile_y: usize,
+    width: usize,
+    height: usize,
+    texel: impl Fn(usize, usize) -> Color,
+) -> impl Iterator<Item = [f32; 3]> {
+    (0..TILE_SIZE * TILE_SIZE).map(move |offset| {
+        let x = tile_x * TILE_SIZE + offset % TILE_SIZE;
+        let y = tile_y * TILE_SIZE + offset / TILE_SIZE;
+        if x < width && y < height {
+            let c = texel(x, y);
+            [c.x as f32, c.y as f32, c.z as f32]
+        } else {
+            [0.0; 3]
+        }
+    })
+}
+
+/// Pixel (`x`, `y`) of `image` in the working space.
+fn source_texel(image: &RtwImage, encoding: ColorEncoding, x: usize, y: usize) -> Color {
+    let color_scale = 1.0 / 255.0;
+    let (x, y) = (x as u32, y as u32);
+    let pixel = if encoding == ColorEncoding::Alpha {
+        [image.alpha(x, y); 3]
+    } else {
+        image.pixel_data(x, y)
+    };
+    encoding.decode(&(Color::new(pixel[0] as f64, pixel[1] as f64, pixel[2] as f64) * color_scale))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lazy_tiles_match_resident_within_budget() {
+        let texel = |x: usize, y: usize| Color::new(x as f64, y as f64, 0.5);
+        let (width, height) = (100, 70);
+        let resident = TiledImage::from_fn(width, height, texel);
+        // a pool of its own, so other tests loading images don't share it
+        let pool = Arc::new(TilePool::new(2 * TILE_BYTES));
+        let lazy = TiledImage::lazy(&pool, width, height, texel).unwrap();
+
+        for y in 0..height {
+            for x in 0..width {
+                assert_eq!(lazy.texel(x, y), resident.texel(x, y));
+                assert!(pool.resident_bytes.load(Ordering::Relaxed) <= 3 * TILE_BYTES);
+            }
+        }
+
+        let Storage::Lazy(tiles) = &lazy.storage else {
+            panic!("expected lazy storage");
+        };
+        let path = tiles.path.clone();
+        assert!(path.exists());
+        drop(lazy);
+        assert!(!path.exists());
+        assert_eq!(pool.resident_bytes.load(Ordering::Relaxed), 0);
+    }
+}
@@ -424,14 +424,14 @@ mod tests {
 
     #[test]
     fn test_squared_length() {
-        assert_eq!(Vec3::new(1.0, 2.0, 3.0).squared_length(), 14.0 as f64);
+        assert_eq!(Vec3::new(1.0, 2.0, 3.0).squared_length(), 14.0);
     }
 
     #[test]
     fn test_length() {
         assert_eq!(
             Vec3::new(3.0, 4.0, 5.0).length(),
-            ((3.0 * 3.0 + 4.0 * 4.0 + 5.0 * 5.0) as f64).sqrt()
+            (3.0 * 3.0 + 4.0 * 4.0 + 5.0_f64 * 5.0).sqrt()
         );
     }
 